@@ -0,0 +1,63 @@
+use crate::{analysis::StepResponse, system::Sample};
+
+// Tracking costs take the input of each sample as the reference the output
+// should follow, as for a closed loop. They integrate with the trapezoidal
+// rule over the sample instants.
+
+fn integrate<Input>(samples: &[Sample<Input, f64>], f: impl Fn(f64, f64) -> f64) -> f64
+where
+    Input: Copy + Into<f64>,
+{
+    let value = |s: &Sample<Input, f64>| f(s.instant, s.input.into() - s.output);
+    samples
+        .windows(2)
+        .map(|w| 0.5 * (w[1].instant - w[0].instant) * (value(&w[0]) + value(&w[1])))
+        .sum()
+}
+
+/// The integral of the squared tracking error.
+pub fn ise<Input: Copy + Into<f64>>(samples: &[Sample<Input, f64>]) -> f64 {
+    integrate(samples, |_, e| e * e)
+}
+
+/// The integral of the absolute tracking error.
+pub fn iae<Input: Copy + Into<f64>>(samples: &[Sample<Input, f64>]) -> f64 {
+    integrate(samples, |_, e| e.abs())
+}
+
+/// The integral of the time-weighted absolute tracking error, which
+/// penalizes slow settling more than the initial transient.
+pub fn itae<Input: Copy + Into<f64>>(samples: &[Sample<Input, f64>]) -> f64 {
+    integrate(samples, |t, e| t * e.abs())
+}
+
+/// Overshoot in percent beyond `allowed`, or zero, meant to be weighted and
+/// added to a tracking cost.
+pub fn overshoot_penalty<Input>(samples: &[Sample<Input, f64>], allowed: f64) -> f64 {
+    (StepResponse::from_samples(samples).overshoot - allowed).max(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracking_costs() {
+        // An error of 1 - t/2 over two seconds
+        let samples: Vec<Sample<f64, f64>> = (0..=200)
+            .map(|i| {
+                let instant = i as f64 * 0.01;
+                Sample {
+                    instant,
+                    input: 1.0,
+                    output: instant / 2.0,
+                }
+            })
+            .collect();
+
+        assert!((ise(&samples) - 2.0 / 3.0).abs() < 1e-4);
+        assert!((iae(&samples) - 1.0).abs() < 1e-9);
+        assert!((itae(&samples) - 2.0 / 3.0).abs() < 1e-4);
+        assert_eq!(overshoot_penalty(&samples, 5.0), 0.0);
+    }
+}
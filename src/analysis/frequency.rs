@@ -0,0 +1,473 @@
+use std::f64::consts::PI;
+
+use nalgebra::Complex;
+
+use crate::system::System;
+
+/// A frequency response estimated from data
+#[derive(Clone, Debug)]
+pub struct FrequencyResponse {
+    /// Angular frequencies, in rad/s.
+    pub frequencies: Vec<f64>,
+    /// The complex gain from the input to the output at each frequency.
+    pub response: Vec<Complex<f64>>,
+    /// How much of the output is explained linearly by the input at each
+    /// frequency, from zero to one, for responses estimated from data. Noise
+    /// and nonlinearity lower it.
+    pub coherence: Option<Vec<f64>>,
+}
+
+impl FrequencyResponse {
+    pub fn magnitude(&self) -> Vec<f64> {
+        self.response.iter().map(|h| h.norm()).collect()
+    }
+
+    /// Phases in radians, unwrapped along increasing frequency.
+    pub fn phase(&self) -> Vec<f64> {
+        let mut phases: Vec<f64> = Vec::with_capacity(self.response.len());
+        for h in &self.response {
+            let phase = h.arg();
+            phases.push(match phases.last() {
+                Some(previous) => phase + 2.0 * PI * ((previous - phase) / (2.0 * PI)).round(),
+                None => phase,
+            });
+        }
+        phases
+    }
+}
+
+/// Tapers applied to each segment before its transform.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Window {
+    Rectangular,
+    #[default]
+    Hann,
+}
+
+impl Window {
+    fn weights(self, length: usize) -> Vec<f64> {
+        match self {
+            Window::Rectangular => vec![1.0; length],
+            Window::Hann => (0..length)
+                .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f64 / length as f64).cos())
+                .collect(),
+        }
+    }
+}
+
+/// In-place radix-2 fast Fourier transform, for power-of-two lengths.
+fn fft(values: &mut [Complex<f64>]) {
+    let n = values.len();
+
+    // Bit-reversed order
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            values.swap(i, j);
+        }
+    }
+
+    let mut length = 2;
+    while length <= n {
+        let twiddle = Complex::from_polar(1.0, -2.0 * PI / length as f64);
+        for start in (0..n).step_by(length) {
+            let mut w = Complex::new(1.0, 0.0);
+            for k in 0..length / 2 {
+                let even = values[start + k];
+                let odd = values[start + k + length / 2] * w;
+                values[start + k] = even + odd;
+                values[start + k + length / 2] = even - odd;
+                w *= twiddle;
+            }
+        }
+        length <<= 1;
+    }
+}
+
+/// Estimates the frequency response between two recorded signals by
+/// Welch's method
+///
+/// The signals are split into overlapping segments, each detrended,
+/// windowed and transformed. Averaging over the segments gives the cross
+/// spectrum $S_{uy}$ and the auto spectra $S_{uu}$ and $S_{yy}$, from which
+/// the H1 estimate $S_{uy} / S_{uu}$ is unbiased by output noise, and the
+/// coherence is $|S_{uy}|^2 / (S_{uu} S_{yy})$. Frequencies the input has no
+/// power at cannot be estimated, and get a zero response and coherence.
+#[derive(Clone, Debug)]
+pub struct Welch {
+    segment: usize,
+    overlap: f64,
+    window: Window,
+}
+
+impl Welch {
+    /// Creates an estimator with segments of `segment` samples, a power of
+    /// two, overlapping by half with a Hann window.
+    pub fn new(segment: usize) -> Self {
+        assert!(
+            segment >= 4 && segment.is_power_of_two(),
+            "Segment length must be a power of two, at least 4"
+        );
+        Self {
+            segment,
+            overlap: 0.5,
+            window: Window::default(),
+        }
+    }
+
+    /// Sets the fraction of each segment shared with the next one.
+    pub fn overlap(mut self, overlap: f64) -> Self {
+        assert!((0.0..1.0).contains(&overlap), "Overlap must be in [0, 1)");
+        self.overlap = overlap;
+        self
+    }
+
+    pub fn window(mut self, window: Window) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Estimates the response from `inputs` to `outputs` sampled every
+    /// `timestep`, at each frequency resolved by a segment except zero.
+    ///
+    /// As with `System::simulate`, each output is taken at the end of the
+    /// step its input is held over. Returns `None` if the signals are
+    /// shorter than a segment.
+    pub fn estimate(
+        &self,
+        inputs: &[f64],
+        outputs: &[f64],
+        timestep: f64,
+    ) -> Option<FrequencyResponse> {
+        assert_eq!(
+            inputs.len(),
+            outputs.len(),
+            "There must be one output per input"
+        );
+        if inputs.len() < self.segment {
+            return None;
+        }
+
+        let n = self.segment;
+        let bins = n / 2;
+        let window = self.window.weights(n);
+        let hop = ((n as f64 * (1.0 - self.overlap)).round() as usize).max(1);
+
+        let transform = |signal: &[f64]| {
+            let mean = signal.iter().sum::<f64>() / n as f64;
+            let mut values: Vec<Complex<f64>> = signal
+                .iter()
+                .zip(&window)
+                .map(|(x, w)| Complex::new((x - mean) * w, 0.0))
+                .collect();
+            fft(&mut values);
+            values
+        };
+
+        let mut suu = vec![0.0; bins];
+        let mut syy = vec![0.0; bins];
+        let mut suy = vec![Complex::new(0.0, 0.0); bins];
+        for start in (0..=inputs.len() - n).step_by(hop) {
+            let u = transform(&inputs[start..start + n]);
+            let y = transform(&outputs[start..start + n]);
+            for k in 0..bins {
+                suu[k] += u[k + 1].norm_sqr();
+                syy[k] += y[k + 1].norm_sqr();
+                suy[k] += u[k + 1].conj() * y[k + 1];
+            }
+        }
+
+        Some(FrequencyResponse {
+            frequencies: (1..=bins)
+                .map(|k| 2.0 * PI * k as f64 / (n as f64 * timestep))
+                .collect(),
+            response: suy
+                .iter()
+                .zip(&suu)
+                .map(|(uy, &uu)| {
+                    if uu > 0.0 {
+                        uy / uu
+                    } else {
+                        Complex::new(0.0, 0.0)
+                    }
+                })
+                .collect(),
+            coherence: Some(
+                (0..bins)
+                    .map(|k| {
+                        let power = suu[k] * syy[k];
+                        if power > 0.0 {
+                            suy[k].norm_sqr() / power
+                        } else {
+                            0.0
+                        }
+                    })
+                    .collect(),
+            ),
+        })
+    }
+}
+
+/// Measures the frequency response of a plant by exciting it with one
+/// sinusoid at a time
+///
+/// At each frequency, a fresh plant is driven with `offset + amplitude *
+/// sin(ω t)` until it settles, then the fundamental of its output is
+/// extracted by correlation over whole cycles. For a nonlinear plant this
+/// is its describing function around the offset, and the coherence is the
+/// share of the output variance in the fundamental, which harmonics lower.
+#[derive(Clone, Debug)]
+pub struct SteppedSine {
+    frequencies: Vec<f64>,
+    amplitude: f64,
+    offset: f64,
+    timestep: f64,
+    settling_cycles: f64,
+    settling_time: f64,
+    cycles: usize,
+}
+
+impl SteppedSine {
+    /// Creates an experiment at angular `frequencies`, in rad/s.
+    pub fn new(frequencies: Vec<f64>, amplitude: f64) -> Self {
+        assert!(
+            frequencies.iter().all(|&w| w > 0.0),
+            "Frequencies must be positive"
+        );
+        assert!(amplitude > 0.0, "Amplitude must be positive");
+        Self {
+            frequencies,
+            amplitude,
+            offset: 0.0,
+            timestep: 0.01,
+            settling_cycles: 5.0,
+            settling_time: 0.0,
+            cycles: 10,
+        }
+    }
+
+    /// Sets the input the sinusoid is centred on.
+    pub fn offset(mut self, offset: f64) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Sets the largest step, which is shortened to keep at least 20 steps
+    /// per cycle.
+    pub fn timestep(mut self, timestep: f64) -> Self {
+        assert!(timestep > 0.0, "Timestep must be positive");
+        self.timestep = timestep;
+        self
+    }
+
+    /// Sets how long to wait before measuring, as the longer of a number of
+    /// cycles and a duration.
+    pub fn settling(mut self, cycles: f64, time: f64) -> Self {
+        self.settling_cycles = cycles;
+        self.settling_time = time;
+        self
+    }
+
+    /// Sets the number of cycles the output is correlated over.
+    pub fn cycles(mut self, cycles: usize) -> Self {
+        assert!(cycles > 0, "At least one cycle must be measured");
+        self.cycles = cycles;
+        self
+    }
+
+    /// Runs the experiment on plants from `build`, one per frequency.
+    pub fn run<Sys>(&self, mut build: impl FnMut() -> Sys) -> FrequencyResponse
+    where
+        Sys: System<Input = f64, Output = f64>,
+    {
+        let mut response = Vec::with_capacity(self.frequencies.len());
+        let mut coherence = Vec::with_capacity(self.frequencies.len());
+
+        for &omega in &self.frequencies {
+            let period = 2.0 * PI / omega;
+            let dt = self.timestep.min(period / 20.0);
+            let settling =
+                ((self.settling_cycles * period).max(self.settling_time) / dt).ceil() as usize;
+            let measured = (self.cycles as f64 * period / dt).round() as usize;
+
+            let mut plant = build();
+            let mut samples = Vec::with_capacity(measured);
+            for k in 0..=settling + measured {
+                let time = k as f64 * dt;
+                // Each input holds over the step before it, so take the
+                // sinusoid at its middle
+                let phase = omega * (time - 0.5 * dt);
+                let input = if k == 0 {
+                    self.offset
+                } else {
+                    self.offset + self.amplitude * phase.sin()
+                };
+                plant.update(time, &input);
+                if k > settling {
+                    samples.push((time, plant.get_output(time)));
+                }
+            }
+
+            let count = samples.len() as f64;
+            let mean = samples.iter().map(|(_, y)| y).sum::<f64>() / count;
+            let (mut output, mut reference) = (Complex::new(0.0, 0.0), Complex::new(0.0, 0.0));
+            let mut variance = 0.0;
+            for &(time, y) in &samples {
+                let rotation = Complex::from_polar(2.0 / count, -omega * time);
+                output += rotation * (y - mean);
+                reference += rotation * (self.amplitude * (omega * time).sin());
+                variance += (y - mean).powi(2) / count;
+            }
+
+            response.push(output / reference);
+            coherence.push(if variance > 0.0 {
+                (0.5 * output.norm_sqr() / variance).min(1.0)
+            } else {
+                0.0
+            });
+        }
+
+        FrequencyResponse {
+            frequencies: self.frequencies.clone(),
+            response,
+            coherence: Some(coherence),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        linear::Fopdt,
+        system::{lookup::LookupTable1D, series::SeriesSystem},
+        utils::Rng,
+    };
+
+    #[test]
+    fn test_fft_matches_dft() {
+        let signal: Vec<f64> = (0..16).map(|i| ((i * 5) % 7) as f64 - 3.0).collect();
+        let mut values: Vec<Complex<f64>> = signal.iter().map(|&x| Complex::new(x, 0.0)).collect();
+        fft(&mut values);
+
+        for (k, value) in values.iter().enumerate() {
+            let dft: Complex<f64> = signal
+                .iter()
+                .enumerate()
+                .map(|(i, &x)| Complex::from_polar(x, -2.0 * PI * (i * k) as f64 / 16.0))
+                .sum();
+            assert!((value - dft).norm() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_stepped_sine_matches_analytic_response() {
+        let model = Fopdt::new(2.0, 1.0, 0.2);
+        let frequencies = vec![0.1, 1.0, 10.0];
+        let measured = SteppedSine::new(frequencies.clone(), 0.5)
+            .settling(5.0, 10.0)
+            .run(|| model.clone());
+
+        let (magnitude, phase) = (measured.magnitude(), measured.phase());
+        for (i, &omega) in frequencies.iter().enumerate() {
+            let (expected_magnitude, expected_phase) = model.frequency_response(omega);
+            assert!((magnitude[i] / expected_magnitude - 1.0).abs() < 1e-2);
+            assert!((phase[i] - expected_phase).abs() < 1e-2);
+            assert!(measured.coherence.as_ref().unwrap()[i] > 0.999);
+        }
+    }
+
+    #[test]
+    fn test_stepped_sine_describing_function() {
+        // A unit saturation driven at an amplitude of 3, then a slow lag
+        let plant = || {
+            SeriesSystem::new(
+                LookupTable1D::new(vec![-1.0, 1.0], vec![-1.0, 1.0]),
+                Fopdt::new(1.0, 1.0, 0.0),
+            )
+        };
+        let measured = SteppedSine::new(vec![0.1], 3.0).run(plant);
+
+        // N(A) = 2/π (asin(1/A) + sqrt(1 - 1/A²) / A)
+        let a: f64 = 3.0;
+        let gain = 2.0 / PI * ((1.0 / a).asin() + (1.0 - 1.0 / (a * a)).sqrt() / a);
+        let lag = Fopdt::new(1.0, 1.0, 0.0).frequency_response(0.1).0;
+        assert!((measured.magnitude()[0] / (gain * lag) - 1.0).abs() < 1e-2);
+        // The clipped sine is rich in harmonics
+        assert!(measured.coherence.as_ref().unwrap()[0] < 0.95);
+    }
+
+    #[test]
+    fn test_welch_estimate_from_random_excitation() {
+        let dt = 0.01;
+        let mut rng = Rng::new(7);
+        let inputs: Vec<f64> = (0..20000).map(|_| rng.normal()).collect();
+
+        let model = Fopdt::new(2.0, 1.0, 0.2);
+        let mut plant = model.clone();
+        let outputs: Vec<f64> = inputs
+            .iter()
+            .enumerate()
+            .map(|(k, u)| {
+                let time = k as f64 * dt;
+                plant.update(time, u);
+                plant.get_output(time)
+            })
+            .collect();
+        let noisy: Vec<f64> = outputs.iter().map(|y| y + 0.5 * rng.normal()).collect();
+
+        let welch = Welch::new(2048);
+        let estimate = welch.estimate(&inputs, &outputs, dt).unwrap();
+        let (magnitude, phase) = (estimate.magnitude(), estimate.phase());
+        for (i, &omega) in estimate.frequencies.iter().enumerate() {
+            if !(0.5..10.0).contains(&omega) {
+                continue;
+            }
+            let (expected_magnitude, expected_phase) = model.frequency_response(omega);
+            assert!((magnitude[i] / expected_magnitude - 1.0).abs() < 0.05);
+            assert!((phase[i] - expected_phase).abs() < 0.08);
+            assert!(estimate.coherence.as_ref().unwrap()[i] > 0.95);
+        }
+
+        // Output noise lowers the coherence where the plant rolls off
+        let estimate = welch.estimate(&inputs, &noisy, dt).unwrap();
+        let at = |omega: f64| {
+            let i = estimate
+                .frequencies
+                .iter()
+                .position(|&w| w >= omega)
+                .unwrap();
+            estimate.coherence.as_ref().unwrap()[i]
+        };
+        assert!(at(0.5) > 0.9);
+        assert!(at(50.0) < 0.5);
+        assert!(
+            welch
+                .estimate(&inputs[..100], &outputs[..100], dt)
+                .is_none()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Timestep must be positive")]
+    fn test_stepped_sine_timestep() {
+        SteppedSine::new(vec![1.0], 1.0).timestep(0.0);
+    }
+
+    #[test]
+    fn test_welch_without_input_power() {
+        // A constant input is removed with the mean of each segment
+        let estimate = Welch::new(16)
+            .estimate(&[1.0; 64], &[2.0; 64], 0.1)
+            .unwrap();
+
+        assert!(estimate.response.iter().all(|h| h.norm() == 0.0));
+        assert!(estimate.coherence.unwrap().iter().all(|&c| c == 0.0));
+    }
+}
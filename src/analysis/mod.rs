@@ -0,0 +1,170 @@
+use std::{
+    fmt::Write,
+    fs, io,
+    path::Path,
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+};
+
+use crate::{
+    system::{Sample, System},
+    utils::Param,
+};
+
+pub mod cost;
+pub mod frequency;
+pub mod monte_carlo;
+pub mod response;
+pub mod sweep;
+
+pub use self::{
+    frequency::{FrequencyResponse, SteppedSine, Welch, Window},
+    monte_carlo::{Distribution, MonteCarlo, MonteCarloResult, Parameters, Statistics},
+    response::StepResponse,
+    sweep::{Sweep, SweepResult},
+};
+
+/// A named CSV column, computed from the metric of each run.
+pub type Column<'a, Metric> = (&'a str, fn(&Metric) -> f64);
+
+/// The outcome of one run of a `MonteCarlo` simulation or a `Sweep`.
+#[derive(Clone, Debug)]
+pub struct Run<Metric> {
+    pub parameters: Parameters,
+    pub metric: Metric,
+}
+
+/// The outcome of all the runs of a `MonteCarlo` simulation or a `Sweep`,
+/// in the order their parameter sets were listed.
+#[derive(Clone, Debug)]
+pub struct RunResults<Metric> {
+    pub runs: Vec<Run<Metric>>,
+}
+
+impl<Metric> RunResults<Metric> {
+    /// Statistics of `value` over all runs.
+    pub fn statistics(&self, value: impl Fn(&Metric) -> f64) -> Statistics {
+        let values: Vec<f64> = self.runs.iter().map(|r| value(&r.metric)).collect();
+        Statistics::of(&values)
+    }
+
+    /// The run for which `value` is the smallest, ignoring NaNs.
+    pub fn minimum(&self, value: impl Fn(&Metric) -> f64) -> Option<&Run<Metric>> {
+        self.runs
+            .iter()
+            .map(|run| (value(&run.metric), run))
+            .filter(|(v, _)| !v.is_nan())
+            .min_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, run)| run)
+    }
+
+    /// Formats the runs as CSV, with one row per run, the run index and
+    /// parameter values first, then one column per entry of `columns`.
+    pub fn to_csv(&self, columns: &[Column<Metric>]) -> String {
+        let mut csv = String::from("run");
+        if let Some(first) = self.runs.first() {
+            for (name, _) in first.parameters.values() {
+                write!(csv, ",{name}").unwrap();
+            }
+        }
+        for (name, _) in columns {
+            write!(csv, ",{name}").unwrap();
+        }
+        csv.push('\n');
+
+        for run in &self.runs {
+            write!(csv, "{}", run.parameters.run).unwrap();
+            for (_, value) in run.parameters.values() {
+                write!(csv, ",{value}").unwrap();
+            }
+            for (_, column) in columns {
+                write!(csv, ",{}", column(&run.metric)).unwrap();
+            }
+            csv.push('\n');
+        }
+
+        csv
+    }
+
+    /// Writes the runs to a CSV file, as formatted by [`Self::to_csv`].
+    pub fn write_csv(&self, path: impl AsRef<Path>, columns: &[Column<Metric>]) -> io::Result<()> {
+        fs::write(path, self.to_csv(columns))
+    }
+}
+
+/// Applies `f` to every item on up to `threads` threads, returning the
+/// results in item order.
+pub(crate) fn evaluate_in_parallel<Item: Sync, Output: Send>(
+    items: &[Item],
+    threads: usize,
+    f: impl Fn(&Item) -> Output + Sync,
+) -> Vec<Output> {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new((0..items.len()).map(|_| None).collect::<Vec<_>>());
+
+    thread::scope(|scope| {
+        for _ in 0..threads.min(items.len()) {
+            scope.spawn(|| {
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(i) else {
+                        break;
+                    };
+                    let output = f(item);
+                    results.lock().unwrap()[i] = Some(output);
+                }
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(Option::unwrap)
+        .collect()
+}
+
+/// Evaluates `run` on every parameter set of `sets` on up to `threads` threads.
+pub(crate) fn evaluate_runs<Metric: Send>(
+    sets: Vec<Parameters>,
+    threads: usize,
+    run: impl Fn(&Parameters) -> Metric + Sync,
+) -> RunResults<Metric> {
+    let metrics = evaluate_in_parallel(&sets, threads, run);
+
+    let runs = sets
+        .into_iter()
+        .zip(metrics)
+        .map(|(parameters, metric)| Run { parameters, metric })
+        .collect();
+
+    RunResults { runs }
+}
+
+/// A run that builds a system from its parameter set with `build`, simulates
+/// it for `total_time` with `input`, and applies `metric` to its samples.
+pub(crate) fn simulation<Sys, Metric>(
+    total_time: f64,
+    max_timestep: f64,
+    input: Param<Sys::Input>,
+    build: impl Fn(&Parameters) -> Sys + Sync,
+    metric: impl Fn(&[Sample<Sys::Input, Sys::Output>]) -> Metric + Sync,
+) -> impl Fn(&Parameters) -> Metric + Sync
+where
+    Sys: System,
+    Sys::Input: Clone + Sync,
+    Sys::Output: Clone,
+{
+    move |parameters| {
+        let mut system = build(parameters);
+        let mut samples = Vec::new();
+        system.simulate(total_time, max_timestep, input.clone(), &mut |s| {
+            samples.push(s)
+        });
+        metric(&samples)
+    }
+}
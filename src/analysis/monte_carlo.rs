@@ -0,0 +1,328 @@
+use std::thread;
+
+use crate::{
+    analysis::{RunResults, evaluate_runs, simulation},
+    system::{Sample, System},
+    utils::{Param, Rng},
+};
+
+/// A distribution parameters are drawn from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Distribution {
+    Constant(f64),
+    Uniform {
+        low: f64,
+        high: f64,
+    },
+    Normal {
+        mean: f64,
+        std_dev: f64,
+    },
+    /// A normal distribution restricted to `[low, high]` by rejection.
+    TruncatedNormal {
+        mean: f64,
+        std_dev: f64,
+        low: f64,
+        high: f64,
+    },
+    /// Values whose logarithm follows `Normal { mean, std_dev }`.
+    LogNormal {
+        mean: f64,
+        std_dev: f64,
+    },
+}
+
+impl Distribution {
+    pub fn sample(&self, rng: &mut Rng) -> f64 {
+        match *self {
+            Self::Constant(value) => value,
+            Self::Uniform { low, high } => rng.range(low, high),
+            Self::Normal { mean, std_dev } => mean + std_dev * rng.normal(),
+            Self::TruncatedNormal {
+                mean,
+                std_dev,
+                low,
+                high,
+            } => {
+                assert!(low < high, "Truncation bounds must be ordered");
+                loop {
+                    let value = mean + std_dev * rng.normal();
+                    if (low..=high).contains(&value) {
+                        break value;
+                    }
+                }
+            }
+            Self::LogNormal { mean, std_dev } => (mean + std_dev * rng.normal()).exp(),
+        }
+    }
+}
+
+/// The parameters drawn for one run.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Parameters {
+    /// Index of the run.
+    pub run: usize,
+    /// A seed drawn for the run, e.g. for its noise sources.
+    pub seed: u64,
+    values: Vec<(String, f64)>,
+}
+
+impl Parameters {
+    pub(crate) fn new(run: usize, seed: u64, values: Vec<(String, f64)>) -> Self {
+        Self { run, seed, values }
+    }
+
+    /// The value drawn for parameter `name`.
+    pub fn get(&self, name: &str) -> f64 {
+        self.values
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| *v)
+            .unwrap_or_else(|| panic!("Unknown parameter `{name}`"))
+    }
+
+    /// The values drawn, in the order the parameters were declared.
+    pub fn values(&self) -> &[(String, f64)] {
+        &self.values
+    }
+}
+
+/// Summary statistics of a set of values.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Statistics {
+    pub count: usize,
+    pub mean: f64,
+    /// Sample standard deviation, zero for fewer than two values.
+    pub std_dev: f64,
+    pub min: f64,
+    pub max: f64,
+    pub median: f64,
+}
+
+impl Statistics {
+    pub fn of(values: &[f64]) -> Self {
+        assert!(!values.is_empty(), "Statistics need at least one value");
+
+        let count = values.len();
+        let mean = values.iter().sum::<f64>() / count as f64;
+        let std_dev = if count > 1 {
+            (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (count - 1) as f64).sqrt()
+        } else {
+            0.0
+        };
+
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let median = if count % 2 == 1 {
+            sorted[count / 2]
+        } else {
+            0.5 * (sorted[count / 2 - 1] + sorted[count / 2])
+        };
+
+        Self {
+            count,
+            mean,
+            std_dev,
+            min: sorted[0],
+            max: sorted[count - 1],
+            median,
+        }
+    }
+}
+
+/// The outcome of all the runs of a Monte Carlo simulation, in run order.
+pub type MonteCarloResult<Metric> = RunResults<Metric>;
+
+/// Simulates many copies of a system with randomly drawn parameters
+///
+/// All parameter sets are drawn up front from a single seed, so results do
+/// not depend on how runs are spread over threads.
+#[derive(Clone, Debug)]
+pub struct MonteCarlo {
+    runs: usize,
+    seed: u64,
+    threads: usize,
+    parameters: Vec<(String, Distribution)>,
+}
+
+impl MonteCarlo {
+    pub fn new(runs: usize, seed: u64) -> Self {
+        Self {
+            runs,
+            seed,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            parameters: Vec::new(),
+        }
+    }
+
+    /// Declares parameter `name`, drawn from `distribution` for each run.
+    pub fn parameter(mut self, name: &str, distribution: Distribution) -> Self {
+        self.parameters.push((name.to_owned(), distribution));
+        self
+    }
+
+    /// Sets the number of threads, which defaults to the available parallelism.
+    pub fn threads(mut self, threads: usize) -> Self {
+        assert!(threads > 0, "At least one thread is needed");
+        self.threads = threads;
+        self
+    }
+
+    /// Draws the parameters of every run.
+    pub fn parameter_sets(&self) -> Vec<Parameters> {
+        let mut rng = Rng::new(self.seed);
+
+        (0..self.runs)
+            .map(|run| {
+                let seed = rng.next_u64();
+                let values = self
+                    .parameters
+                    .iter()
+                    .map(|(name, distribution)| (name.clone(), distribution.sample(&mut rng)))
+                    .collect();
+                Parameters::new(run, seed, values)
+            })
+            .collect()
+    }
+
+    /// Evaluates `run` on every parameter set, in parallel.
+    pub fn evaluate<Metric: Send>(
+        &self,
+        run: impl Fn(&Parameters) -> Metric + Sync,
+    ) -> MonteCarloResult<Metric> {
+        evaluate_runs(self.parameter_sets(), self.threads, run)
+    }
+
+    /// Builds a system from each parameter set with `build`, simulates it for
+    /// `total_time` with `input`, and applies `metric` to its samples.
+    pub fn run<Sys, Metric>(
+        &self,
+        total_time: f64,
+        max_timestep: f64,
+        input: Param<Sys::Input>,
+        build: impl Fn(&Parameters) -> Sys + Sync,
+        metric: impl Fn(&[Sample<Sys::Input, Sys::Output>]) -> Metric + Sync,
+    ) -> MonteCarloResult<Metric>
+    where
+        Sys: System,
+        Sys::Input: Clone + Sync,
+        Sys::Output: Clone,
+        Metric: Send,
+    {
+        self.evaluate(simulation(total_time, max_timestep, input, build, metric))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        analysis::StepResponse,
+        continuous::{ContinuousSystem, integrator::RungeKutta4},
+        linear::StateSpace,
+        system::{noise::WhiteNoise, parallel::ParallelSystem, routing::Adapter},
+    };
+    use nalgebra::{dmatrix, dvector};
+
+    #[test]
+    fn test_distributions() {
+        let mut rng = Rng::new(1);
+        let uniform: Vec<f64> = (0..10_000)
+            .map(|_| {
+                Distribution::Uniform {
+                    low: 1.0,
+                    high: 2.0,
+                }
+                .sample(&mut rng)
+            })
+            .collect();
+        let truncated: Vec<f64> = (0..10_000)
+            .map(|_| {
+                Distribution::TruncatedNormal {
+                    mean: 0.0,
+                    std_dev: 1.0,
+                    low: -0.5,
+                    high: 2.0,
+                }
+                .sample(&mut rng)
+            })
+            .collect();
+
+        assert!((Statistics::of(&uniform).mean - 1.5).abs() < 0.01);
+        assert!(truncated.iter().all(|v| (-0.5..=2.0).contains(v)));
+        assert_eq!(Distribution::Constant(3.0).sample(&mut rng), 3.0);
+    }
+
+    #[test]
+    fn test_statistics() {
+        let statistics = Statistics::of(&[4.0, 1.0, 3.0, 2.0]);
+
+        assert_eq!(statistics.mean, 2.5);
+        assert_eq!(statistics.median, 2.5);
+        assert_eq!((statistics.min, statistics.max), (1.0, 4.0));
+        assert!((statistics.std_dev - (5.0f64 / 3.0).sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_results_do_not_depend_on_threads() {
+        let monte_carlo = MonteCarlo::new(50, 3).parameter(
+            "k",
+            Distribution::Normal {
+                mean: 1.0,
+                std_dev: 0.1,
+            },
+        );
+
+        let square = |p: &Parameters| p.get("k").powi(2) + p.seed as f64;
+        let single = monte_carlo.clone().threads(1).evaluate(square);
+        let multi = monte_carlo.threads(4).evaluate(square);
+
+        assert_eq!(single.runs.len(), 50);
+        for (a, b) in single.runs.iter().zip(&multi.runs) {
+            assert_eq!(a.parameters, b.parameters);
+            assert_eq!(a.metric, b.metric);
+        }
+    }
+
+    #[test]
+    fn test_step_responses_with_perturbed_plant() {
+        // A first-order lag whose time constant is uncertain, measured with noise
+        let result = MonteCarlo::new(40, 7)
+            .parameter(
+                "tau",
+                Distribution::Uniform {
+                    low: 0.5,
+                    high: 1.5,
+                },
+            )
+            .run(
+                15.0,
+                0.01,
+                Param::new(1.0),
+                |p| {
+                    let tau = p.get("tau");
+                    let plant = StateSpace::new(
+                        dmatrix![-1.0 / tau],
+                        dmatrix![1.0 / tau],
+                        dmatrix![1.0],
+                        dmatrix![0.0],
+                    )
+                    .max_timestep(0.01)
+                    .initial_state(dvector![0.0])
+                    .with_integrator(RungeKutta4);
+                    ParallelSystem::new(Adapter::siso(plant), WhiteNoise::new(1e-9, 0.01, p.seed))
+                },
+                StepResponse::from_samples,
+            );
+
+        // The rise time of a first-order lag is tau ln(9)
+        for run in &result.runs {
+            let expected = run.parameters.get("tau") * 9f64.ln();
+            assert!((run.metric.rise_time.unwrap() - expected).abs() < 0.03);
+        }
+
+        let rise = result.statistics(|m| m.rise_time.unwrap());
+        assert!((rise.mean - 9f64.ln()).abs() < 0.15);
+        assert!(rise.min >= 0.5 * 9f64.ln() - 0.03 && rise.max <= 1.5 * 9f64.ln() + 0.03);
+    }
+}
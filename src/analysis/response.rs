@@ -0,0 +1,123 @@
+use crate::system::Sample;
+
+/// Characteristics of a step response
+///
+/// The response is assumed to start at its initial value and to have
+/// settled by its last sample, which is taken as the final value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StepResponse {
+    pub initial_value: f64,
+    pub final_value: f64,
+    /// The furthest value in the direction of the step.
+    pub peak: f64,
+    pub peak_time: f64,
+    /// How far the peak goes past the final value, in percent of the step.
+    pub overshoot: f64,
+    /// Time taken to go from 10% to 90% of the step, if it gets there.
+    pub rise_time: Option<f64>,
+    /// Instant after which the response stays within the settling band
+    /// around the final value, if it ever leaves it.
+    pub settling_time: Option<f64>,
+}
+
+impl StepResponse {
+    /// Analyzes `values` sampled at `times`, with a settling band of 2%.
+    pub fn new(times: &[f64], values: &[f64]) -> Self {
+        Self::with_band(times, values, 0.02)
+    }
+
+    /// Analyzes `values` sampled at `times`, with a settling band of `band`
+    /// times the step.
+    pub fn with_band(times: &[f64], values: &[f64], band: f64) -> Self {
+        assert_eq!(
+            times.len(),
+            values.len(),
+            "There must be one value per instant"
+        );
+        assert!(!values.is_empty(), "A response needs at least one sample");
+
+        let initial_value = values[0];
+        let final_value = values[values.len() - 1];
+        let step = final_value - initial_value;
+        let direction = if step < 0.0 { -1.0 } else { 1.0 };
+
+        let (peak_index, &peak) = values
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| (direction * *a).total_cmp(&(direction * *b)))
+            .unwrap();
+
+        let overshoot = if step == 0.0 {
+            0.0
+        } else {
+            ((peak - final_value) / step * 100.0).max(0.0)
+        };
+
+        let crossing = |fraction: f64| {
+            let level = initial_value + fraction * step;
+            values
+                .iter()
+                .position(|&v| direction * (v - level) >= 0.0)
+                .map(|i| times[i])
+        };
+        let rise_time = match (crossing(0.1), crossing(0.9)) {
+            (Some(start), Some(end)) if step != 0.0 => Some(end - start),
+            _ => None,
+        };
+
+        let tolerance = band * step.abs();
+        let settling_time = values
+            .iter()
+            .rposition(|&v| (v - final_value).abs() > tolerance)
+            .map(|i| times[(i + 1).min(times.len() - 1)]);
+
+        Self {
+            initial_value,
+            final_value,
+            peak,
+            peak_time: times[peak_index],
+            overshoot,
+            rise_time,
+            settling_time,
+        }
+    }
+
+    /// Analyzes the outputs of simulation samples, with a settling band of 2%.
+    pub fn from_samples<Input>(samples: &[Sample<Input, f64>]) -> Self {
+        let times: Vec<f64> = samples.iter().map(|s| s.instant).collect();
+        let values: Vec<f64> = samples.iter().map(|s| s.output).collect();
+        Self::new(&times, &values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_order_response() {
+        let times: Vec<f64> = (0..=1000).map(|i| i as f64 * 0.01).collect();
+        let values: Vec<f64> = times.iter().map(|t| 2.0 * (1.0 - (-t).exp())).collect();
+        let response = StepResponse::new(&times, &values);
+
+        // ln(9) for the rise time and about ln(50) for the settling time
+        assert!((response.rise_time.unwrap() - 9f64.ln()).abs() < 0.02);
+        assert!((response.settling_time.unwrap() - 50f64.ln()).abs() < 0.02);
+        assert_eq!(response.overshoot, 0.0);
+        assert!((response.final_value - 2.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_underdamped_downward_response() {
+        // A step from 1 to 0 that undershoots to -0.2
+        let times = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0];
+        let values = [1.0, 0.5, -0.2, 0.1, 0.0, 0.0];
+        let response = StepResponse::new(&times, &values);
+
+        assert_eq!(response.peak, -0.2);
+        assert_eq!(response.peak_time, 2.0);
+        assert!((response.overshoot - 20.0).abs() < 1e-12);
+        assert_eq!(response.rise_time, Some(1.0));
+        assert_eq!(response.settling_time, Some(4.0));
+    }
+}
@@ -0,0 +1,219 @@
+use std::thread;
+
+use crate::{
+    analysis::{Parameters, RunResults, evaluate_runs, simulation},
+    system::{Sample, System},
+    utils::{Param, Rng},
+};
+
+/// The outcome of all the runs of a sweep, in grid order.
+pub type SweepResult<Metric> = RunResults<Metric>;
+
+/// Simulates a system for every combination of parameter values on a grid
+///
+/// The first parameter declared varies the slowest. Each run also gets a
+/// seed, drawn from the sweep's own seed, so that noisy systems differ
+/// between runs but the sweep stays reproducible.
+#[derive(Clone, Debug)]
+pub struct Sweep {
+    seed: u64,
+    threads: usize,
+    parameters: Vec<(String, Vec<f64>)>,
+}
+
+impl Default for Sweep {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sweep {
+    pub fn new() -> Self {
+        Self {
+            seed: 0,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            parameters: Vec::new(),
+        }
+    }
+
+    /// Declares parameter `name`, taking each of `values` in turn.
+    pub fn parameter(mut self, name: &str, values: impl IntoIterator<Item = f64>) -> Self {
+        let values: Vec<f64> = values.into_iter().collect();
+        assert!(!values.is_empty(), "A parameter needs at least one value");
+        self.parameters.push((name.to_owned(), values));
+        self
+    }
+
+    /// Declares parameter `name`, taking `count` evenly spaced values from
+    /// `first` to `last`.
+    pub fn linear(self, name: &str, first: f64, last: f64, count: usize) -> Self {
+        self.parameter(name, spaced(first, last, count))
+    }
+
+    /// Declares parameter `name`, taking `count` logarithmically spaced
+    /// values from `first` to `last`.
+    pub fn logarithmic(self, name: &str, first: f64, last: f64, count: usize) -> Self {
+        assert!(
+            first > 0.0 && last > 0.0,
+            "Logarithmic values must be positive"
+        );
+        self.parameter(name, spaced(first.ln(), last.ln(), count).map(f64::exp))
+    }
+
+    /// Sets the seed the runs' seeds are drawn from, which defaults to zero.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Sets the number of threads, which defaults to the available parallelism.
+    pub fn threads(mut self, threads: usize) -> Self {
+        assert!(threads > 0, "At least one thread is needed");
+        self.threads = threads;
+        self
+    }
+
+    /// Lists every combination of parameter values.
+    pub fn parameter_sets(&self) -> Vec<Parameters> {
+        let count = self.parameters.iter().map(|(_, v)| v.len()).product();
+        let mut rng = Rng::new(self.seed);
+
+        (0..count)
+            .map(|run| {
+                // Decompose the run index, the last parameter varying the fastest
+                let mut rest = run;
+                let mut values: Vec<(String, f64)> = self
+                    .parameters
+                    .iter()
+                    .rev()
+                    .map(|(name, values)| {
+                        let value = values[rest % values.len()];
+                        rest /= values.len();
+                        (name.clone(), value)
+                    })
+                    .collect();
+                values.reverse();
+                Parameters::new(run, rng.next_u64(), values)
+            })
+            .collect()
+    }
+
+    /// Evaluates `run` on every parameter set, in parallel.
+    pub fn evaluate<Metric: Send>(
+        &self,
+        run: impl Fn(&Parameters) -> Metric + Sync,
+    ) -> SweepResult<Metric> {
+        evaluate_runs(self.parameter_sets(), self.threads, run)
+    }
+
+    /// Builds a system from each parameter set with `build`, simulates it for
+    /// `total_time` with `input`, and applies `metric` to its samples.
+    pub fn run<Sys, Metric>(
+        &self,
+        total_time: f64,
+        max_timestep: f64,
+        input: Param<Sys::Input>,
+        build: impl Fn(&Parameters) -> Sys + Sync,
+        metric: impl Fn(&[Sample<Sys::Input, Sys::Output>]) -> Metric + Sync,
+    ) -> SweepResult<Metric>
+    where
+        Sys: System,
+        Sys::Input: Clone + Sync,
+        Sys::Output: Clone,
+        Metric: Send,
+    {
+        self.evaluate(simulation(total_time, max_timestep, input, build, metric))
+    }
+}
+
+fn spaced(first: f64, last: f64, count: usize) -> impl Iterator<Item = f64> {
+    assert!(count > 0, "A parameter needs at least one value");
+    let step = if count > 1 {
+        (last - first) / (count - 1) as f64
+    } else {
+        0.0
+    };
+    (0..count).map(move |i| first + step * i as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        analysis::StepResponse,
+        continuous::{ContinuousSystem, integrator::RungeKutta4},
+        linear::StateSpace,
+        system::routing::Adapter,
+    };
+    use nalgebra::{dmatrix, dvector};
+
+    #[test]
+    fn test_grid_order() {
+        let sweep = Sweep::new()
+            .parameter("a", [1.0, 2.0])
+            .linear("b", 0.0, 1.0, 3)
+            .logarithmic("c", 1.0, 100.0, 3);
+        let sets = sweep.parameter_sets();
+
+        assert_eq!(sets.len(), 18);
+        assert_eq!(sets[0].values()[0], ("a".to_owned(), 1.0));
+        assert!((sets[1].get("c") - 10.0).abs() < 1e-9);
+        assert_eq!(sets[3].get("b"), 0.5);
+        assert_eq!(sets[9].get("a"), 2.0);
+        assert_eq!(sets[17].get("b"), 1.0);
+        assert!((sets[17].get("c") - 100.0).abs() < 1e-9);
+        assert_eq!(sets, sweep.threads(2).parameter_sets());
+    }
+
+    #[test]
+    fn test_csv_export() {
+        let result = Sweep::new()
+            .parameter("x", [1.0, 2.0])
+            .parameter("y", [0.5])
+            .evaluate(|p| (p.get("x") * p.get("y"), p.get("x") + p.get("y")));
+
+        let csv = result.to_csv(&[("product", |m| m.0), ("sum", |m| m.1)]);
+        assert_eq!(csv, "run,x,y,product,sum\n0,1,0.5,0.5,1.5\n1,2,0.5,1,2.5\n");
+    }
+
+    #[test]
+    fn test_sweep_plant_time_constants() {
+        // First-order lags of varying gain and time constant
+        let result = Sweep::new()
+            .parameter("gain", [1.0, 2.0])
+            .linear("tau", 0.5, 1.5, 3)
+            .run(
+                10.0,
+                0.01,
+                Param::new(1.0),
+                |p| {
+                    let tau = p.get("tau");
+                    let plant = StateSpace::new(
+                        dmatrix![-1.0 / tau],
+                        dmatrix![p.get("gain") / tau],
+                        dmatrix![1.0],
+                        dmatrix![0.0],
+                    )
+                    .max_timestep(0.01)
+                    .initial_state(dvector![0.0])
+                    .with_integrator(RungeKutta4);
+                    Adapter::siso(plant)
+                },
+                StepResponse::from_samples,
+            );
+
+        assert_eq!(result.runs.len(), 6);
+        for run in &result.runs {
+            let expected = run.parameters.get("tau") * 9f64.ln();
+            assert!((run.metric.rise_time.unwrap() - expected).abs() < 0.02);
+            assert!((run.metric.final_value - run.parameters.get("gain")).abs() < 1e-2);
+        }
+
+        let fastest = result.minimum(|m| m.rise_time.unwrap()).unwrap();
+        assert_eq!(fastest.parameters.get("tau"), 0.5);
+
+        let csv = result.to_csv(&[("rise_time", |m| m.rise_time.unwrap())]);
+        assert_eq!(csv.lines().count(), 7);
+        assert!(csv.starts_with("run,gain,tau,rise_time\n0,1,0.5,"));
+    }
+}
@@ -17,6 +17,15 @@ pub trait ContinuousSystem<Input, State, Output> {
 
     fn max_timestep(&self) -> f64;
 
+    /// Records the input held over the step about to be integrated, for
+    /// systems whose output depends on it.
+    fn set_input(&mut self, _input: &Input) {}
+
+    /// Whether `get_output` depends on the input last given to `set_input`.
+    fn has_feedthrough(&self) -> bool {
+        false
+    }
+
     fn with_integrator<Int>(
         self,
         integrator: Int,
@@ -29,6 +38,7 @@ pub trait ContinuousSystem<Input, State, Output> {
             system: self,
             integrator,
             last_time: 0.0,
+            previous: None,
             _dummy: PhantomData,
         }
     }
@@ -38,6 +48,8 @@ pub struct IntegratedSystem<Sys, Int, Input, State, Output> {
     system: Sys,
     integrator: Int,
     last_time: f64,
+    /// The instant and state the last step started from.
+    previous: Option<(f64, State)>,
     _dummy: PhantomData<(Input, State, Output)>,
 }
 
@@ -45,15 +57,27 @@ impl<Sys, Int, Input, State, Output> System for IntegratedSystem<Sys, Int, Input
 where
     Sys: ContinuousSystem<Input, State, Output>,
     Int: Integrator<Sys, Input, State, Output>,
+    State: Clone,
 {
     type Input = Input;
     type Output = Output;
 
     fn update(&mut self, time: f64, input: &Input) -> f64 {
         let max_dt = self.system.max_timestep();
+
+        // Updating again at the same instant redoes the last step
+        if let Some((start, state)) = &self.previous
+            && time == self.last_time
+        {
+            self.system.set_state(state);
+            self.last_time = *start;
+        }
+
         let dt = time - self.last_time;
+        self.previous = Some((self.last_time, self.system.state().clone()));
         self.last_time = time;
 
+        self.system.set_input(input);
         self.integrator.integrate(&mut self.system, time, dt, input);
 
         time + max_dt
@@ -62,6 +86,10 @@ where
     fn get_output(&self, time: f64) -> Output {
         self.system.get_output(time)
     }
+
+    fn has_feedthrough(&self) -> bool {
+        self.system.has_feedthrough()
+    }
 }
 
 pub struct PureIntegrator<Data> {
@@ -122,6 +150,7 @@ where
             system: PureIntegrator::new(max_timestep),
             integrator,
             last_time: 0.0,
+            previous: None,
             _dummy: PhantomData,
         })
     }
@@ -0,0 +1,11 @@
+pub mod mpc;
+pub mod pid;
+pub mod pid_tuning;
+pub mod tuning;
+
+pub use self::{
+    mpc::{ModelPredictiveController, MpcReport, MpcState},
+    pid::Pid,
+    pid_tuning::{PidStructure, RelayExperiment, UltimatePoint},
+    tuning::{GainTuner, TunedGains},
+};
@@ -0,0 +1,468 @@
+use nalgebra::{DMatrix, DVector};
+
+use crate::{
+    discrete::DiscreteSystem,
+    linear::DiscreteStateSpace,
+    optimization::qp::{AdmmSolver, QpSolution, QpStatus, QuadraticProgram},
+};
+
+/// Solver outcome of a single controller step.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MpcReport {
+    pub time: f64,
+    pub status: QpStatus,
+    pub iterations: usize,
+}
+
+/// State carried by a `ModelPredictiveController` between samples.
+#[derive(Clone, Debug)]
+pub struct MpcState {
+    /// The input applied on the last sample.
+    pub input: DVector<f64>,
+    /// The last QP solution, used to warm start the next one.
+    pub solution: Option<QpSolution>,
+    /// The solver outcome of the last sample.
+    pub report: Option<MpcReport>,
+}
+
+/// Lower and upper limits applied on every step of the horizon.
+#[derive(Clone, Debug)]
+struct Bounds {
+    lower: DVector<f64>,
+    upper: DVector<f64>,
+}
+
+/// A linear model predictive controller
+///
+/// Each sample, it minimizes
+///
+/// $$\sum_{k=1}^{N} (y_k - r)^T Q (y_k - r) + \sum_{k=0}^{N_c - 1} u_k^T R u_k + \Delta u_k^T S \Delta u_k$$
+///
+/// over the next $N_c$ inputs (held constant up to the prediction horizon
+/// $N$), subject to input, state and input rate limits, with $y = C x$. The
+/// resulting dense QP is solved with an `AdmmSolver`, warm started from the
+/// previous sample.
+///
+/// Its input is the current plant state and the output reference stacked as
+/// $[x; r]$, and its output is the first optimal input $u_0$. The last step's
+/// solver status is kept in `report`.
+pub struct ModelPredictiveController {
+    plant: DiscreteStateSpace,
+    horizon: usize,
+    control_horizon: usize,
+    output_weight: DMatrix<f64>,
+    input_weight: DMatrix<f64>,
+    rate_weight: DMatrix<f64>,
+    input_limits: Option<Bounds>,
+    state_limits: Option<Bounds>,
+    rate_limits: Option<Bounds>,
+    solver: AdmmSolver,
+    state: MpcState,
+}
+
+impl ModelPredictiveController {
+    /// Creates a controller for `plant` predicting `horizon` samples ahead,
+    /// with identity output weights and zero input weights.
+    pub fn new(plant: &DiscreteStateSpace, horizon: usize) -> Self {
+        assert!(horizon > 0, "Horizon must be at least one sample");
+        let (m, p) = (plant.inputs(), plant.outputs());
+
+        Self {
+            plant: plant.clone(),
+            horizon,
+            control_horizon: horizon,
+            output_weight: DMatrix::identity(p, p),
+            input_weight: DMatrix::zeros(m, m),
+            rate_weight: DMatrix::zeros(m, m),
+            input_limits: None,
+            state_limits: None,
+            rate_limits: None,
+            solver: AdmmSolver::default(),
+            state: MpcState {
+                input: DVector::zeros(m),
+                solution: None,
+                report: None,
+            },
+        }
+    }
+
+    /// Sets how many future inputs are optimized; the last one is held until the end of the horizon.
+    pub fn control_horizon(mut self, control_horizon: usize) -> Self {
+        assert!(
+            (1..=self.horizon).contains(&control_horizon),
+            "Control horizon must be between one and the prediction horizon"
+        );
+        self.control_horizon = control_horizon;
+        self
+    }
+
+    /// Sets the output tracking weight $Q$ and the input weight $R$.
+    pub fn weights(mut self, output_weight: DMatrix<f64>, input_weight: DMatrix<f64>) -> Self {
+        let (m, p) = (self.plant.inputs(), self.plant.outputs());
+        assert_eq!(
+            output_weight.shape(),
+            (p, p),
+            "Q must be square with one row per output"
+        );
+        assert_eq!(
+            input_weight.shape(),
+            (m, m),
+            "R must be square with one row per input"
+        );
+        self.output_weight = output_weight;
+        self.input_weight = input_weight;
+        self
+    }
+
+    /// Sets the input rate weight $S$.
+    pub fn rate_weight(mut self, rate_weight: DMatrix<f64>) -> Self {
+        let m = self.plant.inputs();
+        assert_eq!(
+            rate_weight.shape(),
+            (m, m),
+            "S must be square with one row per input"
+        );
+        self.rate_weight = rate_weight;
+        self
+    }
+
+    pub fn input_limits(mut self, lower: DVector<f64>, upper: DVector<f64>) -> Self {
+        assert_eq!(
+            lower.len(),
+            self.plant.inputs(),
+            "Input limits have the wrong dimension"
+        );
+        assert_eq!(
+            upper.len(),
+            self.plant.inputs(),
+            "Input limits have the wrong dimension"
+        );
+        self.input_limits = Some(Bounds { lower, upper });
+        self
+    }
+
+    pub fn state_limits(mut self, lower: DVector<f64>, upper: DVector<f64>) -> Self {
+        assert_eq!(
+            lower.len(),
+            self.plant.states(),
+            "State limits have the wrong dimension"
+        );
+        assert_eq!(
+            upper.len(),
+            self.plant.states(),
+            "State limits have the wrong dimension"
+        );
+        self.state_limits = Some(Bounds { lower, upper });
+        self
+    }
+
+    /// Limits the change of the input between consecutive samples.
+    pub fn rate_limits(mut self, lower: DVector<f64>, upper: DVector<f64>) -> Self {
+        assert_eq!(
+            lower.len(),
+            self.plant.inputs(),
+            "Rate limits have the wrong dimension"
+        );
+        assert_eq!(
+            upper.len(),
+            self.plant.inputs(),
+            "Rate limits have the wrong dimension"
+        );
+        self.rate_limits = Some(Bounds { lower, upper });
+        self
+    }
+
+    pub fn solver(mut self, solver: AdmmSolver) -> Self {
+        self.solver = solver;
+        self
+    }
+
+    /// The solver outcome of the last step, if any step was taken.
+    pub fn report(&self) -> Option<&MpcReport> {
+        self.state.report.as_ref()
+    }
+
+    /// Predicted states $X = \Phi x_0 + \Gamma U$ over the horizon.
+    fn prediction(&self) -> (DMatrix<f64>, DMatrix<f64>) {
+        let (n, m) = (self.plant.states(), self.plant.inputs());
+        let (horizon, control) = (self.horizon, self.control_horizon);
+        let a = &self.plant.a;
+        let b = &self.plant.b;
+
+        let mut phi = DMatrix::zeros(horizon * n, n);
+        let mut gamma = DMatrix::zeros(horizon * n, control * m);
+        let mut power = DMatrix::identity(n, n);
+        let mut impulse = Vec::with_capacity(horizon);
+
+        for k in 0..horizon {
+            impulse.push(&power * b);
+            power = a * &power;
+            phi.view_mut((k * n, 0), (n, n)).copy_from(&power);
+        }
+
+        for k in 0..horizon {
+            for j in 0..=k {
+                let column = j.min(control - 1);
+                let mut block = gamma.view_mut((k * n, column * m), (n, m));
+                block += &impulse[k - j];
+            }
+        }
+
+        (phi, gamma)
+    }
+
+    /// Difference operator $\Delta U = D U - E u_{-1}$, returning $D$.
+    fn difference(&self) -> DMatrix<f64> {
+        let m = self.plant.inputs();
+        let size = self.control_horizon * m;
+        let mut d = DMatrix::identity(size, size);
+        for k in 1..self.control_horizon {
+            d.view_mut((k * m, (k - 1) * m), (m, m))
+                .copy_from(&-DMatrix::identity(m, m));
+        }
+        d
+    }
+
+    /// Builds the QP for initial state `x0`, output reference `reference`
+    /// and previously applied input `previous`.
+    fn problem(
+        &self,
+        x0: &DVector<f64>,
+        reference: &DVector<f64>,
+        previous: &DVector<f64>,
+    ) -> QuadraticProgram {
+        let m = self.plant.inputs();
+        let (horizon, control) = (self.horizon, self.control_horizon);
+
+        let (phi, gamma) = self.prediction();
+        let c_bar = block_diagonal(&self.plant.c, horizon);
+        let q_bar = block_diagonal(&self.output_weight, horizon);
+        let r_bar = block_diagonal(&self.input_weight, control);
+        let s_bar = block_diagonal(&self.rate_weight, control);
+        let d = self.difference();
+
+        let g = &c_bar * &gamma;
+        let free = &c_bar * &phi * x0 - stack(reference, horizon);
+        let mut e = DVector::zeros(control * m);
+        e.rows_mut(0, m).copy_from(previous);
+
+        let hessian = (g.transpose() * &q_bar * &g + &r_bar + d.transpose() * &s_bar * &d) * 2.0;
+        let linear = (g.transpose() * &q_bar * &free - d.transpose() * &s_bar * &e) * 2.0;
+
+        let mut rows: Vec<DMatrix<f64>> = Vec::new();
+        let mut lower: Vec<DVector<f64>> = Vec::new();
+        let mut upper: Vec<DVector<f64>> = Vec::new();
+
+        if let Some(limits) = &self.input_limits {
+            rows.push(DMatrix::identity(control * m, control * m));
+            lower.push(stack(&limits.lower, control));
+            upper.push(stack(&limits.upper, control));
+        }
+        if let Some(limits) = &self.rate_limits {
+            rows.push(d.clone());
+            lower.push(stack(&limits.lower, control) + &e);
+            upper.push(stack(&limits.upper, control) + &e);
+        }
+        if let Some(limits) = &self.state_limits {
+            let free_state = &phi * x0;
+            rows.push(gamma.clone());
+            lower.push(stack(&limits.lower, horizon) - &free_state);
+            upper.push(stack(&limits.upper, horizon) - &free_state);
+        }
+
+        let constraints: usize = rows.iter().map(|r| r.nrows()).sum();
+        let mut a = DMatrix::zeros(constraints, control * m);
+        let mut offset = 0;
+        for row in &rows {
+            a.view_mut((offset, 0), row.shape()).copy_from(row);
+            offset += row.nrows();
+        }
+
+        QuadraticProgram {
+            p: hessian,
+            q: linear,
+            a,
+            lower: concat(&lower),
+            upper: concat(&upper),
+        }
+    }
+}
+
+fn block_diagonal(block: &DMatrix<f64>, count: usize) -> DMatrix<f64> {
+    let (r, c) = block.shape();
+    let mut result = DMatrix::zeros(r * count, c * count);
+    for k in 0..count {
+        result.view_mut((k * r, k * c), (r, c)).copy_from(block);
+    }
+    result
+}
+
+fn stack(v: &DVector<f64>, count: usize) -> DVector<f64> {
+    DVector::from_iterator(v.len() * count, (0..count).flat_map(|_| v.iter().copied()))
+}
+
+fn concat(parts: &[DVector<f64>]) -> DVector<f64> {
+    DVector::from_iterator(
+        parts.iter().map(|p| p.len()).sum(),
+        parts.iter().flat_map(|p| p.iter().copied()),
+    )
+}
+
+impl DiscreteSystem<DVector<f64>, MpcState, DVector<f64>> for ModelPredictiveController {
+    fn next_state(&self, time: f64, state: &MpcState, input: &DVector<f64>) -> MpcState {
+        let (n, m, p) = (
+            self.plant.states(),
+            self.plant.inputs(),
+            self.plant.outputs(),
+        );
+        assert_eq!(
+            input.len(),
+            n + p,
+            "Controller input must stack the state and the reference"
+        );
+
+        let x0 = input.rows(0, n).into_owned();
+        let reference = input.rows(n, p).into_owned();
+
+        let problem = self.problem(&x0, &reference, &state.input);
+        let solution = self.solver.solve(&problem, state.solution.as_ref());
+
+        MpcState {
+            input: solution.x.rows(0, m).into_owned(),
+            report: Some(MpcReport {
+                time,
+                status: solution.status,
+                iterations: solution.iterations,
+            }),
+            solution: Some(solution),
+        }
+    }
+
+    fn get_output(&self) -> DVector<f64> {
+        self.state.input.clone()
+    }
+
+    fn state(&self) -> &MpcState {
+        &self.state
+    }
+
+    fn set_state(&mut self, new_state: &MpcState) {
+        self.state = new_state.clone();
+    }
+
+    fn timestep(&self) -> f64 {
+        self.plant.timestep()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{dmatrix, dvector};
+
+    fn double_integrator() -> DiscreteStateSpace {
+        DiscreteStateSpace::new(
+            dmatrix![1.0, 0.1; 0.0, 1.0],
+            dmatrix![0.005; 0.1],
+            dmatrix![1.0, 0.0],
+            dmatrix![0.0],
+            0.1,
+        )
+    }
+
+    /// Runs `mpc` in closed loop with its own model, returning the state and
+    /// input trajectories and the solver outcome of every step.
+    fn run(
+        mut mpc: ModelPredictiveController,
+        reference: f64,
+        steps: usize,
+    ) -> (Vec<DVector<f64>>, Vec<f64>, Vec<MpcReport>) {
+        let plant = double_integrator();
+        let mut x = dvector![0.0, 0.0];
+        let mut states = vec![];
+        let mut inputs = vec![];
+        let mut reports = vec![];
+
+        for k in 0..steps {
+            let input = dvector![x[0], x[1], reference];
+            let next = mpc.next_state(k as f64 * 0.1, mpc.state(), &input);
+            mpc.set_state(&next);
+
+            let u = mpc.get_output();
+            x = &plant.a * &x + &plant.b * &u;
+            states.push(x.clone());
+            inputs.push(u[0]);
+            reports.push(*mpc.report().unwrap());
+        }
+
+        (states, inputs, reports)
+    }
+
+    #[test]
+    fn test_prediction_matches_simulation() {
+        let plant = double_integrator();
+        let mpc = ModelPredictiveController::new(&plant, 4).control_horizon(2);
+        let (phi, gamma) = mpc.prediction();
+
+        let x0 = dvector![1.0, -0.5];
+        let u = dvector![0.3, -0.2];
+        let predicted = &phi * &x0 + &gamma * &u;
+
+        let mut x = x0;
+        for k in 0..4 {
+            x = &plant.a * &x + &plant.b * dvector![u[k.min(1)]];
+            assert!((predicted.rows(2 * k, 2) - &x).norm() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_unconstrained_tracking() {
+        let plant = double_integrator();
+        let mpc = ModelPredictiveController::new(&plant, 20).weights(dmatrix![1.0], dmatrix![0.01]);
+        let (states, _, reports) = run(mpc, 1.0, 100);
+
+        assert!((states.last().unwrap()[0] - 1.0).abs() < 1e-3);
+        assert!(reports.iter().all(|r| r.status == QpStatus::Solved));
+    }
+
+    #[test]
+    fn test_input_state_and_rate_limits() {
+        let plant = double_integrator();
+        let mpc = ModelPredictiveController::new(&plant, 15)
+            .weights(dmatrix![1.0], dmatrix![0.001])
+            .input_limits(dvector![-1.0], dvector![1.0])
+            .rate_limits(dvector![-0.25], dvector![0.25])
+            .state_limits(dvector![-10.0, -0.6], dvector![10.0, 0.6]);
+        let (states, inputs, reports) = run(mpc, 1.0, 100);
+
+        let tolerance = 1e-3;
+        assert!(inputs.iter().all(|u| u.abs() <= 1.0 + tolerance));
+        assert!(
+            inputs
+                .windows(2)
+                .all(|w| (w[1] - w[0]).abs() <= 0.25 + tolerance)
+        );
+        assert!(states.iter().all(|x| x[1].abs() <= 0.6 + tolerance));
+        assert!((states.last().unwrap()[0] - 1.0).abs() < 1e-2);
+        assert_eq!(reports.len(), 100);
+        assert_eq!(reports.last().unwrap().time, 9.9);
+    }
+
+    #[test]
+    fn test_reports_infeasibility() {
+        let plant = double_integrator();
+        let mut mpc = ModelPredictiveController::new(&plant, 5)
+            .input_limits(dvector![-1.0], dvector![1.0])
+            .state_limits(dvector![-1.0, -1.0], dvector![1.0, 1.0]);
+
+        // Starting far outside the state limits, no admissible input exists
+        let next = mpc.next_state(0.0, mpc.state(), &dvector![5.0, 0.0, 0.0]);
+        mpc.set_state(&next);
+        assert_eq!(mpc.report().unwrap().status, QpStatus::PrimalInfeasible);
+
+        // Redoing the sample replaces its report
+        let next = mpc.next_state(0.0, mpc.state(), &dvector![0.0, 0.0, 0.0]);
+        mpc.set_state(&next);
+        assert_eq!(mpc.report().unwrap().status, QpStatus::Solved);
+    }
+}
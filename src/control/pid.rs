@@ -0,0 +1,220 @@
+use crate::system::System;
+
+/// A PID controller acting on a tracking error
+///
+/// $$C(s) = K_p + \frac{K_i}{s} + \frac{K_d s}{T_f s + 1}$$
+///
+/// The derivative is filtered with a time constant $T_f$, which defaults to a
+/// tenth of the derivative time $K_d / K_p$, or of $\sqrt{K_d / K_i}$ without
+/// proportional action. A pure derivative controller needs an explicit
+/// `derivative_filter`. The integral is frozen while the
+/// output is saturated and the error would push it further, to avoid windup.
+#[derive(Clone, Debug)]
+pub struct Pid {
+    kp: f64,
+    ki: f64,
+    kd: f64,
+    filter: f64,
+    limits: (f64, f64),
+    integral: f64,
+    /// State of the derivative filter, the filtered error.
+    filtered: f64,
+    last_time: f64,
+    /// The instant, integral and filter state the last update started from.
+    previous: Option<(f64, f64, f64)>,
+    output: f64,
+}
+
+impl Pid {
+    /// A controller in parallel form, with independent gains.
+    pub fn new(kp: f64, ki: f64, kd: f64) -> Self {
+        let filter = if kd == 0.0 {
+            0.0
+        } else if kp != 0.0 {
+            0.1 * (kd / kp).abs()
+        } else if ki != 0.0 {
+            0.1 * (kd / ki).abs().sqrt()
+        } else {
+            0.0
+        };
+
+        Self {
+            kp,
+            ki,
+            kd,
+            filter,
+            limits: (f64::NEG_INFINITY, f64::INFINITY),
+            integral: 0.0,
+            filtered: 0.0,
+            last_time: 0.0,
+            previous: None,
+            output: 0.0,
+        }
+    }
+
+    /// A controller in standard form, $K_p (1 + \frac{1}{T_i s} + T_d s)$.
+    /// An infinite `integral_time` removes the integral action.
+    pub fn standard(kp: f64, integral_time: f64, derivative_time: f64) -> Self {
+        assert!(integral_time > 0.0, "Integral time must be positive");
+        Self::new(kp, kp / integral_time, kp * derivative_time)
+    }
+
+    /// Sets the time constant of the derivative filter.
+    pub fn derivative_filter(mut self, time_constant: f64) -> Self {
+        assert!(
+            time_constant >= 0.0,
+            "Filter time constant must not be negative"
+        );
+        self.filter = time_constant;
+        self
+    }
+
+    /// Saturates the output to `[min, max]`.
+    pub fn output_limits(mut self, min: f64, max: f64) -> Self {
+        assert!(min < max, "Output limits must be ordered");
+        self.limits = (min, max);
+        self
+    }
+
+    pub fn kp(&self) -> f64 {
+        self.kp
+    }
+
+    pub fn ki(&self) -> f64 {
+        self.ki
+    }
+
+    pub fn kd(&self) -> f64 {
+        self.kd
+    }
+}
+
+impl System for Pid {
+    type Input = f64;
+    type Output = f64;
+
+    fn update(&mut self, time: f64, error: &f64) -> f64 {
+        assert!(
+            self.kd == 0.0 || self.filter > 0.0,
+            "A derivative action needs a filter"
+        );
+
+        // Updating again at the same instant redoes the last step
+        if let Some((start, integral, filtered)) = self.previous
+            && time == self.last_time
+        {
+            self.last_time = start;
+            self.integral = integral;
+            self.filtered = filtered;
+        }
+        self.previous = Some((self.last_time, self.integral, self.filtered));
+        let dt = time - self.last_time;
+        self.last_time = time;
+
+        // The filter is discretized exactly, holding the error over the step
+        let derivative = if self.kd != 0.0 {
+            self.filtered = error + (self.filtered - error) * (-dt / self.filter).exp();
+            self.kd * (error - self.filtered) / self.filter
+        } else {
+            0.0
+        };
+
+        let (min, max) = self.limits;
+        let integral = self.integral + self.ki * error * dt;
+        let unsaturated = self.kp * error + integral + derivative;
+        let winding_up = (unsaturated > max && self.ki * error > 0.0)
+            || (unsaturated < min && self.ki * error < 0.0);
+        if !winding_up {
+            self.integral = integral;
+        }
+
+        self.output = (self.kp * error + self.integral + derivative).clamp(min, max);
+        f64::INFINITY
+    }
+
+    fn get_output(&self, _time: f64) -> f64 {
+        self.output
+    }
+
+    fn has_feedthrough(&self) -> bool {
+        self.kp != 0.0 || self.kd != 0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        linear::Fopdt,
+        system::{UnitSystem, cloop::ClosedLoop, series::SeriesSystem},
+        utils::Param,
+    };
+
+    fn outputs(pid: &mut Pid, errors: &[(f64, f64)]) -> Vec<f64> {
+        errors
+            .iter()
+            .map(|&(time, error)| {
+                pid.update(time, &error);
+                pid.get_output(time)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parallel_and_standard_forms() {
+        let errors = [(0.0, 1.0), (0.1, 0.5), (0.2, -0.5), (0.3, 0.0)];
+        let parallel = outputs(&mut Pid::new(2.0, 4.0, 0.5), &errors);
+        let standard = outputs(&mut Pid::standard(2.0, 0.5, 0.25), &errors);
+
+        assert_eq!(parallel, standard);
+    }
+
+    #[test]
+    fn test_same_instant_update_is_redone() {
+        let mut pid = Pid::new(1.0, 1.0, 0.1);
+        let once = outputs(&mut pid.clone(), &[(0.0, 0.0), (0.5, 1.0)]);
+        let twice = outputs(&mut pid, &[(0.0, 0.0), (0.5, 3.0), (0.5, 1.0)]);
+
+        assert_eq!(once[1], twice[2]);
+    }
+
+    #[test]
+    fn test_default_filter_without_proportional_action() {
+        // The zeros of ki / s + kd s are at ±j sqrt(ki / kd)
+        let mut pid = Pid::new(0.0, 4.0, 1.0);
+        let output = outputs(&mut pid, &[(0.0, 0.0), (0.1, 1.0)]);
+
+        assert_eq!(pid.filter, 0.05);
+        assert!(output[1].is_finite());
+    }
+
+    #[test]
+    fn test_integral_removes_steady_state_error() {
+        let loop_with = |pid: Pid| {
+            let mut cloop = ClosedLoop::new(
+                SeriesSystem::new(pid, Fopdt::new(2.0, 1.0, 0.1)),
+                UnitSystem::default(),
+            );
+            let mut last = 0.0;
+            cloop.simulate(20.0, 0.01, Param::new(1.0), &mut |s| last = s.output);
+            last
+        };
+
+        // A proportional loop settles at kp k / (1 + kp k)
+        assert!((loop_with(Pid::new(1.0, 0.0, 0.0)) - 2.0 / 3.0).abs() < 1e-6);
+        assert!((loop_with(Pid::new(1.0, 1.0, 0.0)) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_anti_windup() {
+        // A long saturated error must not charge the integral
+        let mut pid = Pid::new(1.0, 10.0, 0.0).output_limits(-1.0, 1.0);
+        let errors: Vec<(f64, f64)> = (0..=100).map(|k| (k as f64 * 0.1, 5.0)).collect();
+        outputs(&mut pid, &errors);
+        assert_eq!(pid.get_output(10.0), 1.0);
+
+        // so the output leaves the limit as soon as the error changes sign
+        pid.update(10.1, &-0.5);
+        assert!(pid.get_output(10.1) < 1.0);
+    }
+}
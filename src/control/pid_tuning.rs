@@ -0,0 +1,278 @@
+use std::f64::consts::PI;
+
+use crate::{control::pid::Pid, linear::Fopdt, system::System};
+
+/// Which actions a tuning rule should produce.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PidStructure {
+    P,
+    Pi,
+    Pid,
+}
+
+/// The gain at which a proportional loop reaches the stability limit, and
+/// the period it then oscillates with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UltimatePoint {
+    pub gain: f64,
+    pub period: f64,
+}
+
+/// Finds the ultimate point of a plant by relay feedback (Åström–Hägglund)
+///
+/// The plant is simulated in a loop with a relay switching its input between
+/// `-amplitude` and `amplitude`, which settles into a limit cycle at the
+/// ultimate period. The ultimate gain follows from the describing function
+/// of the relay. The plant should start at rest around zero.
+#[derive(Clone, Debug)]
+pub struct RelayExperiment {
+    amplitude: f64,
+    hysteresis: f64,
+    timestep: f64,
+    duration: f64,
+    cycles: usize,
+}
+
+impl RelayExperiment {
+    pub fn new(amplitude: f64) -> Self {
+        assert!(amplitude > 0.0, "Relay amplitude must be positive");
+        Self {
+            amplitude,
+            hysteresis: 0.0,
+            timestep: 0.01,
+            duration: 100.0,
+            cycles: 3,
+        }
+    }
+
+    /// Sets the error band within which the relay keeps its position, to
+    /// keep measurement noise from making it chatter.
+    pub fn hysteresis(mut self, hysteresis: f64) -> Self {
+        self.hysteresis = hysteresis;
+        self
+    }
+
+    pub fn timestep(mut self, timestep: f64) -> Self {
+        self.timestep = timestep;
+        self
+    }
+
+    /// Sets how long the experiment may last.
+    pub fn duration(mut self, duration: f64) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Sets the number of final cycles the measurements are averaged over.
+    pub fn cycles(mut self, cycles: usize) -> Self {
+        assert!(cycles > 0, "At least one cycle must be measured");
+        self.cycles = cycles;
+        self
+    }
+
+    /// Runs the experiment, if it reaches enough cycles within its duration.
+    pub fn run<Sys>(&self, plant: &mut Sys) -> Option<UltimatePoint>
+    where
+        Sys: System<Input = f64, Output = f64>,
+    {
+        let mut input = self.amplitude;
+        let mut switches = Vec::new();
+        let mut outputs = Vec::new();
+
+        let steps = (self.duration / self.timestep).round() as usize;
+        for k in 0..=steps {
+            let time = k as f64 * self.timestep;
+            plant.update(time, &input);
+            let output = plant.get_output(time);
+            outputs.push((time, output));
+
+            let error = -output;
+            if error > self.hysteresis && input < 0.0 {
+                input = self.amplitude;
+                switches.push(time);
+            } else if error < -self.hysteresis && input > 0.0 {
+                input = -self.amplitude;
+            }
+        }
+
+        // Skip the first cycle, where the loop is still settling
+        if switches.len() < self.cycles + 2 {
+            return None;
+        }
+        let last = switches[switches.len() - 1];
+        let first = switches[switches.len() - 1 - self.cycles];
+        let period = (last - first) / self.cycles as f64;
+
+        let (min, max) = outputs
+            .iter()
+            .filter(|(t, _)| (first..=last).contains(t))
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &(_, y)| {
+                (min.min(y), max.max(y))
+            });
+        let oscillation = 0.5 * (max - min);
+        if oscillation <= self.hysteresis {
+            return None;
+        }
+
+        Some(UltimatePoint {
+            gain: 4.0 * self.amplitude
+                / (PI * (oscillation * oscillation - self.hysteresis * self.hysteresis).sqrt()),
+            period,
+        })
+    }
+}
+
+impl Pid {
+    /// Ziegler–Nichols' frequency-response rule.
+    pub fn ziegler_nichols(ultimate: &UltimatePoint, structure: PidStructure) -> Self {
+        let UltimatePoint { gain, period } = *ultimate;
+        match structure {
+            PidStructure::P => Self::new(0.5 * gain, 0.0, 0.0),
+            PidStructure::Pi => Self::standard(0.45 * gain, period / 1.2, 0.0),
+            PidStructure::Pid => Self::standard(0.6 * gain, 0.5 * period, 0.125 * period),
+        }
+    }
+
+    /// Skogestad's SIMC rule, a PI controller giving a closed-loop time
+    /// constant of `closed_loop_time`. Setting it to the dead time gives a
+    /// good trade-off between speed and robustness.
+    pub fn simc(model: &Fopdt, closed_loop_time: f64) -> Self {
+        let Fopdt {
+            gain,
+            time_constant,
+            dead_time,
+            ..
+        } = *model;
+        let horizon = closed_loop_time + dead_time;
+        assert!(horizon > 0.0, "The closed-loop time must be positive");
+
+        Self::standard(
+            time_constant / (gain * horizon),
+            time_constant.min(4.0 * horizon),
+            0.0,
+        )
+    }
+
+    /// Åström and Hägglund's AMIGO rule, for PI or PID controllers.
+    pub fn amigo(model: &Fopdt, structure: PidStructure) -> Self {
+        let Fopdt {
+            gain: k,
+            time_constant: t,
+            dead_time: l,
+            ..
+        } = *model;
+        assert!(l > 0.0, "AMIGO needs a dead time");
+
+        match structure {
+            PidStructure::P => panic!("AMIGO has no rule for a P controller"),
+            PidStructure::Pi => Self::standard(
+                0.15 / k + (0.35 - l * t / (l + t).powi(2)) * t / (k * l),
+                0.35 * l + 13.0 * l * t * t / (t * t + 12.0 * l * t + 7.0 * l * l),
+                0.0,
+            ),
+            PidStructure::Pid => Self::standard(
+                (0.2 + 0.45 * t / l) / k,
+                (0.4 * l + 0.8 * t) * l / (l + 0.1 * t),
+                0.5 * l * t / (0.3 * l + t),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        analysis::StepResponse,
+        system::{UnitSystem, cloop::ClosedLoop, series::SeriesSystem},
+        utils::Param,
+    };
+
+    fn step_response(pid: Pid, plant: impl System<Input = f64, Output = f64>) -> StepResponse {
+        let mut cloop = ClosedLoop::new(SeriesSystem::new(pid, plant), UnitSystem::default());
+        let mut samples = vec![];
+        cloop.simulate(40.0, 0.01, Param::new(1.0), &mut |s| samples.push(s));
+        StepResponse::from_samples(&samples)
+    }
+
+    fn three_lags() -> impl System<Input = f64, Output = f64> {
+        SeriesSystem::new(
+            Fopdt::new(1.0, 1.0, 0.0),
+            SeriesSystem::new(Fopdt::new(1.0, 1.0, 0.0), Fopdt::new(1.0, 1.0, 0.0)),
+        )
+    }
+
+    #[test]
+    fn test_relay_finds_ultimate_point() {
+        // 1 / (s + 1)^3 crosses -180° at √3 rad/s with a gain of 1/8
+        let ultimate = RelayExperiment::new(1.0).run(&mut three_lags()).unwrap();
+
+        assert!((ultimate.period - 2.0 * PI / 3f64.sqrt()).abs() < 0.05);
+        assert!((ultimate.gain - 8.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn test_relay_needs_enough_cycles() {
+        let experiment = RelayExperiment::new(1.0).duration(2.0);
+        assert!(experiment.run(&mut three_lags()).is_none());
+    }
+
+    #[test]
+    fn test_ziegler_nichols_rules() {
+        let ultimate = UltimatePoint {
+            gain: 10.0,
+            period: 2.0,
+        };
+
+        let p = Pid::ziegler_nichols(&ultimate, PidStructure::P);
+        assert_eq!((p.kp(), p.ki(), p.kd()), (5.0, 0.0, 0.0));
+
+        let pid = Pid::ziegler_nichols(&ultimate, PidStructure::Pid);
+        assert_eq!((pid.kp(), pid.ki(), pid.kd()), (6.0, 6.0, 1.5));
+    }
+
+    #[test]
+    fn test_relay_tuned_loop_is_stable() {
+        let ultimate = RelayExperiment::new(1.0).run(&mut three_lags()).unwrap();
+        let response = step_response(
+            Pid::ziegler_nichols(&ultimate, PidStructure::Pid),
+            three_lags(),
+        );
+
+        // Ziegler–Nichols is known to be aggressive, but it settles
+        assert!(response.overshoot > 20.0 && response.overshoot < 80.0);
+        assert!(response.settling_time.unwrap() < 30.0);
+        assert!((response.final_value - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_simc_rule() {
+        let model = Fopdt::new(2.0, 4.0, 0.5);
+        let pi = Pid::simc(&model, 0.5);
+
+        // kc = T / (k (tc + L)) and Ti = min(T, 4 (tc + L))
+        assert!((pi.kp() - 2.0).abs() < 1e-12);
+        assert!((pi.ki() - 2.0 / 4.0).abs() < 1e-12);
+        assert_eq!(pi.kd(), 0.0);
+
+        let response = step_response(pi, Fopdt::new(2.0, 4.0, 0.5));
+        assert!(response.overshoot < 15.0);
+        assert!((response.final_value - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_amigo_rules() {
+        let model = Fopdt::new(1.0, 1.0, 0.2);
+
+        let pid = Pid::amigo(&model, PidStructure::Pid);
+        // K = 0.2 + 0.45 T / L, Ti = (0.4 L + 0.8 T) L / (L + 0.1 T)
+        assert!((pid.kp() - 2.45).abs() < 1e-12);
+        assert!((pid.kp() / pid.ki() - 0.88 * 0.2 / 0.3).abs() < 1e-12);
+
+        for structure in [PidStructure::Pi, PidStructure::Pid] {
+            let response = step_response(Pid::amigo(&model, structure), Fopdt::new(1.0, 1.0, 0.2));
+            assert!(response.overshoot < 25.0);
+            assert!((response.final_value - 1.0).abs() < 1e-3);
+        }
+    }
+}
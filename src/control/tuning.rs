@@ -0,0 +1,184 @@
+use nalgebra::DVector;
+
+use crate::{
+    optimization::derivative_free::{Bounds, Minimizer},
+    system::{Sample, System},
+    utils::Param,
+};
+
+/// The outcome of a `GainTuner`.
+#[derive(Clone, Debug)]
+pub struct TunedGains<Input, Output> {
+    pub gains: DVector<f64>,
+    pub cost: f64,
+    /// The best cost found by the end of each iteration of the optimizer.
+    pub history: Vec<f64>,
+    /// Number of simulations run.
+    pub evaluations: usize,
+    /// The samples of a simulation with the best gains.
+    pub trace: Vec<Sample<Input, Output>>,
+}
+
+/// Tunes a gain vector by minimizing a cost computed from simulations
+///
+/// Each evaluation builds a fresh system from the gains, simulates it for
+/// `total_time` with `input`, and scores its samples, e.g. with the
+/// costs of `analysis::cost`. Simulations that blow up should return an
+/// infinite or NaN cost.
+pub struct GainTuner<Input> {
+    total_time: f64,
+    max_timestep: f64,
+    input: Param<Input>,
+    bounds: Option<Bounds>,
+}
+
+impl<Input: Clone> GainTuner<Input> {
+    pub fn new(total_time: f64, max_timestep: f64, input: Param<Input>) -> Self {
+        Self {
+            total_time,
+            max_timestep,
+            input,
+            bounds: None,
+        }
+    }
+
+    /// Restricts the gains to `[lower, upper]`.
+    pub fn bounds(mut self, lower: DVector<f64>, upper: DVector<f64>) -> Self {
+        self.bounds = Some(Bounds::new(lower, upper));
+        self
+    }
+
+    fn simulate<Sys>(&self, system: &mut Sys) -> Vec<Sample<Input, Sys::Output>>
+    where
+        Sys: System<Input = Input>,
+        Sys::Output: Clone,
+    {
+        let mut samples = Vec::new();
+        system.simulate(
+            self.total_time,
+            self.max_timestep,
+            self.input.clone(),
+            &mut |s| samples.push(s),
+        );
+        samples
+    }
+
+    /// Minimizes `cost` over the gains with `minimizer`, from `initial`.
+    pub fn tune<Sys>(
+        &self,
+        minimizer: &impl Minimizer,
+        initial: DVector<f64>,
+        build: impl Fn(&DVector<f64>) -> Sys,
+        cost: impl Fn(&[Sample<Input, Sys::Output>]) -> f64,
+    ) -> TunedGains<Input, Sys::Output>
+    where
+        Sys: System<Input = Input>,
+        Sys::Output: Clone,
+    {
+        let bounds = self
+            .bounds
+            .clone()
+            .unwrap_or_else(|| Bounds::unbounded(initial.len()));
+
+        let minimum = minimizer.minimize(
+            &mut |gains| cost(&self.simulate(&mut build(gains))),
+            &initial,
+            &bounds,
+        );
+        let trace = self.simulate(&mut build(&minimum.x));
+
+        TunedGains {
+            gains: minimum.x,
+            cost: minimum.cost,
+            history: minimum.history,
+            evaluations: minimum.evaluations + 1,
+            trace,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        analysis::{
+            StepResponse,
+            cost::{ise, overshoot_penalty},
+        },
+        continuous::{ContinuousSystem, integrator::RungeKutta4},
+        linear::StateSpace,
+        optimization::{CmaEs, NelderMead},
+        system::{
+            UnitSystem, cloop::ClosedLoop, gain::Gain, routing::Adapter, series::SeriesSystem,
+        },
+    };
+    use nalgebra::{dmatrix, dvector};
+
+    /// A unit-feedback loop around `kp / (s + 1)`, with a prefilter `kf`.
+    fn proportional_loop(gains: &DVector<f64>) -> impl System<Input = f64, Output = f64> + use<> {
+        let plant = StateSpace::new(
+            dmatrix![-1.0],
+            dmatrix![gains[0]],
+            dmatrix![1.0],
+            dmatrix![0.0],
+        )
+        .max_timestep(0.01)
+        .initial_state(dvector![0.0])
+        .with_integrator(RungeKutta4);
+        SeriesSystem::new(
+            Gain::new(gains[1]),
+            ClosedLoop::new(Adapter::siso(plant), UnitSystem::default()),
+        )
+    }
+
+    #[test]
+    fn test_tune_proportional_loop() {
+        // A higher gain always tracks faster, so the gain ends on its bound,
+        // and the prefilter scales the unit step response y to minimize
+        // the ISE, at kf = ∫y / ∫y²
+        let tuner = GainTuner::new(5.0, 0.01, Param::new(1.0))
+            .bounds(dvector![0.1, 0.0], dvector![4.0, 10.0]);
+        let tuned = tuner.tune(
+            &NelderMead::default(),
+            dvector![1.0, 1.0],
+            proportional_loop,
+            ise,
+        );
+
+        assert!((tuned.gains[0] - 4.0).abs() < 1e-3);
+        assert!((tuned.gains[1] - 1.25 * 4.8 / 4.7).abs() < 1e-2);
+        assert!(tuned.history.windows(2).all(|w| w[1] <= w[0]));
+        assert!((ise(&tuned.trace) - tuned.cost).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_tune_with_cma_es_and_penalty() {
+        // A unit-feedback loop around kp / (s (s + 1)) tracks faster but
+        // overshoots more as kp grows, reaching 5% overshoot at kp ≈ 0.525
+        let servo = |gains: &DVector<f64>| {
+            let plant = StateSpace::new(
+                dmatrix![0.0, 1.0; 0.0, -1.0],
+                dmatrix![0.0; gains[0]],
+                dmatrix![1.0, 0.0],
+                dmatrix![0.0],
+            )
+            .max_timestep(0.01)
+            .initial_state(dvector![0.0, 0.0])
+            .with_integrator(RungeKutta4);
+            ClosedLoop::new(Adapter::siso(plant), UnitSystem::default())
+        };
+
+        let tuner =
+            GainTuner::new(15.0, 0.01, Param::new(1.0)).bounds(dvector![0.01], dvector![10.0]);
+        let tuned = tuner.tune(
+            &CmaEs::new(2).step_size(0.5).tolerance(1e-4),
+            dvector![2.0],
+            servo,
+            |samples| ise(samples) + 10.0 * overshoot_penalty(samples, 5.0),
+        );
+
+        let response = StepResponse::from_samples(&tuned.trace);
+        assert!(response.overshoot > 4.5 && response.overshoot < 5.2);
+        assert!((tuned.gains[0] - 0.525).abs() < 0.03);
+    }
+}
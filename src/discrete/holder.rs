@@ -29,6 +29,15 @@ impl<Data> ZeroOrderHold<Data> {
     }
 }
 
+impl<Data> Default for ZeroOrderHold<Data>
+where
+    Data: Default,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<Data> Holder<Data> for ZeroOrderHold<Data>
 where
     Data: Clone,
@@ -70,12 +79,22 @@ impl<Data> FirstOrderHold<Data> {
     }
 }
 
+impl<Data> Default for FirstOrderHold<Data>
+where
+    Data: Default,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<Data> Holder<Data> for FirstOrderHold<Data>
 where
     Data: Clone + Mul<f64, Output = Data> + Add<Data, Output = Data>,
 {
     fn hold(&mut self, time: f64, input: &Data) {
-        if !self.initialized {
+        // holding again at the same instant replaces the sample
+        if !self.initialized || time == self.curr_time {
             self.curr_time = time;
             self.curr_input = input.clone();
             self.initialized = true;
@@ -141,6 +160,15 @@ impl<Data> ImpulseHold<Data> {
     }
 }
 
+impl<Data> Default for ImpulseHold<Data>
+where
+    Data: Default,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<Data> Holder<Data> for ImpulseHold<Data>
 where
     Data: Clone,
@@ -3,6 +3,7 @@ use std::marker::PhantomData;
 use crate::{discrete::holder::Holder, system::System};
 
 pub mod holder;
+pub mod transition;
 
 pub trait DiscreteSystem<Input, State, Output> {
     fn next_state(
@@ -19,65 +20,149 @@ pub trait DiscreteSystem<Input, State, Output> {
 
     fn timestep(&self) -> f64;
 
+    /// Whether `get_output` depends on the input last given to `next_state`.
+    /// A held system outputs the result of the step taken at the same
+    /// instant, so this is assumed unless the system says otherwise.
+    fn has_feedthrough(&self) -> bool {
+        true
+    }
+
     fn with_holder<Hol>(self, holder: Hol) -> HeldSystem<Self, Hol, Input, State, Output>
     where
         Self: Sized,
         Hol: Holder<Output>,
     {
         HeldSystem {
+            clock: SampleClock::new(self.timestep()),
             system: self,
             holder,
-            last_time: 0.0,
+            previous: None,
             _dummy: PhantomData
         }
     }
 }
 
+/// What a `SampleClock` does at an instant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Tick {
+    /// The instant is not on the grid.
+    Idle,
+    /// The instant is the next one on the grid.
+    Fire,
+    /// The instant is the one the clock last fired at.
+    Refire,
+}
+
+/// Tracks the grid of instants `k * timestep`, `k >= 1`, a discrete block
+/// fires at.
+///
+/// Instants are computed from their index rather than accumulated, so blocks
+/// of unrelated timesteps stay exactly on their own grid.
+#[derive(Clone, Debug)]
+pub(crate) struct SampleClock {
+    timestep: f64,
+    ticks: u64,
+    last: Option<f64>,
+}
+
+impl SampleClock {
+    pub(crate) fn new(timestep: f64) -> Self {
+        assert!(timestep > 0.0, "Timestep must be positive");
+        Self {
+            timestep,
+            ticks: 0,
+            last: None,
+        }
+    }
+
+    /// The next instant on the grid.
+    pub(crate) fn next(&self) -> f64 {
+        (self.ticks + 1) as f64 * self.timestep
+    }
+
+    /// Advances the clock to `time`, which must not skip an instant of the grid.
+    pub(crate) fn tick(&mut self, time: f64) -> Tick {
+        if self.last == Some(time) {
+            return Tick::Refire;
+        }
+
+        let next = self.next();
+        if time < next - self.timestep * 1e-9 {
+            return Tick::Idle;
+        }
+
+        assert!(
+            time - next < self.timestep * 1e-5,
+            "Requested event was not triggered"
+        );
+
+        self.ticks += 1;
+        self.last = Some(time);
+        Tick::Fire
+    }
+}
+
+/// A `DiscreteSystem` turned into a `System`, firing every `timestep()` and
+/// holding its output in between.
 pub struct HeldSystem<Sys, Hol, Input, State, Output> {
     system: Sys,
     holder: Hol,
-    last_time: f64,
+    clock: SampleClock,
+    /// The state before the last firing, restored if it is redone.
+    previous: Option<State>,
     _dummy: PhantomData<(Input, State, Output)>
 }
 
+impl<Sys, Hol, Input, State, Output> HeldSystem<Sys, Hol, Input, State, Output> {
+    /// The wrapped discrete system.
+    pub fn system(&self) -> &Sys {
+        &self.system
+    }
+}
+
 impl<Sys, Hol, Input, State, Output>
     System for HeldSystem<Sys, Hol, Input, State, Output>
 where
     Sys: DiscreteSystem<Input, State, Output>,
     Hol: Holder<Output>,
+    State: Clone,
 {
     type Input = Input;
     type Output = Output;
 
     fn update(&mut self, time: f64, input: &Input) -> f64 {
-        let req_dt = self.system.timestep();
-        let dt = time - self.last_time;
-
-        if dt < req_dt {
-            return self.last_time + dt;
+        match self.clock.tick(time) {
+            Tick::Idle => return self.clock.next(),
+            Tick::Fire => self.previous = Some(self.system.state().clone()),
+            Tick::Refire => {
+                if let Some(state) = &self.previous {
+                    self.system.set_state(state);
+                }
+            }
         }
 
-        // If the request time
-        assert!(
-            dt - req_dt < req_dt * 1e-5,
-            "Requested event was not triggered"
-        );
-
         self.system.set_state(&self.system.next_state(time, self.system.state(), input));
         self.holder.hold(time, &self.system.get_output());
 
-        self.last_time + 2.0 * dt
+        self.clock.next()
     }
 
     fn get_output(&self, time: f64) -> Output {
         self.holder.get_output(time)
     }
+
+    fn has_feedthrough(&self) -> bool {
+        self.system.has_feedthrough()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::discrete::holder::{FirstOrderHold, ImpulseHold, ZeroOrderHold};
+    use crate::{
+        discrete::holder::{FirstOrderHold, ImpulseHold, ZeroOrderHold},
+        utils::Param,
+    };
     use nalgebra::{Const, Owned, Vector};
 
     pub type VecN<const N: usize, S = Owned<f64, Const<N>, Const<1>>> = Vector<f64, Const<N>, S>;
@@ -128,16 +213,16 @@ mod tests {
     }
 
     #[test]
-    fn heldsystem_no_trigger_returns_last_time_plus_dt_and_does_not_update() {
+    fn heldsystem_no_trigger_returns_next_grid_instant_and_does_not_update() {
         const N: usize = 1;
         let sys = MockDiscrete::<N>::new(0.2);
         let mut held = sys.with_holder(ZeroOrderHold::<VecN<N>>::new());
 
         let input = VecN::<N>::from_row_slice(&[1.0]);
 
-        // time < timestep -> no trigger, return last_time + dt
+        // time < timestep -> no trigger, return the first instant of the grid
         let ret = held.update(0.1, &input);
-        assert_eq!(ret, 0.1);
+        assert_eq!(ret, 0.2);
 
         // state must remain zero
         assert_eq!(held.system.state(), &VecN::<N>::zeros());
@@ -157,8 +242,8 @@ mod tests {
         // trigger at exactly timestep
         let ret = held.update(0.1, &input);
 
-        // impl returns last_time + 2.0 * dt; last_time starts 0.0
-        assert_eq!(ret, 0.0 + 2.0 * 0.1);
+        // the next firing is on the following instant of the grid
+        assert_eq!(ret, 2.0 * 0.1);
 
         // internal system state updated to previous state + input = input
         assert_eq!(held.system.state(), &input);
@@ -179,6 +264,40 @@ mod tests {
         let _ = held.update(0.25, &input);
     }
 
+    #[test]
+    fn heldsystem_fires_on_its_grid_during_simulation() {
+        const N: usize = 1;
+        let sys = MockDiscrete::<N>::new(0.3);
+        let mut held = sys.with_holder(ZeroOrderHold::<VecN<N>>::new());
+        let mut out = vec![];
+
+        // Unrelated simulation steps still let the system fire on 0.3, 0.6, 0.9
+        held.simulate(1.0, 0.25, Param::new(VecN::<N>::from_row_slice(&[1.0])), &mut |x| {
+            out.push((x.instant, x.output[0]))
+        });
+
+        // Each firing adds one to the output
+        let fired: Vec<f64> = out.windows(2).filter(|w| w[1].1 > w[0].1).map(|w| w[1].0).collect();
+        assert_eq!(held.system.state()[0], 3.0);
+        assert_eq!(fired.len(), 3);
+        for (t, expected) in fired.iter().zip([0.3, 0.6, 0.9]) {
+            assert!((t - expected).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn heldsystem_redoes_firing_at_same_instant() {
+        const N: usize = 1;
+        let sys = MockDiscrete::<N>::new(0.1);
+        let mut held = sys.with_holder(ZeroOrderHold::<VecN<N>>::new());
+
+        held.update(0.1, &VecN::<N>::from_row_slice(&[1.0]));
+        held.update(0.1, &VecN::<N>::from_row_slice(&[2.0]));
+
+        // the second update replaces the first instead of stacking on it
+        assert_eq!(held.get_output(0.1)[0], 2.0);
+    }
+
     #[test]
     fn first_order_hold_interpolates_between_two_samples() {
         const N: usize = 2;
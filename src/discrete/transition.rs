@@ -0,0 +1,226 @@
+use std::ops::{Add, Mul};
+
+use crate::{
+    discrete::{
+        SampleClock, Tick,
+        holder::{FirstOrderHold, Holder},
+    },
+    system::System,
+};
+
+/// How a `RateTransition` builds its output from the input samples.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transition {
+    /// Outputs the latest input sample.
+    Hold,
+    /// Interpolates linearly between the last two input samples, delayed by
+    /// one input period so that the interpolation never extrapolates.
+    Interpolate,
+}
+
+/// Moves a signal between blocks of different timesteps.
+///
+/// The input is sampled on the grid of the block driving it, and the output
+/// is updated on the grid of the block it drives, so that each side only
+/// sees changes on its own grid:
+///
+/// - from a fast block to a slow one, `hold` samples the input and holds it
+///   for the slow block;
+/// - from a slow block to a fast one, `interpolate` smooths the steps of the
+///   input at the cost of one slow period of delay, while `hold` keeps them.
+///
+///                   +--------------+
+///  INPUT (input) ---+  transition  +--- OUTPUT (output)
+///                   +--------------+
+///
+pub struct RateTransition<Data> {
+    transition: Transition,
+    input_timestep: f64,
+    input_clock: SampleClock,
+    output_clock: SampleClock,
+    samples: FirstOrderHold<Data>,
+    output: Data,
+}
+
+impl<Data> RateTransition<Data>
+where
+    Data: Default,
+{
+    pub fn new(transition: Transition, input_timestep: f64, output_timestep: f64) -> Self {
+        Self {
+            transition,
+            input_timestep,
+            input_clock: SampleClock::new(input_timestep),
+            output_clock: SampleClock::new(output_timestep),
+            samples: FirstOrderHold::new(),
+            output: Data::default(),
+        }
+    }
+
+    /// Samples the input every `input_timestep` and holds the latest sample,
+    /// updating the output every `output_timestep`.
+    pub fn hold(input_timestep: f64, output_timestep: f64) -> Self {
+        Self::new(Transition::Hold, input_timestep, output_timestep)
+    }
+
+    /// Samples the input every `input_timestep` and interpolates between the
+    /// last two samples, updating the output every `output_timestep`.
+    pub fn interpolate(input_timestep: f64, output_timestep: f64) -> Self {
+        Self::new(Transition::Interpolate, input_timestep, output_timestep)
+    }
+}
+
+impl<Data> System for RateTransition<Data>
+where
+    Data: Clone + Mul<f64, Output = Data> + Add<Data, Output = Data>,
+{
+    type Input = Data;
+    type Output = Data;
+
+    fn update(&mut self, time: f64, input: &Data) -> f64 {
+        if self.input_clock.tick(time) != Tick::Idle {
+            self.samples.hold(time, input);
+        }
+
+        if self.output_clock.tick(time) != Tick::Idle {
+            self.output = match self.transition {
+                Transition::Hold => self.samples.get_output(f64::INFINITY),
+                Transition::Interpolate => self.samples.get_output(time - self.input_timestep),
+            };
+        }
+
+        self.input_clock.next().min(self.output_clock.next())
+    }
+
+    fn get_output(&self, _time: f64) -> Data {
+        self.output.clone()
+    }
+
+    fn has_feedthrough(&self) -> bool {
+        self.transition == Transition::Hold
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use crate::{
+        discrete::{DiscreteSystem, holder::ZeroOrderHold},
+        system::series::SeriesSystem,
+        utils::Param,
+    };
+
+    type Fires = Rc<RefCell<Vec<(f64, f64)>>>;
+
+    // Records the instants it fires at, with the input received. Its state
+    // is either the firing instant or the input.
+    struct Recorder {
+        timestep: f64,
+        stamp: bool,
+        state: f64,
+        fires: Fires,
+    }
+
+    impl Recorder {
+        fn new(timestep: f64, stamp: bool) -> (Self, Fires) {
+            let fires = Rc::new(RefCell::new(Vec::new()));
+            let recorder = Self {
+                timestep,
+                stamp,
+                state: 0.0,
+                fires: fires.clone(),
+            };
+            (recorder, fires)
+        }
+    }
+
+    impl DiscreteSystem<f64, f64, f64> for Recorder {
+        fn next_state(&self, time: f64, _state: &f64, input: &f64) -> f64 {
+            self.fires.borrow_mut().push((time, *input));
+            if self.stamp { time } else { *input }
+        }
+
+        fn get_output(&self) -> f64 {
+            self.state
+        }
+
+        fn state(&self) -> &f64 {
+            &self.state
+        }
+
+        fn set_state(&mut self, new_state: &f64) {
+            self.state = *new_state;
+        }
+
+        fn timestep(&self) -> f64 {
+            self.timestep
+        }
+    }
+
+    fn assert_on_grid(fires: &[(f64, f64)], timestep: f64, total: f64) {
+        let count = (total / timestep - 1e-9).floor() as usize;
+        assert_eq!(fires.len(), count);
+        for (k, (time, _)) in fires.iter().enumerate() {
+            assert!((time - (k + 1) as f64 * timestep).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_fast_to_slow_hold() {
+        // 3 ms and 7 ms are not multiples of one another
+        let (fast, fast_fires) = Recorder::new(0.003, true);
+        let (slow, slow_fires) = Recorder::new(0.007, false);
+        let transition = RateTransition::hold(0.003, 0.007);
+
+        let mut sys = SeriesSystem::new(
+            SeriesSystem::new(fast.with_holder(ZeroOrderHold::new()), transition),
+            slow.with_holder(ZeroOrderHold::new()),
+        );
+        sys.simulate(0.1, 1.0, Param::new(0.0), &mut |_| {});
+
+        assert_on_grid(&fast_fires.borrow(), 0.003, 0.1);
+        assert_on_grid(&slow_fires.borrow(), 0.007, 0.1);
+
+        // The slow block sees the fast output of its own instant, or the one before
+        for &(time, input) in slow_fires.borrow().iter() {
+            let latest = (time / 0.003 + 1e-9).floor() * 0.003;
+            assert!((input - latest).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_slow_to_fast_interpolate() {
+        let (slow, _) = Recorder::new(0.007, true);
+        let (fast, fast_fires) = Recorder::new(0.003, false);
+        let transition = RateTransition::interpolate(0.007, 0.003);
+
+        let mut sys = SeriesSystem::new(
+            SeriesSystem::new(slow.with_holder(ZeroOrderHold::new()), transition),
+            fast.with_holder(ZeroOrderHold::new()),
+        );
+        sys.simulate(0.1, 1.0, Param::new(0.0), &mut |_| {});
+
+        assert_on_grid(&fast_fires.borrow(), 0.003, 0.1);
+
+        // Once two samples are known, the slow ramp is rebuilt one period late
+        for &(time, input) in fast_fires.borrow().iter().filter(|(t, _)| *t > 0.021) {
+            assert!((input - (time - 0.007)).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_hold_is_sampled_on_input_grid() {
+        let mut transition = RateTransition::<f64>::hold(0.5, 0.2);
+
+        // Off the input grid, the input is ignored
+        assert_eq!(transition.update(0.2, &1.0), 0.4);
+        assert_eq!(transition.get_output(0.2), 0.0);
+        transition.update(0.4, &2.0);
+        transition.update(0.5, &3.0);
+        transition.update(0.6, &4.0);
+
+        assert_eq!(transition.get_output(0.6), 3.0);
+    }
+}
@@ -0,0 +1,314 @@
+use nalgebra::{DMatrix, DVector};
+
+use crate::{
+    discrete::DiscreteSystem,
+    linear::{DiscreteStateSpace, riccati::dare},
+    utils::Param,
+};
+
+/// The estimate and error covariance carried by a Kalman filter between samples.
+#[derive(Clone, Debug)]
+pub struct KalmanState {
+    pub estimate: DVector<f64>,
+    pub covariance: DMatrix<f64>,
+    /// The plant input applied since `estimate` was computed.
+    pub input: DVector<f64>,
+}
+
+/// A linear discrete-time Kalman filter for the model
+///
+/// $$x_{k+1} = A x_k + B u_k + w_k, \quad w_k \sim N(0, Q)$$
+/// $$y_k = C x_k + D u_k + v_k, \quad v_k \sim N(0, R)$$
+///
+/// Its input is the plant input and the measured output stacked as $[u; y]$,
+/// and its output is the filtered estimate $\hat{x}_{k|k}$. Each sample runs
+/// a prediction with the previous input followed by a measurement update.
+pub struct KalmanFilter {
+    plant: DiscreteStateSpace,
+    process_noise: Param<DMatrix<f64>>,
+    measurement_noise: Param<DMatrix<f64>>,
+    state: KalmanState,
+}
+
+impl KalmanFilter {
+    /// Creates a filter for `plant` with process noise covariance $Q$ and
+    /// measurement noise covariance $R$.
+    pub fn new(
+        plant: &DiscreteStateSpace,
+        process_noise: impl Into<Param<DMatrix<f64>>>,
+        measurement_noise: impl Into<Param<DMatrix<f64>>>,
+    ) -> Self {
+        let process_noise = process_noise.into();
+        let measurement_noise = measurement_noise.into();
+
+        assert_eq!(
+            process_noise.shape(),
+            (plant.states(), plant.states()),
+            "Q must be square with the same size as A"
+        );
+        assert_eq!(
+            measurement_noise.shape(),
+            (plant.outputs(), plant.outputs()),
+            "R must be square with as many rows as C"
+        );
+
+        Self {
+            state: KalmanState {
+                estimate: DVector::zeros(plant.states()),
+                covariance: DMatrix::identity(plant.states(), plant.states()),
+                input: DVector::zeros(plant.inputs()),
+            },
+            plant: plant.clone(),
+            process_noise,
+            measurement_noise,
+        }
+    }
+
+    /// Sets the initial estimate and its error covariance.
+    pub fn initial_estimate(mut self, estimate: DVector<f64>, covariance: DMatrix<f64>) -> Self {
+        assert_eq!(
+            estimate.len(),
+            self.plant.states(),
+            "Estimate has the wrong dimension"
+        );
+        assert_eq!(
+            covariance.shape(),
+            (self.plant.states(), self.plant.states()),
+            "Covariance has the wrong dimension"
+        );
+        self.state.estimate = estimate;
+        self.state.covariance = covariance;
+        self
+    }
+
+    /// The error covariance of the current estimate.
+    pub fn covariance(&self) -> &DMatrix<f64> {
+        &self.state.covariance
+    }
+
+    /// Propagates `state` through the model, returning the prior for instant `time`.
+    pub fn predict(&self, time: f64, state: &KalmanState) -> KalmanState {
+        let a = &self.plant.a;
+        let q = self.process_noise.at(time);
+
+        KalmanState {
+            estimate: a * &state.estimate + &self.plant.b * &state.input,
+            covariance: a * &state.covariance * a.transpose() + q,
+            input: state.input.clone(),
+        }
+    }
+
+    /// Corrects the prior `state` with the measurement `output` taken while
+    /// `input` was applied.
+    pub fn correct(
+        &self,
+        time: f64,
+        state: &KalmanState,
+        input: &DVector<f64>,
+        output: &DVector<f64>,
+    ) -> KalmanState {
+        let c = &self.plant.c;
+        let r = self.measurement_noise.at(time);
+        let p = &state.covariance;
+
+        let innovation = output - c * &state.estimate - &self.plant.d * input;
+        let innovation_covariance = c * p * c.transpose() + r;
+        let gain = p
+            * c.transpose()
+            * innovation_covariance
+                .try_inverse()
+                .expect("Innovation covariance is singular");
+
+        // Joseph form, to keep the covariance symmetric and positive definite
+        let n = self.plant.states();
+        let ikc = DMatrix::identity(n, n) - &gain * c;
+        let covariance = &ikc * p * ikc.transpose() + &gain * r * gain.transpose();
+
+        KalmanState {
+            estimate: &state.estimate + gain * innovation,
+            covariance,
+            input: input.clone(),
+        }
+    }
+}
+
+impl DiscreteSystem<DVector<f64>, KalmanState, DVector<f64>> for KalmanFilter {
+    fn next_state(&self, time: f64, state: &KalmanState, input: &DVector<f64>) -> KalmanState {
+        let inputs = self.plant.inputs();
+        let u = input.rows(0, inputs).into_owned();
+        let y = input.rows(inputs, self.plant.outputs()).into_owned();
+
+        let prior = self.predict(time, state);
+        self.correct(time, &prior, &u, &y)
+    }
+
+    fn get_output(&self) -> DVector<f64> {
+        self.state.estimate.clone()
+    }
+
+    fn state(&self) -> &KalmanState {
+        &self.state
+    }
+
+    fn set_state(&mut self, new_state: &KalmanState) {
+        self.state = new_state.clone();
+    }
+
+    fn timestep(&self) -> f64 {
+        self.plant.timestep()
+    }
+}
+
+/// Steady-state solution of a Kalman filter design.
+#[derive(Clone, Debug)]
+pub struct KalmanGain {
+    /// Filter gain $M$, such that $\hat{x}_{k|k} = \hat{x}_{k|k-1} + M (y_k - C \hat{x}_{k|k-1})$.
+    pub gain: DMatrix<f64>,
+    /// Prior error covariance $P = \lim E[(x_k - \hat{x}_{k|k-1})(x_k - \hat{x}_{k|k-1})^T]$.
+    pub prior_covariance: DMatrix<f64>,
+    /// Posterior error covariance $(I - M C) P$.
+    pub posterior_covariance: DMatrix<f64>,
+}
+
+impl KalmanGain {
+    /// The equivalent gain of the prediction-form observer, $L = A M$,
+    /// usable with [`DiscreteObserver`](crate::estimation::DiscreteObserver).
+    pub fn predictor_gain(&self, plant: &DiscreteStateSpace) -> DMatrix<f64> {
+        &plant.a * &self.gain
+    }
+}
+
+/// Computes the steady-state Kalman gain of `plant` from the discrete
+/// algebraic Riccati equation, given the process noise covariance $Q$ and the
+/// measurement noise covariance $R$. Returns `None` if the Riccati equation
+/// has no stabilizing solution.
+pub fn kalman_gain(
+    plant: &DiscreteStateSpace,
+    process_noise: &DMatrix<f64>,
+    measurement_noise: &DMatrix<f64>,
+) -> Option<KalmanGain> {
+    let c = &plant.c;
+    let prior_covariance = dare(
+        &plant.a.transpose(),
+        &c.transpose(),
+        process_noise,
+        measurement_noise,
+    )?;
+
+    let gain = &prior_covariance
+        * c.transpose()
+        * (c * &prior_covariance * c.transpose() + measurement_noise).try_inverse()?;
+    let n = plant.states();
+    let posterior_covariance = (DMatrix::identity(n, n) - &gain * c) * &prior_covariance;
+
+    Some(KalmanGain {
+        gain,
+        prior_covariance,
+        posterior_covariance,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{discrete::holder::ZeroOrderHold, system::System};
+    use nalgebra::{dmatrix, dvector};
+
+    fn constant_velocity() -> DiscreteStateSpace {
+        DiscreteStateSpace::new(
+            dmatrix![1.0, 0.1; 0.0, 1.0],
+            dmatrix![0.005; 0.1],
+            dmatrix![1.0, 0.0],
+            dmatrix![0.0],
+            0.1,
+        )
+    }
+
+    #[test]
+    fn test_kalman_gain_scalar_random_walk() {
+        // x+ = x + w, y = x + v, with q = r = 1 gives P = (1 + sqrt(5)) / 2
+        let plant = DiscreteStateSpace::new(
+            dmatrix![1.0],
+            dmatrix![0.0],
+            dmatrix![1.0],
+            dmatrix![0.0],
+            1.0,
+        );
+        let design = kalman_gain(&plant, &dmatrix![1.0], &dmatrix![1.0]).unwrap();
+        let p = (1.0 + 5f64.sqrt()) / 2.0;
+
+        assert!((design.prior_covariance[0] - p).abs() < 1e-10);
+        assert!((design.gain[0] - p / (p + 1.0)).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_filter_covariance_converges_to_design() {
+        let plant = constant_velocity();
+        let q = dmatrix![1e-4, 0.0; 0.0, 1e-3];
+        let r = dmatrix![0.05];
+
+        let mut filter = KalmanFilter::new(&plant, q.clone(), r.clone());
+        for k in 0..500 {
+            let next = filter.next_state(k as f64 * 0.1, filter.state(), &dvector![0.0, 0.0]);
+            filter.set_state(&next);
+        }
+
+        let design = kalman_gain(&plant, &q, &r).unwrap();
+        assert!((filter.covariance() - design.posterior_covariance).norm() < 1e-8);
+    }
+
+    #[test]
+    fn test_filter_tracks_noiseless_plant() {
+        let plant = constant_velocity();
+        let mut filter = KalmanFilter::new(&plant, dmatrix![1e-4, 0.0; 0.0, 1e-3], dmatrix![0.05]);
+
+        let mut x = dvector![1.0, 0.5];
+        let u = dvector![0.1];
+        let mut u_prev = dvector![0.0];
+        for k in 0..300 {
+            x = &plant.a * &x + &plant.b * &u_prev;
+            let y = &plant.c * &x;
+            let next = filter.next_state(k as f64 * 0.1, filter.state(), &dvector![u[0], y[0]]);
+            filter.set_state(&next);
+            u_prev = u.clone();
+        }
+
+        assert!((filter.get_output() - x).norm() < 1e-3);
+    }
+
+    #[test]
+    fn test_time_varying_measurement_noise() {
+        let plant = constant_velocity();
+        let r = Param::<DMatrix<f64>>::new(dmatrix![0.05]).step(dmatrix![1e6], 1.0);
+        let filter = KalmanFilter::new(&plant, dmatrix![1e-4, 0.0; 0.0, 1e-3], r);
+
+        let prior = filter.predict(2.0, filter.state());
+        let posterior = filter.correct(2.0, &prior, &dvector![0.0], &dvector![10.0]);
+
+        // A very noisy sensor barely moves the estimate
+        assert!(posterior.estimate[0] < 1e-4);
+    }
+
+    #[test]
+    fn test_kalman_filter_with_holder() {
+        let plant = constant_velocity();
+        let new_filter =
+            || KalmanFilter::new(&plant, dmatrix![1e-4, 0.0; 0.0, 1e-3], dmatrix![0.05]);
+        let filter = new_filter();
+        let mut held = new_filter().with_holder(ZeroOrderHold::new());
+
+        // The held filter matches predict and correct at each sample, and
+        // holds the posterior estimate in between
+        let mut state = filter.state().clone();
+        for k in 1..=5 {
+            let time = k as f64 * 0.1;
+            let (u, y) = (dvector![0.5], dvector![0.2 * k as f64]);
+            held.update(time, &dvector![u[0], y[0]]);
+            held.update(time + 0.05, &dvector![u[0], y[0]]);
+
+            let prior = filter.predict(time, &state);
+            state = filter.correct(time, &prior, &u, &y);
+            assert!((held.get_output(time + 0.05) - &state.estimate).norm() < 1e-12);
+        }
+    }
+}
@@ -0,0 +1,9 @@
+pub mod kalman;
+pub mod nonlinear;
+pub mod observer;
+
+pub use self::{
+    kalman::{KalmanFilter, KalmanGain, KalmanState, kalman_gain},
+    nonlinear::{ExtendedKalmanFilter, UnscentedKalmanFilter},
+    observer::{DiscreteObserver, Observer},
+};
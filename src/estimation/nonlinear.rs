@@ -0,0 +1,600 @@
+use std::cell::RefCell;
+
+use nalgebra::{DMatrix, DVector, linalg::Cholesky};
+
+use crate::{
+    continuous::{ContinuousSystem, integrator::Integrator},
+    discrete::DiscreteSystem,
+    estimation::KalmanState,
+    linear::linearize::jacobian,
+};
+
+type Vector = DVector<f64>;
+
+/// A nonlinear `ContinuousSystem` together with the integrator used to
+/// propagate it from one sample to the next.
+struct SampledModel<Sys, Int> {
+    system: RefCell<Sys>,
+    integrator: RefCell<Int>,
+    timestep: f64,
+}
+
+impl<Sys, Int> SampledModel<Sys, Int>
+where
+    Sys: ContinuousSystem<Vector, Vector, Vector>,
+    Int: Integrator<Sys, Vector, Vector, Vector>,
+{
+    /// Integrates `state` from `time - timestep` up to `time` under a constant `input`.
+    fn propagate(&self, time: f64, state: &Vector, input: &Vector) -> Vector {
+        let mut system = self.system.borrow_mut();
+        let mut integrator = self.integrator.borrow_mut();
+
+        let max_timestep = system.max_timestep();
+        assert!(max_timestep > 0.0, "The maximum timestep must be positive");
+        let steps = (self.timestep / max_timestep).ceil().max(1.0);
+        let dt = self.timestep / steps;
+
+        system.set_input(input);
+        system.set_state(state);
+        let mut t = time - self.timestep;
+        for _ in 0..steps as usize {
+            t += dt;
+            integrator.integrate(&mut system, t, dt, input);
+        }
+
+        system.state().clone()
+    }
+
+    /// Evaluates the output map of the system at `state` under `input`.
+    fn measure(&self, time: f64, state: &Vector, input: &Vector) -> Vector {
+        let mut system = self.system.borrow_mut();
+        system.set_input(input);
+        system.set_state(state);
+        system.get_output(time)
+    }
+
+    /// Central finite-difference Jacobian of the state derivative with respect to the state.
+    fn state_jacobian(&self, time: f64, state: &Vector, input: &Vector) -> DMatrix<f64> {
+        let system = self.system.borrow();
+        jacobian(state, None, |x| system.get_derivative(time, x, input))
+    }
+
+    /// Central finite-difference Jacobian of the output map with respect to the state.
+    fn output_jacobian(&self, time: f64, state: &Vector, input: &Vector) -> DMatrix<f64> {
+        jacobian(state, None, |x| self.measure(time, x, input))
+    }
+}
+
+fn split_input(input: &Vector, inputs: usize, outputs: usize) -> (Vector, Vector) {
+    (
+        input.rows(0, inputs).into_owned(),
+        input.rows(inputs, outputs).into_owned(),
+    )
+}
+
+fn initial_state(states: usize, inputs: usize) -> KalmanState {
+    KalmanState {
+        estimate: DVector::zeros(states),
+        covariance: DMatrix::identity(states, states),
+        input: DVector::zeros(inputs),
+    }
+}
+
+/// An extended Kalman filter for a nonlinear `ContinuousSystem` sampled every `timestep`
+///
+/// The estimate is propagated with the integrator, while the covariance is
+/// propagated with the discretized Jacobian of `get_derivative`. The
+/// measurement update linearizes the system's output map around the prior.
+///
+/// Its input is the plant input and the measured output stacked as $[u; y]$,
+/// and its output is the filtered estimate $\hat{x}_{k|k}$.
+pub struct ExtendedKalmanFilter<Sys, Int> {
+    model: SampledModel<Sys, Int>,
+    process_noise: DMatrix<f64>,
+    measurement_noise: DMatrix<f64>,
+    state: KalmanState,
+}
+
+impl<Sys, Int> ExtendedKalmanFilter<Sys, Int>
+where
+    Sys: ContinuousSystem<Vector, Vector, Vector>,
+    Int: Integrator<Sys, Vector, Vector, Vector>,
+{
+    /// Creates a filter sampling `system`, which has `inputs` inputs, every
+    /// `timestep`, with process noise covariance $Q$ (accumulated over one
+    /// sample) and measurement noise covariance $R$.
+    pub fn new(
+        system: Sys,
+        integrator: Int,
+        timestep: f64,
+        inputs: usize,
+        process_noise: DMatrix<f64>,
+        measurement_noise: DMatrix<f64>,
+    ) -> Self {
+        let states = system.state().len();
+        assert_eq!(
+            process_noise.shape(),
+            (states, states),
+            "Q must be square with one row per state"
+        );
+        assert!(measurement_noise.is_square(), "R must be square");
+
+        Self {
+            model: SampledModel {
+                system: RefCell::new(system),
+                integrator: RefCell::new(integrator),
+                timestep,
+            },
+            process_noise,
+            measurement_noise,
+            state: initial_state(states, inputs),
+        }
+    }
+
+    /// Sets the initial estimate and its error covariance.
+    pub fn initial_estimate(mut self, estimate: Vector, covariance: DMatrix<f64>) -> Self {
+        let states = self.state.estimate.len();
+        assert_eq!(estimate.len(), states, "Estimate has the wrong dimension");
+        assert_eq!(
+            covariance.shape(),
+            (states, states),
+            "Covariance has the wrong dimension"
+        );
+        self.state.estimate = estimate;
+        self.state.covariance = covariance;
+        self
+    }
+
+    /// The error covariance of the current estimate.
+    pub fn covariance(&self) -> &DMatrix<f64> {
+        &self.state.covariance
+    }
+
+    /// Propagates `state` through the model, returning the prior for instant `time`.
+    pub fn predict(&self, time: f64, state: &KalmanState) -> KalmanState {
+        let jacobian =
+            self.model
+                .state_jacobian(time - self.model.timestep, &state.estimate, &state.input);
+        let transition = (jacobian * self.model.timestep).exp();
+
+        KalmanState {
+            estimate: self.model.propagate(time, &state.estimate, &state.input),
+            covariance: &transition * &state.covariance * transition.transpose()
+                + &self.process_noise,
+            input: state.input.clone(),
+        }
+    }
+
+    /// Corrects the prior `state` with the measurement `output` taken while
+    /// `input` was applied.
+    pub fn correct(
+        &self,
+        time: f64,
+        state: &KalmanState,
+        input: &Vector,
+        output: &Vector,
+    ) -> KalmanState {
+        let h = self.model.output_jacobian(time, &state.estimate, input);
+        let p = &state.covariance;
+        let r = &self.measurement_noise;
+
+        let innovation = output - self.model.measure(time, &state.estimate, input);
+        let gain = p
+            * h.transpose()
+            * (&h * p * h.transpose() + r)
+                .try_inverse()
+                .expect("Innovation covariance is singular");
+
+        let n = state.estimate.len();
+        let ikh = DMatrix::identity(n, n) - &gain * &h;
+
+        KalmanState {
+            estimate: &state.estimate + &gain * innovation,
+            covariance: &ikh * p * ikh.transpose() + &gain * r * gain.transpose(),
+            input: input.clone(),
+        }
+    }
+}
+
+impl<Sys, Int> DiscreteSystem<Vector, KalmanState, Vector> for ExtendedKalmanFilter<Sys, Int>
+where
+    Sys: ContinuousSystem<Vector, Vector, Vector>,
+    Int: Integrator<Sys, Vector, Vector, Vector>,
+{
+    fn next_state(&self, time: f64, state: &KalmanState, input: &Vector) -> KalmanState {
+        let (inputs, outputs) = (state.input.len(), self.measurement_noise.nrows());
+        assert_eq!(
+            input.len(),
+            inputs + outputs,
+            "Filter input must stack the plant input and the measured output"
+        );
+        let (u, y) = split_input(input, inputs, outputs);
+
+        let prior = self.predict(time, state);
+        self.correct(time, &prior, &u, &y)
+    }
+
+    fn get_output(&self) -> Vector {
+        self.state.estimate.clone()
+    }
+
+    fn state(&self) -> &KalmanState {
+        &self.state
+    }
+
+    fn set_state(&mut self, new_state: &KalmanState) {
+        self.state = new_state.clone();
+    }
+
+    fn timestep(&self) -> f64 {
+        self.model.timestep
+    }
+}
+
+/// An unscented Kalman filter for a nonlinear `ContinuousSystem` sampled every `timestep`
+///
+/// Sigma points are drawn from the current estimate and propagated through
+/// the integrator and the system's output map, using the scaled unscented
+/// transform with parameters $\alpha$, $\beta$ and $\kappa$.
+///
+/// Its input is the plant input and the measured output stacked as $[u; y]$,
+/// and its output is the filtered estimate $\hat{x}_{k|k}$.
+pub struct UnscentedKalmanFilter<Sys, Int> {
+    model: SampledModel<Sys, Int>,
+    process_noise: DMatrix<f64>,
+    measurement_noise: DMatrix<f64>,
+    alpha: f64,
+    beta: f64,
+    kappa: f64,
+    state: KalmanState,
+}
+
+impl<Sys, Int> UnscentedKalmanFilter<Sys, Int>
+where
+    Sys: ContinuousSystem<Vector, Vector, Vector>,
+    Int: Integrator<Sys, Vector, Vector, Vector>,
+{
+    /// Creates a filter sampling `system`, which has `inputs` inputs, every
+    /// `timestep`, with process noise covariance $Q$ (accumulated over one
+    /// sample) and measurement noise covariance $R$.
+    pub fn new(
+        system: Sys,
+        integrator: Int,
+        timestep: f64,
+        inputs: usize,
+        process_noise: DMatrix<f64>,
+        measurement_noise: DMatrix<f64>,
+    ) -> Self {
+        let states = system.state().len();
+        assert_eq!(
+            process_noise.shape(),
+            (states, states),
+            "Q must be square with one row per state"
+        );
+        assert!(measurement_noise.is_square(), "R must be square");
+
+        Self {
+            model: SampledModel {
+                system: RefCell::new(system),
+                integrator: RefCell::new(integrator),
+                timestep,
+            },
+            process_noise,
+            measurement_noise,
+            alpha: 1e-3,
+            beta: 2.0,
+            kappa: 0.0,
+            state: initial_state(states, inputs),
+        }
+    }
+
+    /// Sets the spread ($\alpha$), prior knowledge ($\beta$) and secondary
+    /// scaling ($\kappa$) parameters of the unscented transform.
+    pub fn transform(mut self, alpha: f64, beta: f64, kappa: f64) -> Self {
+        self.alpha = alpha;
+        self.beta = beta;
+        self.kappa = kappa;
+        self
+    }
+
+    /// Sets the initial estimate and its error covariance.
+    pub fn initial_estimate(mut self, estimate: Vector, covariance: DMatrix<f64>) -> Self {
+        let states = self.state.estimate.len();
+        assert_eq!(estimate.len(), states, "Estimate has the wrong dimension");
+        assert_eq!(
+            covariance.shape(),
+            (states, states),
+            "Covariance has the wrong dimension"
+        );
+        self.state.estimate = estimate;
+        self.state.covariance = covariance;
+        self
+    }
+
+    /// The error covariance of the current estimate.
+    pub fn covariance(&self) -> &DMatrix<f64> {
+        &self.state.covariance
+    }
+
+    /// Sigma points of `(mean, covariance)`, with their mean and covariance weights.
+    fn sigma_points(
+        &self,
+        mean: &Vector,
+        covariance: &DMatrix<f64>,
+    ) -> (Vec<Vector>, Vec<f64>, Vec<f64>) {
+        let n = mean.len() as f64;
+        let lambda = self.alpha * self.alpha * (n + self.kappa) - n;
+
+        let root = Cholesky::new(covariance * (n + lambda))
+            .expect("Covariance is not positive definite")
+            .l();
+
+        let mut points = vec![mean.clone()];
+        for column in root.column_iter() {
+            points.push(mean + column);
+        }
+        for column in root.column_iter() {
+            points.push(mean - column);
+        }
+
+        let w = 0.5 / (n + lambda);
+        let mut mean_weights = vec![w; points.len()];
+        let mut covariance_weights = vec![w; points.len()];
+        mean_weights[0] = lambda / (n + lambda);
+        covariance_weights[0] = mean_weights[0] + 1.0 - self.alpha * self.alpha + self.beta;
+
+        (points, mean_weights, covariance_weights)
+    }
+
+    /// Propagates `state` through the model, returning the prior for instant `time`.
+    pub fn predict(&self, time: f64, state: &KalmanState) -> KalmanState {
+        let (points, wm, wc) = self.sigma_points(&state.estimate, &state.covariance);
+        let propagated: Vec<_> = points
+            .iter()
+            .map(|x| self.model.propagate(time, x, &state.input))
+            .collect();
+
+        let (estimate, covariance) = weighted_statistics(&propagated, &wm, &wc);
+
+        KalmanState {
+            estimate,
+            covariance: covariance + &self.process_noise,
+            input: state.input.clone(),
+        }
+    }
+
+    /// Corrects the prior `state` with the measurement `output` taken while
+    /// `input` was applied.
+    pub fn correct(
+        &self,
+        time: f64,
+        state: &KalmanState,
+        input: &Vector,
+        output: &Vector,
+    ) -> KalmanState {
+        let (points, wm, wc) = self.sigma_points(&state.estimate, &state.covariance);
+        let measured: Vec<_> = points
+            .iter()
+            .map(|x| self.model.measure(time, x, input))
+            .collect();
+
+        let (predicted, innovation_covariance) = weighted_statistics(&measured, &wm, &wc);
+        let innovation_covariance = innovation_covariance + &self.measurement_noise;
+
+        let mut cross = DMatrix::zeros(state.estimate.len(), output.len());
+        for ((x, y), w) in points.iter().zip(&measured).zip(&wc) {
+            cross += (x - &state.estimate) * (y - &predicted).transpose() * *w;
+        }
+
+        let gain = cross
+            * innovation_covariance
+                .clone()
+                .try_inverse()
+                .expect("Innovation covariance is singular");
+
+        KalmanState {
+            estimate: &state.estimate + &gain * (output - predicted),
+            covariance: &state.covariance - &gain * innovation_covariance * gain.transpose(),
+            input: input.clone(),
+        }
+    }
+}
+
+fn weighted_statistics(points: &[Vector], wm: &[f64], wc: &[f64]) -> (Vector, DMatrix<f64>) {
+    let mut mean = DVector::zeros(points[0].len());
+    for (x, w) in points.iter().zip(wm) {
+        mean += x * *w;
+    }
+
+    let mut covariance = DMatrix::zeros(mean.len(), mean.len());
+    for (x, w) in points.iter().zip(wc) {
+        let dx = x - &mean;
+        covariance += &dx * dx.transpose() * *w;
+    }
+
+    (mean, covariance)
+}
+
+impl<Sys, Int> DiscreteSystem<Vector, KalmanState, Vector> for UnscentedKalmanFilter<Sys, Int>
+where
+    Sys: ContinuousSystem<Vector, Vector, Vector>,
+    Int: Integrator<Sys, Vector, Vector, Vector>,
+{
+    fn next_state(&self, time: f64, state: &KalmanState, input: &Vector) -> KalmanState {
+        let (inputs, outputs) = (state.input.len(), self.measurement_noise.nrows());
+        assert_eq!(
+            input.len(),
+            inputs + outputs,
+            "Filter input must stack the plant input and the measured output"
+        );
+        let (u, y) = split_input(input, inputs, outputs);
+
+        let prior = self.predict(time, state);
+        self.correct(time, &prior, &u, &y)
+    }
+
+    fn get_output(&self) -> Vector {
+        self.state.estimate.clone()
+    }
+
+    fn state(&self) -> &KalmanState {
+        &self.state
+    }
+
+    fn set_state(&mut self, new_state: &KalmanState) {
+        self.state = new_state.clone();
+    }
+
+    fn timestep(&self) -> f64 {
+        self.model.timestep
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{continuous::integrator::RungeKutta4, system::System};
+    use nalgebra::{dmatrix, dvector};
+
+    // A damped pendulum measured by its angle
+    struct Pendulum {
+        state: Vector,
+    }
+
+    impl ContinuousSystem<Vector, Vector, Vector> for Pendulum {
+        fn get_derivative(&self, _time: f64, state: &Vector, input: &Vector) -> Vector {
+            dvector![state[1], -9.81 * state[0].sin() - 0.5 * state[1] + input[0]]
+        }
+
+        fn get_output(&self, _time: f64) -> Vector {
+            dvector![self.state[0]]
+        }
+
+        fn state(&self) -> &Vector {
+            &self.state
+        }
+
+        fn set_state(&mut self, new_state: &Vector) {
+            self.state.copy_from(new_state);
+        }
+
+        fn max_timestep(&self) -> f64 {
+            0.01
+        }
+    }
+
+    fn pendulum() -> Pendulum {
+        Pendulum {
+            state: dvector![0.0, 0.0],
+        }
+    }
+
+    /// Runs `filter` against a noiseless pendulum released from 1 rad, returning
+    /// the final estimation error.
+    fn track<F>(mut filter: F) -> f64
+    where
+        F: DiscreteSystem<Vector, KalmanState, Vector>,
+    {
+        let mut real = Pendulum {
+            state: dvector![1.0, 0.0],
+        }
+        .with_integrator(RungeKutta4);
+        let u = dvector![0.0];
+
+        let mut time = 0.0;
+        for k in 1..=200 {
+            while time < k as f64 * 0.05 - 1e-9 {
+                time += 0.01;
+                real.update(time, &u);
+            }
+            let y = real.get_output(time);
+            let next = filter.next_state(time, filter.state(), &dvector![u[0], y[0]]);
+            filter.set_state(&next);
+        }
+
+        let truth = real.get_output(time);
+        (filter.get_output()[0] - truth[0]).abs()
+    }
+
+    #[test]
+    fn test_sampled_model_matches_integrated_system() {
+        let model = SampledModel {
+            system: RefCell::new(pendulum()),
+            integrator: RefCell::new(RungeKutta4),
+            timestep: 0.1,
+        };
+        let x = model.propagate(0.1, &dvector![0.2, 0.0], &dvector![0.0]);
+
+        let mut real = Pendulum {
+            state: dvector![0.2, 0.0],
+        }
+        .with_integrator(RungeKutta4);
+        for k in 1..=10 {
+            real.update(k as f64 * 0.01, &dvector![0.0]);
+        }
+
+        assert!((x[0] - real.get_output(0.1)[0]).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_extended_kalman_filter_tracks_pendulum() {
+        let filter = ExtendedKalmanFilter::new(
+            pendulum(),
+            RungeKutta4,
+            0.05,
+            1,
+            dmatrix![1e-6, 0.0; 0.0, 1e-6],
+            dmatrix![1e-4],
+        );
+
+        assert!(track(filter) < 1e-3);
+    }
+
+    #[test]
+    fn test_unscented_kalman_filter_tracks_pendulum() {
+        let filter = UnscentedKalmanFilter::new(
+            pendulum(),
+            RungeKutta4,
+            0.05,
+            1,
+            dmatrix![1e-6, 0.0; 0.0, 1e-6],
+            dmatrix![1e-4],
+        )
+        .transform(0.5, 2.0, 0.0);
+
+        assert!(track(filter) < 1e-3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Filter input must stack the plant input and the measured output")]
+    fn test_extended_kalman_filter_rejects_short_input() {
+        let filter = ExtendedKalmanFilter::new(
+            pendulum(),
+            RungeKutta4,
+            0.05,
+            1,
+            dmatrix![1e-6, 0.0; 0.0, 1e-6],
+            dmatrix![1e-4],
+        );
+
+        filter.next_state(0.05, filter.state(), &dvector![0.0]);
+    }
+
+    #[test]
+    fn test_unscented_transform_of_linear_model_is_exact() {
+        let filter = UnscentedKalmanFilter::new(
+            pendulum(),
+            RungeKutta4,
+            0.05,
+            1,
+            dmatrix![0.0, 0.0; 0.0, 0.0],
+            dmatrix![1e-4],
+        );
+        let covariance = dmatrix![0.3, 0.1; 0.1, 0.2];
+        let (points, wm, wc) = filter.sigma_points(&dvector![1.0, -1.0], &covariance);
+        let (mean, recovered) = weighted_statistics(&points, &wm, &wc);
+
+        assert!((mean - dvector![1.0, -1.0]).norm() < 1e-9);
+        assert!((recovered - covariance).norm() < 1e-9);
+    }
+}
@@ -0,0 +1,251 @@
+use nalgebra::{DMatrix, DVector};
+
+use crate::{
+    continuous::ContinuousSystem,
+    discrete::DiscreteSystem,
+    linear::{DiscreteStateSpace, StateSpace},
+};
+
+/// A continuous-time Luenberger observer
+///
+/// $$\dot{\hat{x}} = A \hat{x} + B u + L (y - C \hat{x} - D u)$$
+///
+/// Its input is the plant input and the measured output stacked as $[u; y]$,
+/// and its output is the state estimate $\hat{x}$.
+pub struct Observer {
+    plant: StateSpace,
+    gain: DMatrix<f64>,
+    estimate: DVector<f64>,
+}
+
+/// A discrete-time Luenberger observer, in prediction form
+///
+/// $$\hat{x}_{k+1} = A \hat{x}_k + B u_k + L (y_k - C \hat{x}_k - D u_k)$$
+///
+/// Its input is the plant input and the measured output stacked as $[u; y]$,
+/// and its output is the state estimate $\hat{x}$.
+pub struct DiscreteObserver {
+    plant: DiscreteStateSpace,
+    gain: DMatrix<f64>,
+    estimate: DVector<f64>,
+}
+
+fn check_gain(gain: &DMatrix<f64>, states: usize, outputs: usize) {
+    assert_eq!(gain.nrows(), states, "L must have as many rows as A");
+    assert_eq!(
+        gain.ncols(),
+        outputs,
+        "L must have as many columns as C has rows"
+    );
+}
+
+fn innovation(
+    c: &DMatrix<f64>,
+    d: &DMatrix<f64>,
+    estimate: &DVector<f64>,
+    input: &DVector<f64>,
+) -> (DVector<f64>, DVector<f64>) {
+    let inputs = d.ncols();
+    let u = input.rows(0, inputs).into_owned();
+    let y = input.rows(inputs, c.nrows());
+
+    let residual = y - c * estimate - d * &u;
+    (u, residual)
+}
+
+impl Observer {
+    /// Creates an observer for `plant` with observer gain `gain`.
+    pub fn new(plant: &StateSpace, gain: DMatrix<f64>) -> Self {
+        check_gain(&gain, plant.states(), plant.outputs());
+
+        Self {
+            estimate: DVector::zeros(plant.states()),
+            plant: plant.clone(),
+            gain,
+        }
+    }
+
+    /// Sets the initial state estimate.
+    pub fn initial_estimate(mut self, estimate: DVector<f64>) -> Self {
+        assert_eq!(
+            estimate.len(),
+            self.plant.states(),
+            "Estimate has the wrong dimension"
+        );
+        self.estimate = estimate;
+        self
+    }
+}
+
+impl DiscreteObserver {
+    /// Creates an observer for `plant` with observer gain `gain`.
+    pub fn new(plant: &DiscreteStateSpace, gain: DMatrix<f64>) -> Self {
+        check_gain(&gain, plant.states(), plant.outputs());
+
+        Self {
+            estimate: DVector::zeros(plant.states()),
+            plant: plant.clone(),
+            gain,
+        }
+    }
+
+    /// Sets the initial state estimate.
+    pub fn initial_estimate(mut self, estimate: DVector<f64>) -> Self {
+        assert_eq!(
+            estimate.len(),
+            self.plant.states(),
+            "Estimate has the wrong dimension"
+        );
+        self.estimate = estimate;
+        self
+    }
+}
+
+impl ContinuousSystem<DVector<f64>, DVector<f64>, DVector<f64>> for Observer {
+    fn get_derivative(
+        &self,
+        _time: f64,
+        state: &DVector<f64>,
+        input: &DVector<f64>,
+    ) -> DVector<f64> {
+        let (u, residual) = innovation(&self.plant.c, &self.plant.d, state, input);
+        &self.plant.a * state + &self.plant.b * u + &self.gain * residual
+    }
+
+    fn get_output(&self, _time: f64) -> DVector<f64> {
+        self.estimate.clone()
+    }
+
+    fn state(&self) -> &DVector<f64> {
+        &self.estimate
+    }
+
+    fn set_state(&mut self, new_state: &DVector<f64>) {
+        self.estimate.copy_from(new_state);
+    }
+
+    fn max_timestep(&self) -> f64 {
+        ContinuousSystem::max_timestep(&self.plant)
+    }
+}
+
+impl DiscreteSystem<DVector<f64>, DVector<f64>, DVector<f64>> for DiscreteObserver {
+    fn next_state(&self, _time: f64, state: &DVector<f64>, input: &DVector<f64>) -> DVector<f64> {
+        let (u, residual) = innovation(&self.plant.c, &self.plant.d, state, input);
+        &self.plant.a * state + &self.plant.b * u + &self.gain * residual
+    }
+
+    fn get_output(&self) -> DVector<f64> {
+        self.estimate.clone()
+    }
+
+    fn state(&self) -> &DVector<f64> {
+        &self.estimate
+    }
+
+    fn set_state(&mut self, new_state: &DVector<f64>) {
+        self.estimate.copy_from(new_state);
+    }
+
+    fn timestep(&self) -> f64 {
+        self.plant.timestep()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        continuous::integrator::RungeKutta4,
+        system::{System, cloop::ClosedLoop, gain::MatrixGain, series::SeriesSystem},
+        utils::Param,
+    };
+    use nalgebra::{dmatrix, dvector};
+
+    fn double_integrator() -> StateSpace {
+        StateSpace::new(
+            dmatrix![0.0, 1.0; 0.0, 0.0],
+            dmatrix![0.0; 1.0],
+            dmatrix![1.0, 0.0],
+            dmatrix![0.0],
+        )
+        .max_timestep(0.01)
+    }
+
+    #[test]
+    fn test_discrete_observer_deadbeat() {
+        let plant = DiscreteStateSpace::new(
+            dmatrix![1.0, 1.0; 0.0, 1.0],
+            dmatrix![0.0; 1.0],
+            dmatrix![1.0, 0.0],
+            dmatrix![0.0],
+            1.0,
+        );
+        // Places both eigenvalues of A - LC at the origin
+        let mut observer = DiscreteObserver::new(&plant, dmatrix![2.0; 1.0]);
+
+        let mut x = dvector![3.0, -1.0];
+        let u = dvector![0.5];
+        for _ in 0..2 {
+            let y = &plant.c * &x;
+            let input = dvector![u[0], y[0]];
+            let next = observer.next_state(0.0, observer.state(), &input);
+            observer.set_state(&next);
+            x = &plant.a * &x + &plant.b * &u;
+        }
+
+        assert!((observer.get_output() - x).norm() < 1e-12);
+    }
+
+    #[test]
+    fn test_observer_converges() {
+        let plant = double_integrator();
+        let mut observer = Observer::new(&plant, dmatrix![4.0; 4.0]).with_integrator(RungeKutta4);
+        let mut real = plant
+            .initial_state(dvector![1.0, -0.5])
+            .with_integrator(RungeKutta4);
+
+        let u = dvector![0.2];
+        let mut time = 0.0;
+        while time < 8.0 {
+            real.update(time, &u);
+            let y = real.get_output(time);
+            observer.update(time, &dvector![u[0], y[0]]);
+            time += 0.01;
+        }
+
+        let y = real.get_output(time);
+        let estimate = observer.get_output(time);
+        // The measurement is held between events, so a bias of the order of `dt` remains
+        assert!((estimate[0] - y[0]).abs() < 1e-2);
+    }
+
+    #[test]
+    fn test_observer_based_controller() {
+        let base = double_integrator();
+        // The plant also exposes its own input, so the observer can see $[u; y]$
+        let plant = StateSpace::new(
+            base.a.clone(),
+            base.b.clone(),
+            dmatrix![0.0, 0.0; 1.0, 0.0],
+            dmatrix![1.0; 0.0],
+        )
+        .max_timestep(0.01)
+        .initial_state(dvector![1.0, 0.0]);
+
+        let observer = Observer::new(&base, dmatrix![4.0; 4.0]).with_integrator(RungeKutta4);
+        let feedback = MatrixGain::<DVector<f64>, DVector<f64>>::new(dmatrix![1.0, 2.0]);
+
+        let mut cloop = ClosedLoop::new(
+            plant.with_integrator(RungeKutta4),
+            SeriesSystem::new(observer, feedback),
+        );
+
+        let mut last = dvector![];
+        cloop.simulate(15.0, 0.01, Param::new(dvector![0.0]), &mut |x| {
+            last = x.output
+        });
+
+        assert!(last[1].abs() < 1e-2);
+    }
+}
@@ -0,0 +1,390 @@
+use nalgebra::{DMatrix, DVector};
+
+use crate::linear::DiscreteTransferFunction;
+
+/// Polynomial orders of an ARX or ARMAX model
+///
+/// $$A(q) y_k = B(q) u_{k - n_k} + C(q) e_k$$
+///
+/// with $A$ of order `na`, `nb` coefficients in $B$, $C$ of order `nc` (zero
+/// for ARX), and an input delay of `nk` samples.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Orders {
+    pub na: usize,
+    pub nb: usize,
+    pub nc: usize,
+    pub nk: usize,
+}
+
+impl Orders {
+    pub fn arx(na: usize, nb: usize, nk: usize) -> Self {
+        Self { na, nb, nc: 0, nk }
+    }
+
+    pub fn armax(na: usize, nb: usize, nc: usize, nk: usize) -> Self {
+        Self { na, nb, nc, nk }
+    }
+
+    /// Number of estimated parameters.
+    pub fn parameters(&self) -> usize {
+        self.na + self.nb + self.nc
+    }
+}
+
+/// An information criterion ranking models by fit and complexity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Criterion {
+    /// Akaike's information criterion.
+    Aic,
+    /// Akaike's final prediction error.
+    Fpe,
+}
+
+/// A model identified from input/output data
+#[derive(Clone, Debug)]
+pub struct IdentifiedModel {
+    /// The transfer function from the input to the output, $B q^{-n_k} / A$.
+    pub model: DiscreteTransferFunction,
+    /// Coefficients $1, c_1, \dots, c_{n_c}$ of the noise model.
+    pub noise: Vec<f64>,
+    pub orders: Orders,
+    /// Mean squared one-step prediction error.
+    pub loss: f64,
+    /// Number of prediction errors the loss is averaged over.
+    pub samples: usize,
+}
+
+impl IdentifiedModel {
+    /// $N \ln V + 2 d$, for a loss $V$ over $N$ samples and $d$ parameters.
+    pub fn aic(&self) -> f64 {
+        let n = self.samples as f64;
+        n * self.loss.ln() + 2.0 * self.orders.parameters() as f64
+    }
+
+    /// $V \frac{1 + d / N}{1 - d / N}$, for a loss $V$ over $N$ samples and $d$
+    /// parameters.
+    pub fn fpe(&self) -> f64 {
+        let ratio = self.orders.parameters() as f64 / self.samples as f64;
+        self.loss * (1.0 + ratio) / (1.0 - ratio)
+    }
+
+    pub fn criterion(&self, criterion: Criterion) -> f64 {
+        match criterion {
+            Criterion::Aic => self.aic(),
+            Criterion::Fpe => self.fpe(),
+        }
+    }
+}
+
+/// Logged input/output samples of a single-input single-output plant,
+/// taken every `timestep`.
+#[derive(Clone, Debug)]
+pub struct IoData {
+    pub inputs: Vec<f64>,
+    pub outputs: Vec<f64>,
+    pub timestep: f64,
+}
+
+/// Parameters of an ARMAX model, split by polynomial.
+struct Polynomials {
+    a: Vec<f64>,
+    b: Vec<f64>,
+    c: Vec<f64>,
+}
+
+impl Polynomials {
+    fn from_parameters(theta: &DVector<f64>, orders: Orders) -> Self {
+        let Orders { na, nb, nc, .. } = orders;
+        Self {
+            a: theta.rows(0, na).iter().copied().collect(),
+            b: theta.rows(na, nb).iter().copied().collect(),
+            c: theta.rows(na + nb, nc).iter().copied().collect(),
+        }
+    }
+}
+
+impl IoData {
+    pub fn new(inputs: Vec<f64>, outputs: Vec<f64>, timestep: f64) -> Self {
+        assert_eq!(
+            inputs.len(),
+            outputs.len(),
+            "There must be one output per input"
+        );
+        Self {
+            inputs,
+            outputs,
+            timestep,
+        }
+    }
+
+    /// First sample whose regressors are all available.
+    fn start(&self, orders: Orders) -> usize {
+        orders
+            .na
+            .max(orders.nc)
+            .max((orders.nb + orders.nk).saturating_sub(1))
+    }
+
+    /// The regressors of sample `k`, given the past prediction errors.
+    fn regressors(&self, k: usize, orders: Orders, errors: &[f64]) -> DVector<f64> {
+        let Orders { na, nb, nc, nk } = orders;
+        DVector::from_iterator(
+            na + nb + nc,
+            (1..=na)
+                .map(|i| -self.outputs[k - i])
+                .chain((0..nb).map(|i| self.inputs[k - nk - i]))
+                .chain((1..=nc).map(|i| errors[k - i])),
+        )
+    }
+
+    /// The one-step prediction errors of a model, zero before `start`.
+    fn prediction_errors(&self, theta: &DVector<f64>, orders: Orders) -> Vec<f64> {
+        let mut errors = vec![0.0; self.outputs.len()];
+        for k in self.start(orders)..self.outputs.len() {
+            errors[k] = self.outputs[k] - self.regressors(k, orders, &errors).dot(theta);
+        }
+        errors
+    }
+
+    fn loss(&self, errors: &[f64], orders: Orders) -> f64 {
+        let used = &errors[self.start(orders)..];
+        used.iter().map(|e| e * e).sum::<f64>() / used.len() as f64
+    }
+
+    /// Solves the linear least-squares problem over the regressors built
+    /// with `errors`, or `None` if they are not informative enough.
+    fn least_squares(&self, orders: Orders, errors: &[f64]) -> Option<DVector<f64>> {
+        let start = self.start(orders);
+        let rows = self.outputs.len().checked_sub(start)?;
+        if rows <= orders.parameters() {
+            return None;
+        }
+
+        let phi = DMatrix::from_fn(rows, orders.parameters(), |r, c| {
+            self.regressors(start + r, orders, errors)[c]
+        });
+        let y = DVector::from_fn(rows, |r, _| self.outputs[start + r]);
+        let normal = phi.transpose() * &phi;
+        normal.cholesky().map(|c| c.solve(&(phi.transpose() * y)))
+    }
+
+    fn identified(&self, theta: &DVector<f64>, orders: Orders) -> IdentifiedModel {
+        let errors = self.prediction_errors(theta, orders);
+        let Polynomials { a, b, c } = Polynomials::from_parameters(theta, orders);
+
+        let numerator = std::iter::repeat_n(0.0, orders.nk).chain(b).collect();
+        let denominator = std::iter::once(1.0).chain(a).collect();
+
+        IdentifiedModel {
+            model: DiscreteTransferFunction::new(numerator, denominator, self.timestep),
+            noise: std::iter::once(1.0).chain(c).collect(),
+            orders,
+            loss: self.loss(&errors, orders),
+            samples: self.outputs.len() - self.start(orders),
+        }
+    }
+
+    /// Fits an ARX model by linear least squares.
+    ///
+    /// Returns `None` if there are too few samples or the input does not
+    /// excite the plant enough.
+    pub fn arx(&self, na: usize, nb: usize, nk: usize) -> Option<IdentifiedModel> {
+        let orders = Orders::arx(na, nb, nk);
+        let theta = self.least_squares(orders, &[])?;
+        Some(self.identified(&theta, orders))
+    }
+
+    /// Fits an ARMAX model by minimizing its prediction errors
+    ///
+    /// The parameters start from a few rounds of extended least squares,
+    /// which regress on the errors of the previous round, then are refined
+    /// by Gauss–Newton iterations on the prediction-error loss.
+    pub fn armax(&self, na: usize, nb: usize, nc: usize, nk: usize) -> Option<IdentifiedModel> {
+        let orders = Orders::armax(na, nb, nc, nk);
+        let arx = Orders::arx(na, nb, nk);
+
+        let mut theta = self.least_squares(arx, &[])?;
+        theta = theta.clone().insert_rows(na + nb, nc, 0.0);
+        for _ in 0..10 {
+            let errors = self.prediction_errors(&theta, orders);
+            theta = self.least_squares(orders, &errors)?;
+        }
+
+        let start = self.start(orders);
+        let mut errors = self.prediction_errors(&theta, orders);
+        let mut loss = self.loss(&errors, orders);
+
+        for _ in 0..50 {
+            // The gradient of the prediction is the regressor filtered by 1 / C
+            let c = Polynomials::from_parameters(&theta, orders).c;
+            let mut gradients: Vec<DVector<f64>> = Vec::with_capacity(self.outputs.len());
+            for k in 0..self.outputs.len() {
+                let gradient = if k < start {
+                    DVector::zeros(orders.parameters())
+                } else {
+                    c.iter()
+                        .enumerate()
+                        .fold(self.regressors(k, orders, &errors), |g, (i, ci)| {
+                            g - &gradients[k - i - 1] * *ci
+                        })
+                };
+                gradients.push(gradient);
+            }
+
+            let psi = DMatrix::from_fn(self.outputs.len() - start, orders.parameters(), |r, c| {
+                gradients[start + r][c]
+            });
+            let e = DVector::from_column_slice(&errors[start..]);
+            let step = (psi.transpose() * &psi)
+                .cholesky()?
+                .solve(&(psi.transpose() * e));
+
+            // Halve the step until the loss decreases
+            let mut scale = 1.0;
+            let improved = loop {
+                let candidate = &theta + &step * scale;
+                let candidate_errors = self.prediction_errors(&candidate, orders);
+                let candidate_loss = self.loss(&candidate_errors, orders);
+                if candidate_loss.is_finite() && candidate_loss < loss {
+                    break Some((candidate, candidate_errors, candidate_loss));
+                }
+                scale *= 0.5;
+                if scale < 1e-6 {
+                    break None;
+                }
+            };
+
+            let Some((candidate, candidate_errors, candidate_loss)) = improved else {
+                break;
+            };
+            let converged = loss - candidate_loss < 1e-12 * loss;
+            (theta, errors, loss) = (candidate, candidate_errors, candidate_loss);
+            if converged {
+                break;
+            }
+        }
+
+        Some(self.identified(&theta, orders))
+    }
+
+    /// Fits every candidate, as ARX when `nc` is zero and ARMAX otherwise,
+    /// and keeps the one minimizing `criterion`.
+    pub fn select(
+        &self,
+        candidates: impl IntoIterator<Item = Orders>,
+        criterion: Criterion,
+    ) -> Option<IdentifiedModel> {
+        candidates
+            .into_iter()
+            .filter_map(|o| {
+                if o.nc == 0 {
+                    self.arx(o.na, o.nb, o.nk)
+                } else {
+                    self.armax(o.na, o.nb, o.nc, o.nk)
+                }
+            })
+            .min_by(|a, b| a.criterion(criterion).total_cmp(&b.criterion(criterion)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        discrete::{DiscreteSystem, holder::ZeroOrderHold},
+        system::System,
+        utils::Rng,
+    };
+
+    /// Data from `A y = B u + C e`, driven by a random binary input.
+    fn generate(
+        plant: &DiscreteTransferFunction,
+        noise: &[f64],
+        std_dev: f64,
+        seed: u64,
+    ) -> IoData {
+        let mut rng = Rng::new(seed);
+        let count = 2000;
+        let inputs: Vec<f64> = (0..count)
+            .map(|_| if rng.uniform() < 0.5 { -1.0 } else { 1.0 })
+            .collect();
+        let white: Vec<f64> = (0..count).map(|_| std_dev * rng.normal()).collect();
+
+        let disturbance =
+            DiscreteTransferFunction::new(noise.to_vec(), plant.denominator().to_vec(), 1.0);
+        let outputs = plant
+            .response(&inputs)
+            .iter()
+            .zip(disturbance.response(&white))
+            .map(|(y, v)| y + v)
+            .collect();
+
+        IoData::new(inputs, outputs, 1.0)
+    }
+
+    fn second_order() -> DiscreteTransferFunction {
+        DiscreteTransferFunction::new(vec![0.0, 1.0, 0.5], vec![1.0, -1.5, 0.7], 1.0)
+    }
+
+    #[test]
+    fn test_arx_recovers_plant() {
+        let data = generate(&second_order(), &[1.0], 0.1, 1);
+        let identified = data.arx(2, 2, 1).unwrap();
+
+        let model = &identified.model;
+        for (a, b) in model.denominator().iter().zip(second_order().denominator()) {
+            assert!((a - b).abs() < 0.01);
+        }
+        for (a, b) in model.numerator().iter().zip(second_order().numerator()) {
+            assert!((a - b).abs() < 0.02);
+        }
+        assert!((identified.loss - 0.01).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_order_selection() {
+        let data = generate(&second_order(), &[1.0], 0.1, 5);
+        let candidates = (1..=4).map(|n| Orders::arx(n, n, 1));
+
+        for criterion in [Criterion::Aic, Criterion::Fpe] {
+            let best = data.select(candidates.clone(), criterion).unwrap();
+            assert_eq!(best.orders, Orders::arx(2, 2, 1));
+        }
+        assert!(data.arx(2000, 2, 1).is_none());
+    }
+
+    #[test]
+    fn test_armax_recovers_noise_model() {
+        // Colored noise biases ARX, but not ARMAX
+        let data = generate(&second_order(), &[1.0, 0.8], 0.3, 3);
+        let arx = data.arx(2, 2, 1).unwrap();
+        let armax = data.armax(2, 2, 1, 1).unwrap();
+
+        let error = |m: &IdentifiedModel| {
+            m.model
+                .denominator()
+                .iter()
+                .zip(second_order().denominator())
+                .map(|(a, b)| (a - b).abs())
+                .fold(0.0, f64::max)
+        };
+        assert!(error(&armax) < 0.02);
+        assert!(error(&arx) > 2.0 * error(&armax));
+        assert!((armax.noise[1] - 0.8).abs() < 0.05);
+        assert!((armax.loss - 0.09).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_identified_model_simulates() {
+        // The identified model runs as a discrete system on the data's input
+        let data = generate(&second_order(), &[1.0], 0.0, 4);
+        let identified = data.arx(2, 2, 1).unwrap();
+
+        let mut held = identified.model.with_holder(ZeroOrderHold::new());
+        for (k, (u, y)) in data.inputs.iter().zip(&data.outputs).enumerate() {
+            let time = (k + 1) as f64;
+            held.update(time, u);
+            assert!((held.get_output(time) - y).abs() < 1e-8);
+        }
+    }
+}
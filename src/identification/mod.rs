@@ -0,0 +1,11 @@
+pub mod arx;
+pub mod rls;
+pub mod step;
+pub mod subspace;
+
+pub use self::{
+    arx::{Criterion, IdentifiedModel, IoData, Orders},
+    rls::{RecursiveLeastSquares, RlsState},
+    step::StepData,
+    subspace::{MimoData, SubspaceModel},
+};
@@ -0,0 +1,198 @@
+use nalgebra::{DMatrix, DVector};
+
+use crate::discrete::DiscreteSystem;
+
+/// The estimate and its scaled covariance carried by a recursive least
+/// squares estimator between samples.
+#[derive(Clone, Debug)]
+pub struct RlsState {
+    pub parameters: DVector<f64>,
+    pub covariance: DMatrix<f64>,
+}
+
+/// A recursive least squares estimator with exponential forgetting, for
+/// models linear in their parameters
+///
+/// $$y_k = \varphi_k^T \theta + e_k$$
+///
+/// Its input is the regressor and the measured output stacked as
+/// $[\varphi; y]$, and its output is the estimate $\hat{\theta}_k$. A
+/// forgetting factor $\lambda < 1$ weighs a sample $j$ steps old by
+/// $\lambda^j$, so the estimate keeps tracking parameters that drift, as
+/// self-tuning regulators need.
+#[derive(Clone, Debug)]
+pub struct RecursiveLeastSquares {
+    forgetting_factor: f64,
+    state: RlsState,
+    timestep: f64,
+}
+
+impl RecursiveLeastSquares {
+    /// Creates an estimator of `parameters` parameters, starting from zero
+    /// with a covariance of $10^3 I$ so that the first samples dominate.
+    pub fn new(parameters: usize, timestep: f64) -> Self {
+        assert!(parameters > 0, "At least one parameter must be estimated");
+        Self {
+            forgetting_factor: 1.0,
+            state: RlsState {
+                parameters: DVector::zeros(parameters),
+                covariance: DMatrix::identity(parameters, parameters) * 1e3,
+            },
+            timestep,
+        }
+    }
+
+    /// Sets the forgetting factor $\lambda \in (0, 1]$. One gives ordinary
+    /// least squares, and typical values for tracking are 0.95 to 0.999.
+    pub fn forgetting_factor(mut self, forgetting_factor: f64) -> Self {
+        assert!(
+            forgetting_factor > 0.0 && forgetting_factor <= 1.0,
+            "Forgetting factor must be in (0, 1]"
+        );
+        self.forgetting_factor = forgetting_factor;
+        self
+    }
+
+    /// Sets the initial estimate and its covariance.
+    pub fn initial_estimate(mut self, parameters: DVector<f64>, covariance: DMatrix<f64>) -> Self {
+        let n = self.parameters();
+        assert_eq!(parameters.len(), n, "Estimate has the wrong dimension");
+        assert_eq!(
+            covariance.shape(),
+            (n, n),
+            "Covariance has the wrong dimension"
+        );
+        self.state.parameters = parameters;
+        self.state.covariance = covariance;
+        self
+    }
+
+    /// Number of estimated parameters.
+    pub fn parameters(&self) -> usize {
+        self.state.parameters.len()
+    }
+
+    /// The covariance of the current estimate, up to the noise variance.
+    pub fn covariance(&self) -> &DMatrix<f64> {
+        &self.state.covariance
+    }
+
+    /// Corrects `state` with the measurement `output` of regressor `regressor`.
+    pub fn correct(&self, state: &RlsState, regressor: &DVector<f64>, output: f64) -> RlsState {
+        let lambda = self.forgetting_factor;
+        let p = &state.covariance;
+
+        let p_phi = p * regressor;
+        let gain = &p_phi / (lambda + regressor.dot(&p_phi));
+        let error = output - regressor.dot(&state.parameters);
+
+        let covariance = (p - &gain * p_phi.transpose()) / lambda;
+        RlsState {
+            parameters: &state.parameters + &gain * error,
+            // Keep it symmetric against rounding
+            covariance: (&covariance + covariance.transpose()) * 0.5,
+        }
+    }
+}
+
+impl DiscreteSystem<DVector<f64>, RlsState, DVector<f64>> for RecursiveLeastSquares {
+    fn next_state(&self, _time: f64, state: &RlsState, input: &DVector<f64>) -> RlsState {
+        let n = self.parameters();
+        assert_eq!(
+            input.len(),
+            n + 1,
+            "Input must stack the regressor and the output"
+        );
+        self.correct(state, &input.rows(0, n).into_owned(), input[n])
+    }
+
+    fn get_output(&self) -> DVector<f64> {
+        self.state.parameters.clone()
+    }
+
+    fn state(&self) -> &RlsState {
+        &self.state
+    }
+
+    fn set_state(&mut self, new_state: &RlsState) {
+        self.state = new_state.clone();
+    }
+
+    fn timestep(&self) -> f64 {
+        self.timestep
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::dvector;
+
+    use super::*;
+    use crate::{discrete::holder::ZeroOrderHold, system::System, utils::Rng};
+
+    /// Runs `estimator` on y_k = -a y_(k-1) + b u_(k-1) + noise, with `a`
+    /// and `b` given per step.
+    fn estimate_first_order(
+        estimator: &mut RecursiveLeastSquares,
+        steps: usize,
+        parameters: impl Fn(usize) -> (f64, f64),
+        noise: f64,
+    ) -> Vec<DVector<f64>> {
+        let mut rng = Rng::new(3);
+        let (mut y, mut u) = (0.0, 0.0);
+        let mut estimates = vec![];
+        for k in 0..steps {
+            let (a, b) = parameters(k);
+            let next = -a * y + b * u + noise * rng.normal();
+            let regressor = dvector![-y, u];
+            y = next;
+            u = rng.normal();
+
+            let state = estimator.next_state(k as f64, estimator.state(), &regressor.push(y));
+            estimator.set_state(&state);
+            estimates.push(estimator.get_output());
+        }
+        estimates
+    }
+
+    #[test]
+    fn test_converges_to_arx_parameters() {
+        let mut rls = RecursiveLeastSquares::new(2, 1.0);
+        let estimates = estimate_first_order(&mut rls, 500, |_| (-0.8, 0.5), 0.05);
+
+        let last = &estimates[estimates.len() - 1];
+        assert!((last - dvector![-0.8, 0.5]).norm() < 0.02);
+        // The covariance shrinks as evidence accumulates
+        assert!(rls.covariance().trace() < 0.1);
+    }
+
+    #[test]
+    fn test_forgetting_tracks_parameter_change() {
+        let change = |k: usize| if k < 300 { (-0.8, 0.5) } else { (-0.5, 1.0) };
+
+        let mut forgetting = RecursiveLeastSquares::new(2, 1.0).forgetting_factor(0.95);
+        let tracked = estimate_first_order(&mut forgetting, 600, change, 0.01);
+        let mut plain = RecursiveLeastSquares::new(2, 1.0);
+        let averaged = estimate_first_order(&mut plain, 600, change, 0.01);
+
+        let target = dvector![-0.5, 1.0];
+        assert!((&tracked[599] - &target).norm() < 0.02);
+        assert!((&averaged[599] - &target).norm() > 0.1);
+    }
+
+    #[test]
+    fn test_held_estimator() {
+        // y = 2 φ1 - φ2, exactly, with an informative initial guess
+        let rls = RecursiveLeastSquares::new(2, 0.1)
+            .initial_estimate(dvector![1.0, 0.0], DMatrix::identity(2, 2) * 100.0);
+        let mut held = rls.with_holder(ZeroOrderHold::new());
+        assert!(held.has_feedthrough());
+
+        for k in 1..=10 {
+            let time = k as f64 * 0.1;
+            let (p1, p2) = ((k as f64).sin(), (k as f64 * 0.7).cos());
+            held.update(time, &dvector![p1, p2, 2.0 * p1 - p2]);
+        }
+        assert!((held.get_output(1.0) - dvector![2.0, -1.0]).norm() < 1e-2);
+    }
+}
@@ -0,0 +1,328 @@
+use std::{fs, io, path::Path};
+
+use nalgebra::{DVector, dvector};
+
+use crate::{
+    linear::Fopdt,
+    optimization::{Bounds, Minimizer, NelderMead},
+    system::Sample,
+    utils::{csv_lines, invalid_data, parse_cells},
+};
+
+/// A recorded response to a step of the input
+#[derive(Clone, Debug)]
+pub struct StepData {
+    pub times: Vec<f64>,
+    pub outputs: Vec<f64>,
+    /// The instant the input stepped at.
+    pub step_time: f64,
+    /// The size of the input step.
+    pub step_size: f64,
+}
+
+impl StepData {
+    pub fn new(times: Vec<f64>, outputs: Vec<f64>, step_time: f64, step_size: f64) -> Self {
+        assert_eq!(
+            times.len(),
+            outputs.len(),
+            "There must be one output per instant"
+        );
+        assert!(times.len() >= 2, "A response needs at least two samples");
+        assert!(step_size != 0.0, "The step must not be zero");
+
+        Self {
+            times,
+            outputs,
+            step_time,
+            step_size,
+        }
+    }
+
+    /// Extracts the step from the inputs of a trace
+    ///
+    /// An input given at a sample applies since the previous one, as in
+    /// `System::simulate`, so the step starts at the sample before the input
+    /// first changes. If the input never changes, the trace is taken to be
+    /// the response of a system at rest to a step from zero at its start.
+    ///
+    /// Returns `None` if the trace holds fewer than two samples or its input
+    /// ends where it started, as a pulse does.
+    pub fn from_samples(samples: &[Sample<f64, f64>]) -> Option<Self> {
+        if samples.len() < 2 {
+            return None;
+        }
+        let first = samples[0].input;
+        let last = samples[samples.len() - 1].input;
+
+        let (step_time, step_size) = match samples.iter().position(|s| s.input != first) {
+            Some(i) => (samples[i - 1].instant, last - first),
+            None => (samples[0].instant, first),
+        };
+        if step_size == 0.0 {
+            return None;
+        }
+
+        Some(Self::new(
+            samples.iter().map(|s| s.instant).collect(),
+            samples.iter().map(|s| s.output).collect(),
+            step_time,
+            step_size,
+        ))
+    }
+
+    /// Reads a trace from CSV `text`, with one line per sample holding the
+    /// instant, the input and the output, as in `from_samples`. A header
+    /// line is skipped, as are blank lines and lines starting with `#`.
+    pub fn from_csv_str(text: &str) -> io::Result<Self> {
+        let mut lines = csv_lines(text).peekable();
+        if lines.peek().is_some_and(|line| parse_cells(line).is_err()) {
+            lines.next();
+        }
+
+        let mut samples = Vec::new();
+        for line in lines {
+            match parse_cells(line)?[..] {
+                [Some(instant), Some(input), Some(output)] => samples.push(Sample {
+                    instant,
+                    input,
+                    output,
+                }),
+                _ => {
+                    return Err(invalid_data(
+                        "Each line must hold an instant, an input and an output",
+                    ));
+                }
+            }
+        }
+
+        if samples.windows(2).any(|w| w[1].instant <= w[0].instant) {
+            return Err(invalid_data("Instants must be increasing"));
+        }
+        Self::from_samples(&samples).ok_or_else(|| {
+            invalid_data("A response needs at least two samples and a step of the input")
+        })
+    }
+
+    /// Reads a trace from the CSV file at `path`, as in `from_csv_str`.
+    pub fn from_csv(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_csv_str(&fs::read_to_string(path)?)
+    }
+
+    /// The output before the step, averaged over the samples up to it.
+    pub fn initial_value(&self) -> f64 {
+        let before: Vec<f64> = self
+            .times
+            .iter()
+            .zip(&self.outputs)
+            .take_while(|(t, _)| **t <= self.step_time)
+            .map(|(_, y)| *y)
+            .collect();
+
+        if before.is_empty() {
+            self.outputs[0]
+        } else {
+            before.iter().sum::<f64>() / before.len() as f64
+        }
+    }
+
+    /// The settled output, averaged over the last 5% of the samples.
+    pub fn final_value(&self) -> f64 {
+        let count = (self.outputs.len() / 20).max(1);
+        self.outputs[self.outputs.len() - count..]
+            .iter()
+            .sum::<f64>()
+            / count as f64
+    }
+
+    /// The first instant after the step the output crosses `fraction` of its
+    /// change, interpolating between samples.
+    fn crossing(&self, fraction: f64) -> Option<f64> {
+        let initial = self.initial_value();
+        let change = self.final_value() - initial;
+        let reached = |y: f64| (y - initial) / change;
+
+        let samples: Vec<(f64, f64)> = self
+            .times
+            .iter()
+            .zip(&self.outputs)
+            .map(|(t, y)| (*t, reached(*y)))
+            .filter(|(t, _)| *t >= self.step_time)
+            .collect();
+
+        samples.windows(2).find_map(|w| {
+            let ((t0, r0), (t1, r1)) = (w[0], w[1]);
+            (r0 < fraction && r1 >= fraction).then(|| t0 + (t1 - t0) * (fraction - r0) / (r1 - r0))
+        })
+    }
+}
+
+impl Fopdt {
+    /// Fits a model to a step response with Smith's two-point method, from
+    /// the instants the output reaches 28.3% and 63.2% of its change.
+    ///
+    /// Returns `None` if the output does not change or never crosses them.
+    pub fn two_point(data: &StepData) -> Option<Self> {
+        let change = data.final_value() - data.initial_value();
+        if change == 0.0 {
+            return None;
+        }
+
+        let t28 = data.crossing(0.283)?;
+        let t63 = data.crossing(0.632)?;
+        let time_constant = 1.5 * (t63 - t28);
+        if time_constant <= 0.0 {
+            return None;
+        }
+
+        Some(Self::new(
+            change / data.step_size,
+            time_constant,
+            (t63 - time_constant - data.step_time).max(0.0),
+        ))
+    }
+
+    /// Fits a model to a step response by least squares, starting from
+    /// `two_point`.
+    pub fn fit(data: &StepData) -> Option<Self> {
+        let initial = Self::two_point(data)?;
+        let y0 = data.initial_value();
+
+        let duration = data.times[data.times.len() - 1] - data.step_time;
+        let bounds = Bounds::new(
+            dvector![f64::NEG_INFINITY, 1e-9 * duration, 0.0],
+            dvector![f64::INFINITY, f64::INFINITY, duration],
+        );
+
+        let cost = |p: &DVector<f64>| {
+            let model = Self::new(p[0], p[1], p[2]);
+            data.times
+                .iter()
+                .zip(&data.outputs)
+                .map(|(t, y)| {
+                    let predicted = y0 + data.step_size * model.step_response(t - data.step_time);
+                    (y - predicted).powi(2)
+                })
+                .sum::<f64>()
+        };
+
+        let minimum = NelderMead::default().tolerance(1e-12).minimize(
+            &mut |p| cost(p),
+            &dvector![initial.gain, initial.time_constant, initial.dead_time],
+            &bounds,
+        );
+        let p = minimum.x;
+        Some(Self::new(p[0], p[1], p[2]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        system::{System, series::SeriesSystem},
+        utils::{Param, Rng},
+    };
+
+    fn record(
+        system: &mut impl System<Input = f64, Output = f64>,
+        input: Param<f64>,
+    ) -> Vec<Sample<f64, f64>> {
+        let mut samples = vec![];
+        system.simulate(20.0, 0.05, input, &mut |s| samples.push(s));
+        samples
+    }
+
+    #[test]
+    fn test_recovers_fopdt_plant() {
+        let samples = record(&mut Fopdt::new(2.0, 1.5, 0.4), Param::new(0.5));
+        let data = StepData::from_samples(&samples).unwrap();
+        assert_eq!((data.step_time, data.step_size), (0.0, 0.5));
+
+        let rough = Fopdt::two_point(&data).unwrap();
+        assert!((rough.gain - 2.0).abs() < 1e-3);
+        assert!((rough.time_constant - 1.5).abs() < 0.05);
+        assert!((rough.dead_time - 0.4).abs() < 0.05);
+
+        let fitted = Fopdt::fit(&data).unwrap();
+        assert!((fitted.gain - 2.0).abs() < 1e-6);
+        assert!((fitted.time_constant - 1.5).abs() < 1e-4);
+        assert!((fitted.dead_time - 0.4).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_step_from_inputs() {
+        // The input of 2 given at t = 2.05 applies since t = 2
+        let input = Param::<f64>::new(0.0).step(2.0, 2.01);
+        let data = StepData::from_samples(&record(&mut Fopdt::new(1.0, 1.0, 0.5), input)).unwrap();
+        assert!((data.step_time - 2.0).abs() < 1e-9);
+        assert_eq!(data.step_size, 2.0);
+
+        let fitted = Fopdt::fit(&data).unwrap();
+        assert!((fitted.gain - 1.0).abs() < 1e-6);
+        assert!((fitted.time_constant - 1.0).abs() < 1e-4);
+        assert!((fitted.dead_time - 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_higher_order_plant_from_noisy_csv() {
+        // Three unit lags, measured with noise and exported to CSV
+        let mut plant = SeriesSystem::new(
+            Fopdt::new(1.0, 1.0, 0.0),
+            SeriesSystem::new(Fopdt::new(1.0, 1.0, 0.0), Fopdt::new(1.0, 1.0, 0.0)),
+        );
+        let mut rng = Rng::new(5);
+        let mut csv = String::from("time,input,output\n");
+        for s in record(&mut plant, Param::new(1.0)) {
+            let noisy = s.output + 0.002 * rng.normal();
+            csv.push_str(&format!("{},{},{}\n", s.instant, s.input, noisy));
+        }
+
+        let data = StepData::from_csv_str(&csv).unwrap();
+        let fitted = Fopdt::fit(&data).unwrap();
+
+        // A fit of 1 / (s + 1)^3 lumps the faster lags into a dead time
+        assert!((fitted.gain - 1.0).abs() < 0.01);
+        assert!(fitted.time_constant > 1.5 && fitted.time_constant < 3.0);
+        assert!(fitted.dead_time > 0.5 && fitted.dead_time < 1.5);
+
+        // better than the two-point estimate it starts from
+        let y0 = data.initial_value();
+        let sse = |model: &Fopdt| {
+            data.times
+                .iter()
+                .zip(&data.outputs)
+                .map(|(t, y)| (y - y0 - model.step_response(*t)).powi(2))
+                .sum::<f64>()
+        };
+        assert!(sse(&fitted) < 0.8 * sse(&Fopdt::two_point(&data).unwrap()));
+    }
+
+    #[test]
+    fn test_invalid_csv() {
+        assert!(StepData::from_csv_str("0,0,0\n1,2\n").is_err());
+        assert!(StepData::from_csv_str("0,0,0\n1,0,0\n").is_err());
+        assert!(StepData::from_csv_str("1,1,0\n0,1,1\n").is_err());
+
+        // A pulse comes back to where it started, so it has no step size
+        let pulse = StepData::from_csv_str("0,0,0\n1,1,0\n2,0,0.5\n")
+            .err()
+            .unwrap();
+        assert_eq!(pulse.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_pulse_has_no_step() {
+        let pulse: Vec<_> = [0.0, 1.0, 0.0]
+            .iter()
+            .enumerate()
+            .map(|(i, &input)| Sample {
+                instant: i as f64,
+                input,
+                output: 0.0,
+            })
+            .collect();
+
+        assert!(StepData::from_samples(&pulse).is_none());
+        assert!(StepData::from_samples(&pulse[..1]).is_none());
+    }
+}
@@ -0,0 +1,283 @@
+use nalgebra::{DMatrix, DVector};
+
+use crate::linear::{DiscreteStateSpace, lti::stack};
+
+/// Logged input/output samples of a multivariable plant, taken every
+/// `timestep`.
+#[derive(Clone, Debug)]
+pub struct MimoData {
+    pub inputs: Vec<DVector<f64>>,
+    pub outputs: Vec<DVector<f64>>,
+    pub timestep: f64,
+}
+
+/// A state-space model identified by a subspace method
+#[derive(Clone, Debug)]
+pub struct SubspaceModel {
+    pub model: DiscreteStateSpace,
+    /// Singular values of the projected future outputs, in decreasing
+    /// order. The model order is the number of them clearly above the rest.
+    pub singular_values: Vec<f64>,
+}
+
+/// Stacks `blocks` consecutive samples from `first` into each of `columns`
+/// columns, shifting by one sample per column.
+fn block_hankel(
+    signal: &[DVector<f64>],
+    first: usize,
+    blocks: usize,
+    columns: usize,
+) -> DMatrix<f64> {
+    let size = signal[0].len();
+    DMatrix::from_fn(blocks * size, columns, |r, c| {
+        signal[first + r / size + c][r % size]
+    })
+}
+
+/// Solves $X A = B$ for $X$ in the least-squares sense.
+fn solve_right(a: &DMatrix<f64>, b: &DMatrix<f64>) -> Option<DMatrix<f64>> {
+    let x = a
+        .transpose()
+        .svd(true, true)
+        .solve(&b.transpose(), 1e-12)
+        .ok()?;
+    Some(x.transpose())
+}
+
+/// The oblique projection of `future` along `inputs` onto `past`: the part
+/// of `future` explained by `past` once the future `inputs` are accounted
+/// for.
+fn oblique_projection(
+    future: &DMatrix<f64>,
+    inputs: &DMatrix<f64>,
+    past: &DMatrix<f64>,
+) -> Option<DMatrix<f64>> {
+    let regressors = stack(past, inputs);
+    let coefficients = solve_right(&regressors, future)?;
+    Some(coefficients.columns(0, past.nrows()) * past)
+}
+
+impl MimoData {
+    pub fn new(inputs: Vec<DVector<f64>>, outputs: Vec<DVector<f64>>, timestep: f64) -> Self {
+        assert_eq!(
+            inputs.len(),
+            outputs.len(),
+            "There must be one output per input"
+        );
+        assert!(!inputs.is_empty(), "There must be at least one sample");
+        assert!(
+            inputs.iter().all(|u| u.len() == inputs[0].len()),
+            "All inputs must have the same dimension"
+        );
+        assert!(
+            outputs.iter().all(|y| y.len() == outputs[0].len()),
+            "All outputs must have the same dimension"
+        );
+        Self {
+            inputs,
+            outputs,
+            timestep,
+        }
+    }
+
+    /// The oblique projections of the future outputs over `horizon` and
+    /// `horizon - 1` steps, or `None` if there are too few samples.
+    fn projections(&self, horizon: usize) -> Option<(DMatrix<f64>, DMatrix<f64>)> {
+        assert!(horizon >= 2, "The horizon must be at least two steps");
+        let (m, p) = (self.inputs[0].len(), self.outputs[0].len());
+        let i = horizon;
+
+        // Each column must be one equation, with more equations than unknowns
+        let columns = (self.inputs.len() + 1).checked_sub(2 * i)?;
+        if columns <= 2 * i * (m + p) {
+            return None;
+        }
+        let u = block_hankel(&self.inputs, 0, 2 * i, columns);
+        let y = block_hankel(&self.outputs, 0, 2 * i, columns);
+
+        let past = stack(&u.rows(0, i * m).into(), &y.rows(0, i * p).into());
+        let current = oblique_projection(
+            &y.rows(i * p, i * p).into(),
+            &u.rows(i * m, i * m).into(),
+            &past,
+        )?;
+
+        // The same, with the horizon moved one step into the future
+        let past = stack(
+            &u.rows(0, (i + 1) * m).into(),
+            &y.rows(0, (i + 1) * p).into(),
+        );
+        let shifted = oblique_projection(
+            &y.rows((i + 1) * p, (i - 1) * p).into(),
+            &u.rows((i + 1) * m, (i - 1) * m).into(),
+            &past,
+        )?;
+
+        Some((current, shifted))
+    }
+
+    /// The singular values a subspace fit over `horizon` steps would order
+    /// the model by, to pick the order before fitting.
+    pub fn singular_values(&self, horizon: usize) -> Option<Vec<f64>> {
+        let (current, _) = self.projections(horizon)?;
+        Some(current.singular_values().iter().copied().collect())
+    }
+
+    /// Fits a state-space model with `order` states by the N4SID subspace
+    /// method (Van Overschee and De Moor)
+    ///
+    /// The future outputs over `horizon` steps are projected onto the past
+    /// inputs and outputs, which gives a state sequence from the dominant
+    /// singular vectors of the projection. The matrices then follow from
+    /// linear least squares on the state and output equations. The horizon
+    /// should be a few times larger than the order.
+    ///
+    /// Returns `None` if there are too few samples for the horizon.
+    pub fn n4sid(&self, order: usize, horizon: usize) -> Option<SubspaceModel> {
+        let (m, p) = (self.inputs[0].len(), self.outputs[0].len());
+        let n = order;
+        assert!(n > 0, "The order must be positive");
+        assert!(
+            n < horizon * p,
+            "The horizon is too short for the order: it must exceed order / outputs"
+        );
+        let i = horizon;
+
+        let (current, shifted) = self.projections(horizon)?;
+        let svd = current.clone().svd(true, false);
+        let (vectors, values) = (svd.u?, svd.singular_values);
+        if values[n - 1] <= 0.0 {
+            return None;
+        }
+
+        // Extended observability matrix and the state sequences it implies
+        let scale = DMatrix::from_diagonal(&values.rows(0, n).map(f64::sqrt));
+        let observability = vectors.columns(0, n) * scale;
+        let states = observability.clone().pseudo_inverse(1e-12).ok()? * &current;
+        let next_states = observability
+            .rows(0, (i - 1) * p)
+            .into_owned()
+            .pseudo_inverse(1e-12)
+            .ok()?
+            * &shifted;
+
+        // [x_(k+1); y_k] = [A B; C D] [x_k; u_k]
+        let columns = states.ncols();
+        let inputs = block_hankel(&self.inputs, i, 1, columns);
+        let outputs = block_hankel(&self.outputs, i, 1, columns);
+        let theta = solve_right(&stack(&states, &inputs), &stack(&next_states, &outputs))?;
+
+        Some(SubspaceModel {
+            model: DiscreteStateSpace::new(
+                theta.view((0, 0), (n, n)).into(),
+                theta.view((0, n), (n, m)).into(),
+                theta.view((n, 0), (p, n)).into(),
+                theta.view((n, n), (p, m)).into(),
+                self.timestep,
+            ),
+            singular_values: values.iter().copied().collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{dmatrix, dvector};
+
+    use super::*;
+    use crate::{
+        discrete::{DiscreteSystem, holder::ZeroOrderHold},
+        system::System,
+        utils::Rng,
+    };
+
+    /// Two inputs, two outputs and two states, with a direct term.
+    fn plant() -> DiscreteStateSpace {
+        DiscreteStateSpace::new(
+            dmatrix![0.8, 0.2; -0.3, 0.6],
+            dmatrix![1.0, 0.0; 0.5, 1.0],
+            dmatrix![1.0, 0.0; 0.0, 1.0],
+            dmatrix![0.0, 0.1; 0.0, 0.0],
+            0.1,
+        )
+    }
+
+    /// Outputs of `model` from rest, run as a held discrete system.
+    fn simulate(model: &DiscreteStateSpace, inputs: &[DVector<f64>]) -> Vec<DVector<f64>> {
+        let mut held = model.clone().with_holder(ZeroOrderHold::new());
+        inputs
+            .iter()
+            .enumerate()
+            .map(|(k, u)| {
+                let time = (k + 1) as f64 * model.timestep();
+                held.update(time, u);
+                held.get_output(time)
+            })
+            .collect()
+    }
+
+    fn generate(samples: usize, noise: f64) -> MimoData {
+        let mut rng = Rng::new(11);
+        let inputs: Vec<DVector<f64>> = (0..samples)
+            .map(|_| dvector![rng.normal(), rng.normal()])
+            .collect();
+        let outputs = simulate(&plant(), &inputs)
+            .into_iter()
+            .map(|y| y + dvector![noise * rng.normal(), noise * rng.normal()])
+            .collect();
+        MimoData::new(inputs, outputs, 0.1)
+    }
+
+    #[test]
+    fn test_recovers_noiseless_plant() {
+        let data = generate(400, 0.0);
+
+        let values = data.singular_values(5).unwrap();
+        assert!(values[1] > 1e6 * values[2]);
+
+        let fitted = data.n4sid(2, 5).unwrap();
+        let a = &fitted.model.a;
+        let true_a = plant().a;
+        // The state basis is arbitrary, but the poles are not
+        assert!((a.trace() - true_a.trace()).abs() < 1e-8);
+        assert!((a.determinant() - true_a.determinant()).abs() < 1e-8);
+        assert!((&fitted.model.d - &plant().d).norm() < 1e-8);
+
+        for (y, expected) in simulate(&fitted.model, &data.inputs)
+            .iter()
+            .zip(&data.outputs)
+        {
+            assert!((y - expected).norm() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_noisy_outputs() {
+        let data = generate(1500, 0.05);
+
+        let fitted = data.n4sid(2, 8).unwrap();
+        let values = &fitted.singular_values;
+        assert!(values[1] > 10.0 * values[2]);
+
+        let true_a = plant().a;
+        assert!((fitted.model.a.trace() - true_a.trace()).abs() < 0.05);
+        assert!((fitted.model.a.determinant() - true_a.determinant()).abs() < 0.05);
+
+        // The simulated outputs fit as well as the noise allows
+        let exact = simulate(&plant(), &data.inputs);
+        let error = simulate(&fitted.model, &data.inputs)
+            .iter()
+            .zip(&exact)
+            .map(|(y, e)| (y - e).norm_squared())
+            .sum::<f64>()
+            / data.inputs.len() as f64;
+        assert!(error < 0.05 * 0.05);
+    }
+
+    #[test]
+    fn test_too_few_samples() {
+        let data = generate(30, 0.0);
+        assert!(data.n4sid(2, 5).is_none());
+        assert!(data.singular_values(5).is_none());
+    }
+}
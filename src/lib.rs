@@ -1,5 +1,11 @@
+pub mod analysis;
 pub mod continuous;
+pub mod control;
 pub mod discrete;
+pub mod estimation;
+pub mod identification;
+pub mod linear;
+pub mod optimization;
 pub mod prelude;
 pub mod system;
 pub mod utils;
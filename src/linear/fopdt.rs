@@ -0,0 +1,170 @@
+use std::collections::VecDeque;
+
+use crate::system::System;
+
+/// A first-order-plus-dead-time model, the usual basis of PID tuning rules
+///
+/// $$G(s) = \frac{K e^{-L s}}{T s + 1}$$
+///
+/// As a `System`, it is integrated exactly for inputs held between updates.
+#[derive(Clone, Debug)]
+pub struct Fopdt {
+    pub gain: f64,
+    pub time_constant: f64,
+    pub dead_time: f64,
+    state: f64,
+    /// Inputs received at each update, which apply until the next one
+    /// once delayed.
+    inputs: VecDeque<(f64, f64)>,
+    last_time: f64,
+    /// The instant and state the last update started from.
+    previous: Option<(f64, f64)>,
+}
+
+impl Fopdt {
+    pub fn new(gain: f64, time_constant: f64, dead_time: f64) -> Self {
+        assert!(time_constant > 0.0, "Time constant must be positive");
+        assert!(dead_time >= 0.0, "Dead time must not be negative");
+
+        Self {
+            gain,
+            time_constant,
+            dead_time,
+            state: 0.0,
+            // Nothing reaches the lag before the dead time
+            inputs: VecDeque::from([(0.0, 0.0)]),
+            last_time: 0.0,
+            previous: None,
+        }
+    }
+
+    /// The response at `time` to a unit step applied at zero.
+    pub fn step_response(&self, time: f64) -> f64 {
+        let t = time - self.dead_time;
+        if t <= 0.0 {
+            0.0
+        } else {
+            self.gain * (1.0 - (-t / self.time_constant).exp())
+        }
+    }
+
+    /// Magnitude and phase, in radians, at angular frequency `omega`.
+    pub fn frequency_response(&self, omega: f64) -> (f64, f64) {
+        let wt = omega * self.time_constant;
+        (
+            self.gain.abs() / (1.0 + wt * wt).sqrt(),
+            -wt.atan() - omega * self.dead_time,
+        )
+    }
+
+    /// Advances the state from `from` to `to` under the delayed inputs.
+    fn advance(&mut self, from: f64, to: f64) {
+        // An input received at t_i applies over (t_(i-1), t_i], so it reaches
+        // the lag over (t_(i-1) + L, t_i + L]. The first one kept also covers
+        // everything before.
+        let mut start = from;
+        for &(instant, input) in &self.inputs {
+            let end = (instant + self.dead_time).min(to);
+            if end > start {
+                let target = self.gain * input;
+                let decay = (-(end - start) / self.time_constant).exp();
+                self.state = target + (self.state - target) * decay;
+                start = end;
+            }
+        }
+    }
+}
+
+impl System for Fopdt {
+    type Input = f64;
+    type Output = f64;
+
+    fn update(&mut self, time: f64, input: &f64) -> f64 {
+        // Updating again at the same instant redoes the last step
+        if let Some((start, state)) = self.previous
+            && time == self.last_time
+        {
+            self.last_time = start;
+            self.state = state;
+            self.inputs.pop_back();
+        }
+        let start = self.last_time;
+        self.previous = Some((start, self.state));
+
+        self.inputs.push_back((time, *input));
+        self.advance(start, time);
+        self.last_time = time;
+
+        // Keep the inputs still reaching the lag after the start of this
+        // step, which is advanced again if it is redone
+        while self.inputs.len() > 1 && self.inputs[0].0 + self.dead_time <= start {
+            self.inputs.pop_front();
+        }
+
+        f64::INFINITY
+    }
+
+    fn get_output(&self, _time: f64) -> f64 {
+        self.state
+    }
+
+    fn has_feedthrough(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::Param;
+
+    #[test]
+    fn test_simulated_step_response() {
+        let mut model = Fopdt::new(2.0, 0.5, 0.3);
+        let expected = model.clone();
+        let mut samples = vec![];
+        model.simulate(3.0, 0.07, Param::new(1.0), &mut |s| samples.push(s));
+
+        // The step is applied right after the first instant
+        for s in &samples {
+            assert!((s.output - expected.step_response(s.instant)).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_delayed_input_changes() {
+        let mut model = Fopdt::new(1.0, 1.0, 1.0);
+        let mut samples = vec![];
+        model.simulate(4.0, 0.25, Param::<f64>::new(1.0).step(0.0, 1.1), &mut |s| {
+            samples.push(s)
+        });
+
+        // The last input of one is given at t = 1, so the pulse covers (0, 1]
+        // and reaches the lag over (1, 2]
+        let peak = 1.0 - (-1.0f64).exp();
+        let at = |t: f64| samples.iter().find(|s| s.instant == t).unwrap().output;
+        assert_eq!(at(1.0), 0.0);
+        assert!((at(2.0) - peak).abs() < 1e-12);
+        assert!((at(3.0) - peak * (-1.0f64).exp()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_redo_with_dead_time() {
+        let mut once = Fopdt::new(1.0, 1.0, 1.0);
+        let mut redone = once.clone();
+        for k in 0..=8 {
+            let time = k as f64 * 0.25;
+            once.update(time, &(k as f64));
+            redone.update(time, &(k as f64));
+        }
+
+        // Updating again at the same instant gives the same result
+        redone.update(2.0, &8.0);
+        assert_eq!(redone.get_output(2.0), once.get_output(2.0));
+
+        for model in [&mut once, &mut redone] {
+            model.update(2.25, &9.0);
+        }
+        assert_eq!(redone.get_output(2.25), once.get_output(2.25));
+    }
+}
@@ -0,0 +1,194 @@
+use nalgebra::{DMatrix, DVector};
+
+use crate::{continuous::ContinuousSystem, linear::StateSpace};
+
+/// Finite-difference steps used to linearize a `ContinuousSystem`.
+///
+/// When no step is given for a variable, it defaults to
+/// $\sqrt[3]{\epsilon} \max(|x_i|, 1)$, which balances truncation and
+/// rounding errors of central differences.
+#[derive(Clone, Debug, Default)]
+pub struct Linearization {
+    state_steps: Option<DVector<f64>>,
+    input_steps: Option<DVector<f64>>,
+}
+
+impl Linearization {
+    /// Sets the perturbation applied to each state.
+    pub fn state_steps(mut self, steps: DVector<f64>) -> Self {
+        self.state_steps = Some(steps);
+        self
+    }
+
+    /// Sets the perturbation applied to each input.
+    pub fn input_steps(mut self, steps: DVector<f64>) -> Self {
+        self.input_steps = Some(steps);
+        self
+    }
+
+    /// Linearizes `sys` around state `x0` and input `u0` at instant `time`
+    /// with central differences of `get_derivative` and the output map.
+    ///
+    /// The output map is evaluated by setting the state and input of `sys`.
+    /// The state is restored before returning and the input is left at `u0`.
+    /// The returned model has the same
+    /// `max_timestep` as `sys`.
+    pub fn linearize<Sys>(
+        &self,
+        sys: &mut Sys,
+        x0: &DVector<f64>,
+        u0: &DVector<f64>,
+        time: f64,
+    ) -> StateSpace
+    where
+        Sys: ContinuousSystem<DVector<f64>, DVector<f64>, DVector<f64>>,
+    {
+        let original = sys.state().clone();
+        let state_steps = self.state_steps.as_ref();
+        let input_steps = self.input_steps.as_ref();
+
+        let a = jacobian(x0, state_steps, |x| sys.get_derivative(time, x, u0));
+        let b = jacobian(u0, input_steps, |u| sys.get_derivative(time, x0, u));
+
+        let mut output = |x: &DVector<f64>, u: &DVector<f64>| {
+            sys.set_input(u);
+            sys.set_state(x);
+            sys.get_output(time)
+        };
+        let c = jacobian(x0, state_steps, |x| output(x, u0));
+        let d = jacobian(u0, input_steps, |u| output(x0, u));
+
+        sys.set_input(u0);
+        sys.set_state(&original);
+
+        StateSpace::new(a, b, c, d).max_timestep(sys.max_timestep())
+    }
+}
+
+/// Linearizes `sys` around state `x0` and input `u0` at instant `time`, with
+/// the default finite-difference steps of [`Linearization`].
+pub fn linearize<Sys>(sys: &mut Sys, x0: &DVector<f64>, u0: &DVector<f64>, time: f64) -> StateSpace
+where
+    Sys: ContinuousSystem<DVector<f64>, DVector<f64>, DVector<f64>>,
+{
+    Linearization::default().linearize(sys, x0, u0, time)
+}
+
+/// Central finite-difference Jacobian of `f` at `at`, perturbing each
+/// component by `steps[i]` or by the default step if `steps` is `None`.
+pub(crate) fn jacobian(
+    at: &DVector<f64>,
+    steps: Option<&DVector<f64>>,
+    mut f: impl FnMut(&DVector<f64>) -> DVector<f64>,
+) -> DMatrix<f64> {
+    if let Some(steps) = steps {
+        assert_eq!(steps.len(), at.len(), "There must be one step per variable");
+    }
+
+    let rows = f(at).len();
+    let mut result = DMatrix::zeros(rows, at.len());
+
+    for i in 0..at.len() {
+        let h = steps.map_or_else(|| f64::EPSILON.cbrt() * at[i].abs().max(1.0), |s| s[i]);
+        let mut forward = at.clone();
+        let mut backward = at.clone();
+        forward[i] += h;
+        backward[i] -= h;
+
+        result.set_column(i, &((f(&forward) - f(&backward)) / (2.0 * h)));
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{dmatrix, dvector};
+
+    // A pendulum with torque input, measuring the tip's horizontal position
+    struct Pendulum {
+        state: DVector<f64>,
+    }
+
+    impl ContinuousSystem<DVector<f64>, DVector<f64>, DVector<f64>> for Pendulum {
+        fn get_derivative(&self, _time: f64, x: &DVector<f64>, u: &DVector<f64>) -> DVector<f64> {
+            dvector![x[1], -9.81 * x[0].sin() - 0.1 * x[1] + 2.0 * u[0]]
+        }
+
+        fn get_output(&self, _time: f64) -> DVector<f64> {
+            dvector![self.state[0].sin()]
+        }
+
+        fn state(&self) -> &DVector<f64> {
+            &self.state
+        }
+
+        fn set_state(&mut self, new_state: &DVector<f64>) {
+            self.state.copy_from(new_state);
+        }
+
+        fn max_timestep(&self) -> f64 {
+            0.01
+        }
+    }
+
+    #[test]
+    fn test_linearize_pendulum() {
+        let mut sys = Pendulum {
+            state: dvector![0.3, 0.2],
+        };
+        let theta: f64 = 0.5;
+        let model = linearize(&mut sys, &dvector![theta, 0.0], &dvector![0.0], 0.0);
+
+        let expected_a = dmatrix![0.0, 1.0; -9.81 * theta.cos(), -0.1];
+        assert!((&model.a - expected_a).norm() < 1e-8);
+        assert!((&model.b - dmatrix![0.0; 2.0]).norm() < 1e-8);
+        assert!((&model.c - dmatrix![theta.cos(), 0.0]).norm() < 1e-8);
+        assert!(model.d.norm() < 1e-8);
+
+        // The system is left as it was found
+        assert_eq!(sys.state(), &dvector![0.3, 0.2]);
+        assert_eq!(ContinuousSystem::max_timestep(&model), 0.01);
+    }
+
+    #[test]
+    fn test_linearize_recovers_state_space() {
+        let mut plant = StateSpace::new(
+            dmatrix![-1.0, 2.0; 0.0, -3.0],
+            dmatrix![1.0; 1.0],
+            dmatrix![1.0, 1.0],
+            dmatrix![0.5],
+        );
+        let model = linearize(&mut plant, &dvector![1.0, -1.0], &dvector![2.0], 0.0);
+
+        assert!((&model.a - &plant.a).norm() < 1e-8);
+        assert!((&model.b - &plant.b).norm() < 1e-8);
+        assert!((&model.c - &plant.c).norm() < 1e-8);
+        assert!((&model.d - &plant.d).norm() < 1e-8);
+    }
+
+    #[test]
+    fn test_custom_steps() {
+        let mut sys = Pendulum {
+            state: dvector![0.0, 0.0],
+        };
+        let coarse = Linearization::default()
+            .state_steps(dvector![0.5, 1.0])
+            .input_steps(dvector![1.0])
+            .linearize(&mut sys, &dvector![0.0, 0.0], &dvector![0.0], 0.0);
+
+        // Central differences of sin with a large step underestimate the slope
+        let expected = -9.81 * 0.5f64.sin() / 0.5;
+        assert!((coarse.a[(1, 0)] - expected).abs() < 1e-12);
+        assert!((coarse.b[(1, 0)] - 2.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_jacobian_of_linear_map() {
+        let a = dmatrix![1.0, 2.0; 3.0, 4.0];
+        let j = jacobian(&dvector![0.3, -0.7], None, |x| &a * x);
+
+        assert!((j - a).norm() < 1e-9);
+    }
+}
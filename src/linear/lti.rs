@@ -0,0 +1,455 @@
+use std::ops::{Add, Mul};
+
+use nalgebra::{Complex, DMatrix};
+
+use crate::{
+    analysis::FrequencyResponse,
+    discrete::DiscreteSystem,
+    linear::{DiscreteStateSpace, StateSpace, minimal::minimal_part},
+};
+
+/// Whether a feedback path is subtracted from or added to the input.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FeedbackSign {
+    #[default]
+    Negative,
+    Positive,
+}
+
+mod sealed {
+    use nalgebra::DMatrix;
+
+    /// Builds models from matrices, which only the models of this crate do.
+    pub trait Combine {
+        /// A model of the same kind as `self` and `other` with the given
+        /// matrices, starting at rest.
+        fn combine(
+            &self,
+            other: &Self,
+            a: DMatrix<f64>,
+            b: DMatrix<f64>,
+            c: DMatrix<f64>,
+            d: DMatrix<f64>,
+        ) -> Self;
+    }
+}
+
+use self::sealed::Combine;
+
+/// Algebra and frequency-domain analysis shared by continuous and discrete
+/// state-space models
+///
+/// Models built by interconnection start at rest, and keep every state of
+/// their parts, so they are generally not minimal: `minimal_realization`
+/// removes the redundant ones.
+pub trait LinearModel: Combine + Sized {
+    /// The matrices $A$, $B$, $C$ and $D$.
+    fn matrices(&self) -> [&DMatrix<f64>; 4];
+
+    /// The value of the transform variable at angular frequency `omega`.
+    fn frequency_point(&self, omega: f64) -> Complex<f64>;
+
+    /// The eigenvalues of $A$.
+    fn poles(&self) -> Vec<Complex<f64>> {
+        self.matrices()[0]
+            .complex_eigenvalues()
+            .iter()
+            .copied()
+            .collect()
+    }
+
+    /// The complex gain matrix $C (s I - A)^{-1} B + D$ at angular frequency
+    /// `omega`, or `None` if it falls on a pole.
+    fn frequency_response(&self, omega: f64) -> Option<DMatrix<Complex<f64>>> {
+        let [a, b, c, d] = self.matrices().map(|m| m.map(Complex::from));
+        let n = a.nrows();
+        let s = self.frequency_point(omega);
+        let resolvent = (DMatrix::identity(n, n) * s - a).try_inverse()?;
+        Some(c * resolvent * b + d)
+    }
+
+    /// The response from `input` to `output` at each of `frequencies`, in
+    /// rad/s, to compare with estimates from data. It has no coherence,
+    /// which only estimates have.
+    fn bode(&self, input: usize, output: usize, frequencies: &[f64]) -> FrequencyResponse {
+        let response: Vec<Complex<f64>> = frequencies
+            .iter()
+            .map(|&omega| {
+                self.frequency_response(omega)
+                    .map_or(Complex::new(f64::INFINITY, 0.0), |g| g[(output, input)])
+            })
+            .collect();
+        FrequencyResponse {
+            frequencies: frequencies.to_vec(),
+            response,
+            coherence: None,
+        }
+    }
+
+    /// An equivalent model without uncontrollable or unobservable states,
+    /// found with the staircase form, with singular values below
+    /// `tolerance` relative to the model taken as zero. It starts at rest.
+    fn minimal_realization(&self, tolerance: f64) -> Self {
+        let [a, b, c, d] = self.matrices();
+        let (a, b, c) = minimal_part(a, b, c, tolerance);
+        self.combine(self, a, b, c, d.clone())
+    }
+
+    /// `other` followed by `self`, so that `self.series(other)` is the
+    /// product $G_{self} G_{other}$. It keeps the states of both, even those
+    /// a pole-zero cancellation leaves redundant, until
+    /// `minimal_realization` is called.
+    fn series(&self, other: &Self) -> Self {
+        let [a2, b2, c2, d2] = self.matrices();
+        let [a1, b1, c1, d1] = other.matrices();
+        assert_eq!(
+            b2.ncols(),
+            c1.nrows(),
+            "The outputs of the first model must feed the inputs of the second"
+        );
+        let (n1, n2) = (a1.nrows(), a2.nrows());
+
+        let mut a = DMatrix::zeros(n1 + n2, n1 + n2);
+        a.view_mut((0, 0), (n1, n1)).copy_from(a1);
+        a.view_mut((n1, 0), (n2, n1)).copy_from(&(b2 * c1));
+        a.view_mut((n1, n1), (n2, n2)).copy_from(a2);
+        let b = stack(b1, &(b2 * d1));
+        let c = side_by_side(&(d2 * c1), c2);
+        self.combine(other, a, b, c, d2 * d1)
+    }
+
+    /// `self` and `other` driven by the same input, with their outputs
+    /// summed. It keeps the states of both, even shared modes, until
+    /// `minimal_realization` is called.
+    fn parallel(&self, other: &Self) -> Self {
+        let [a1, b1, c1, d1] = self.matrices();
+        let [a2, b2, c2, d2] = other.matrices();
+        assert_eq!(
+            d1.shape(),
+            d2.shape(),
+            "Models in parallel must have the same inputs and outputs"
+        );
+        self.combine(
+            other,
+            block_diagonal(a1, a2),
+            stack(b1, b2),
+            side_by_side(c1, c2),
+            d1 + d2,
+        )
+    }
+
+    /// `self` and `other` side by side, with their inputs and outputs
+    /// stacked.
+    fn append(&self, other: &Self) -> Self {
+        let [a1, b1, c1, d1] = self.matrices();
+        let [a2, b2, c2, d2] = other.matrices();
+        self.combine(
+            other,
+            block_diagonal(a1, a2),
+            block_diagonal(b1, b2),
+            block_diagonal(c1, c2),
+            block_diagonal(d1, d2),
+        )
+    }
+}
+
+/// The loop of `forward` with `feedback` from its output back to its input,
+/// as in `ClosedLoop` for a negative sign
+///
+/// Returns `None` if the loop is ill-posed, i.e. if the feedthroughs of both
+/// paths make $I \mp D_{forward} D_{feedback}$ singular. The loop keeps the
+/// states of both paths, so call `minimal_realization` on it to remove
+/// those that cancel.
+pub fn feedback<Model: LinearModel>(
+    forward: &Model,
+    feedback: &Model,
+    sign: FeedbackSign,
+) -> Option<Model> {
+    let [a1, b1, c1, d1] = forward.matrices();
+    let [a2, b2, c2, d2] = feedback.matrices();
+    assert_eq!(
+        (b2.ncols(), c2.nrows()),
+        (c1.nrows(), b1.ncols()),
+        "The feedback path must map the outputs back to the inputs"
+    );
+    let s = match sign {
+        FeedbackSign::Negative => -1.0,
+        FeedbackSign::Positive => 1.0,
+    };
+    let n1 = a1.nrows();
+    let (inputs, outputs) = (b1.ncols(), c1.nrows());
+
+    // Solve the loop for the output, y = C1 x1 + D1 (r + s (C2 x2 + D2 y))
+    let loop_inverse = (DMatrix::identity(outputs, outputs) - d1 * d2 * s).try_inverse()?;
+    let output_c = &loop_inverse * side_by_side(c1, &(d1 * c2 * s));
+    let output_d = &loop_inverse * d1;
+
+    // and the error it feeds the forward path, e = r + s (C2 x2 + D2 y)
+    let error_c = (d2 * &output_c + side_by_side(&DMatrix::zeros(inputs, n1), c2)) * s;
+    let error_d = DMatrix::identity(inputs, inputs) + d2 * &output_d * s;
+
+    let a = block_diagonal(a1, a2) + stack(&(b1 * &error_c), &(b2 * &output_c));
+    let b = stack(&(b1 * error_d), &(b2 * &output_d));
+    Some(forward.combine(feedback, a, b, output_c, output_d))
+}
+
+/// Stacks the rows of `top` over those of `bottom`.
+pub(crate) fn stack(top: &DMatrix<f64>, bottom: &DMatrix<f64>) -> DMatrix<f64> {
+    let mut stacked = DMatrix::zeros(top.nrows() + bottom.nrows(), top.ncols());
+    stacked.rows_mut(0, top.nrows()).copy_from(top);
+    stacked
+        .rows_mut(top.nrows(), bottom.nrows())
+        .copy_from(bottom);
+    stacked
+}
+
+/// Places the columns of `right` after those of `left`.
+fn side_by_side(left: &DMatrix<f64>, right: &DMatrix<f64>) -> DMatrix<f64> {
+    let mut joined = DMatrix::zeros(left.nrows(), left.ncols() + right.ncols());
+    joined.columns_mut(0, left.ncols()).copy_from(left);
+    joined
+        .columns_mut(left.ncols(), right.ncols())
+        .copy_from(right);
+    joined
+}
+
+fn block_diagonal(first: &DMatrix<f64>, second: &DMatrix<f64>) -> DMatrix<f64> {
+    let (r1, c1) = first.shape();
+    let (r2, c2) = second.shape();
+    let mut joined = DMatrix::zeros(r1 + r2, c1 + c2);
+    joined.view_mut((0, 0), (r1, c1)).copy_from(first);
+    joined.view_mut((r1, c1), (r2, c2)).copy_from(second);
+    joined
+}
+
+impl Combine for StateSpace {
+    /// The combined model keeps the smaller maximum timestep of the two.
+    fn combine(
+        &self,
+        other: &Self,
+        a: DMatrix<f64>,
+        b: DMatrix<f64>,
+        c: DMatrix<f64>,
+        d: DMatrix<f64>,
+    ) -> Self {
+        StateSpace::new(a, b, c, d).max_timestep(self.max_timestep.min(other.max_timestep))
+    }
+}
+
+impl LinearModel for StateSpace {
+    fn matrices(&self) -> [&DMatrix<f64>; 4] {
+        [&self.a, &self.b, &self.c, &self.d]
+    }
+
+    fn frequency_point(&self, omega: f64) -> Complex<f64> {
+        Complex::new(0.0, omega)
+    }
+}
+
+impl Combine for DiscreteStateSpace {
+    fn combine(
+        &self,
+        other: &Self,
+        a: DMatrix<f64>,
+        b: DMatrix<f64>,
+        c: DMatrix<f64>,
+        d: DMatrix<f64>,
+    ) -> Self {
+        assert_eq!(
+            self.timestep(),
+            other.timestep(),
+            "Discrete models must share a timestep to be connected"
+        );
+        DiscreteStateSpace::new(a, b, c, d, self.timestep())
+    }
+}
+
+impl LinearModel for DiscreteStateSpace {
+    fn matrices(&self) -> [&DMatrix<f64>; 4] {
+        [&self.a, &self.b, &self.c, &self.d]
+    }
+
+    fn frequency_point(&self, omega: f64) -> Complex<f64> {
+        Complex::from_polar(1.0, omega * self.timestep())
+    }
+}
+
+/// `*` connects models in series and `+` in parallel, for owned models and
+/// references alike. As with `series` and `parallel`, the results are not
+/// minimal until `minimal_realization` is called.
+macro_rules! model_algebra {
+    ($model:ty) => {
+        impl Mul for &$model {
+            type Output = $model;
+
+            fn mul(self, rhs: Self) -> $model {
+                self.series(rhs)
+            }
+        }
+
+        impl Mul for $model {
+            type Output = $model;
+
+            fn mul(self, rhs: Self) -> $model {
+                self.series(&rhs)
+            }
+        }
+
+        impl Add for &$model {
+            type Output = $model;
+
+            fn add(self, rhs: Self) -> $model {
+                self.parallel(rhs)
+            }
+        }
+
+        impl Add for $model {
+            type Output = $model;
+
+            fn add(self, rhs: Self) -> $model {
+                self.parallel(&rhs)
+            }
+        }
+    };
+}
+
+model_algebra!(StateSpace);
+model_algebra!(DiscreteStateSpace);
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use nalgebra::{dmatrix, dvector};
+
+    use super::*;
+    use crate::{
+        continuous::{ContinuousSystem, integrator::RungeKutta4},
+        system::System,
+        utils::Param,
+    };
+
+    /// k / (s + p)
+    fn lag(k: f64, p: f64) -> StateSpace {
+        StateSpace::new(dmatrix![-p], dmatrix![1.0], dmatrix![k], dmatrix![0.0])
+    }
+
+    fn gain(model: &impl LinearModel, omega: f64) -> Complex<f64> {
+        model.frequency_response(omega).unwrap()[0]
+    }
+
+    #[test]
+    fn test_series_and_parallel() {
+        let (g1, g2) = (lag(2.0, 1.0), lag(1.0, 3.0));
+        let product = &g2 * &g1;
+        let sum = &g1 + &g2;
+
+        let mut poles: Vec<f64> = product.poles().iter().map(|p| p.re).collect();
+        poles.sort_by(f64::total_cmp);
+        assert!((poles[0] + 3.0).abs() < 1e-12 && (poles[1] + 1.0).abs() < 1e-12);
+        for omega in [0.0, 0.5, 4.0] {
+            let expected = gain(&g1, omega) * gain(&g2, omega);
+            assert!((gain(&product, omega) - expected).norm() < 1e-12);
+            let expected = gain(&g1, omega) + gain(&g2, omega);
+            assert!((gain(&sum, omega) - expected).norm() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_feedback_with_feedthrough() {
+        // G = (s + 2) / (s + 1) and H = 0.5 / (s + 4) + 0.25 both feed through
+        let g = StateSpace::new(dmatrix![-1.0], dmatrix![1.0], dmatrix![1.0], dmatrix![1.0]);
+        let h = StateSpace::new(dmatrix![-4.0], dmatrix![1.0], dmatrix![0.5], dmatrix![0.25]);
+
+        for sign in [FeedbackSign::Negative, FeedbackSign::Positive] {
+            let closed = feedback(&g, &h, sign).unwrap();
+            let s = if sign == FeedbackSign::Negative {
+                -1.0
+            } else {
+                1.0
+            };
+            for omega in [0.0, 1.0, 10.0] {
+                let (go, ho) = (gain(&g, omega), gain(&h, omega));
+                let expected = go / (1.0 - s * go * ho);
+                assert!((gain(&closed, omega) - expected).norm() < 1e-12);
+            }
+        }
+
+        // A unit positive loop through pure gains has no solution
+        let unit = StateSpace::new(dmatrix![-1.0], dmatrix![1.0], dmatrix![0.0], dmatrix![1.0]);
+        assert!(feedback(&unit, &unit, FeedbackSign::Positive).is_none());
+    }
+
+    #[test]
+    fn test_closed_loop_is_simulatable() {
+        // 4 / (s + 1) under unit negative feedback: 4 / (s + 5)
+        let unity = StateSpace::new(
+            DMatrix::zeros(0, 0),
+            DMatrix::zeros(0, 1),
+            DMatrix::zeros(1, 0),
+            dmatrix![1.0],
+        );
+        let closed = feedback(&lag(4.0, 1.0), &unity, FeedbackSign::Negative)
+            .unwrap()
+            .max_timestep(0.01);
+        assert!((closed.poles()[0].re + 5.0).abs() < 1e-12);
+
+        let mut system = closed.with_integrator(RungeKutta4);
+        let mut last = 0.0;
+        system.simulate(5.0, 0.01, Param::new(dvector![1.0]), &mut |s| {
+            last = s.output[0]
+        });
+        assert!((last - 0.8).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_append_and_bode() {
+        let both = lag(1.0, 1.0).append(&lag(3.0, 2.0));
+        let response = both.frequency_response(1.0).unwrap();
+        assert_eq!(response[(0, 1)], Complex::new(0.0, 0.0));
+        assert!((response[(1, 1)] - gain(&lag(3.0, 2.0), 1.0)).norm() < 1e-12);
+
+        let bode = both.bode(0, 0, &[1.0, 1e3]);
+        assert!((bode.magnitude()[0] - 0.5f64.sqrt()).abs() < 1e-12);
+        assert!((bode.phase()[0] + PI / 4.0).abs() < 1e-12);
+        assert!((bode.phase()[1] + PI / 2.0).abs() < 1e-2);
+        assert!(bode.coherence.is_none());
+    }
+
+    #[test]
+    fn test_discrete_models() {
+        let g = DiscreteStateSpace::new(
+            dmatrix![0.5],
+            dmatrix![1.0],
+            dmatrix![1.0],
+            dmatrix![0.0],
+            0.1,
+        );
+        let loop_model = feedback(&g, &g.clone(), FeedbackSign::Negative).unwrap();
+
+        // At ω = 0, z = 1 and G(1) = 2, so the loop gain is 2 / (1 + 4)
+        assert!((gain(&loop_model, 0.0) - Complex::new(0.4, 0.0)).norm() < 1e-12);
+        let nyquist = gain(&g, PI / 0.1);
+        assert!((nyquist - Complex::new(1.0 / -1.5, 0.0)).norm() < 1e-12);
+    }
+
+    #[test]
+    #[should_panic(expected = "Discrete models must share a timestep to be connected")]
+    fn test_discrete_timesteps_must_match() {
+        let g = DiscreteStateSpace::new(
+            dmatrix![0.5],
+            dmatrix![1.0],
+            dmatrix![1.0],
+            dmatrix![0.0],
+            0.1,
+        );
+        let h = DiscreteStateSpace::new(
+            dmatrix![0.5],
+            dmatrix![1.0],
+            dmatrix![1.0],
+            dmatrix![0.0],
+            0.2,
+        );
+        let _ = g + h;
+    }
+}
@@ -0,0 +1,86 @@
+use nalgebra::DMatrix;
+
+const MAX_ITERATIONS: usize = 100;
+const TOLERANCE: f64 = 1e-14;
+
+/// Solves the discrete Lyapunov (Stein) equation
+///
+/// $$X = A X A^T + Q$$
+///
+/// by Smith's doubling of the series $\sum_k A^k Q (A^T)^k$. Returns `None`
+/// if it does not converge, which happens when $A$ is not stable.
+pub fn dlyap(a: &DMatrix<f64>, q: &DMatrix<f64>) -> Option<DMatrix<f64>> {
+    assert!(a.is_square(), "A must be square");
+    assert_eq!(a.shape(), q.shape(), "Q must have the same size as A");
+
+    let mut ak = a.clone();
+    let mut x = q.clone();
+
+    for _ in 0..MAX_ITERATIONS {
+        let change = &ak * &x * ak.transpose();
+        x += &change;
+        ak = &ak * &ak;
+
+        // Give up once it diverges, before overflowing norms fake convergence
+        if !x.norm().is_finite() {
+            return None;
+        }
+        if change.norm() <= TOLERANCE * x.norm().max(f64::MIN_POSITIVE) {
+            return Some((&x + x.transpose()) * 0.5);
+        }
+    }
+
+    None
+}
+
+/// Solves the continuous Lyapunov equation
+///
+/// $$A X + X A^T + Q = 0$$
+///
+/// by mapping it to a discrete one with a Cayley transform. Returns `None`
+/// if $A$ is not stable.
+pub fn lyap(a: &DMatrix<f64>, q: &DMatrix<f64>) -> Option<DMatrix<f64>> {
+    assert!(a.is_square(), "A must be square");
+    assert_eq!(a.shape(), q.shape(), "Q must have the same size as A");
+    let n = a.nrows();
+    let identity = DMatrix::<f64>::identity(n, n);
+
+    // A shift of the order of the eigenvalues keeps the transformed spectrum
+    // away from the unit circle
+    let shift = (a.norm() / (n as f64).sqrt()).max(f64::MIN_POSITIVE);
+    let inverse = (a - &identity * shift).try_inverse()?;
+    let ad = &inverse * (a + &identity * shift);
+    let qd = &inverse * q * inverse.transpose() * (2.0 * shift);
+
+    dlyap(&ad, &qd)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::dmatrix;
+
+    #[test]
+    fn test_lyap_scalar() {
+        // 2 a x + q = 0
+        let x = lyap(&dmatrix![-2.0], &dmatrix![3.0]).unwrap();
+        assert!((x[0] - 0.75).abs() < 1e-12);
+        assert!(lyap(&dmatrix![0.5], &dmatrix![1.0]).is_none());
+    }
+
+    #[test]
+    fn test_lyapunov_residuals() {
+        // A lightly damped mode and a fast pole
+        let a = dmatrix![0.0, 1.0, 0.0; -100.0, -0.2, 0.0; 0.0, 0.0, -50.0];
+        let q = dmatrix![1.0, 0.0, 0.5; 0.0, 2.0, 0.0; 0.5, 0.0, 1.0];
+        let x = lyap(&a, &q).unwrap();
+        let residual = &a * &x + &x * a.transpose() + &q;
+        assert!(residual.norm() < 1e-8 * x.norm());
+
+        let ad = dmatrix![0.9, 0.3; -0.2, 0.7];
+        let x = dlyap(&ad, &q.view((0, 0), (2, 2)).into_owned()).unwrap();
+        let residual = &ad * &x * ad.transpose() + q.view((0, 0), (2, 2)) - &x;
+        assert!(residual.norm() < 1e-10);
+        assert!(dlyap(&(ad * 2.0), &dmatrix![1.0, 0.0; 0.0, 1.0]).is_none());
+    }
+}
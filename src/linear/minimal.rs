@@ -0,0 +1,277 @@
+use nalgebra::{Complex, DMatrix};
+
+use crate::{discrete::DiscreteSystem, linear::DiscreteTransferFunction};
+
+/// Reduces $(A, B, C)$ to its controllable part with the controllability
+/// staircase form
+///
+/// Each stage finds, by a singular value decomposition, the directions the
+/// last block of $B$ (first $B$ itself, then the coupling of the newly
+/// reached states into the rest) reaches, and rotates them to the front. The
+/// states reached when no new direction appears are the controllable ones.
+/// Singular values below `tolerance` times the larger norm of $A$ and $B$
+/// count as zero.
+pub(crate) fn controllable_part(
+    a: &DMatrix<f64>,
+    b: &DMatrix<f64>,
+    c: &DMatrix<f64>,
+    tolerance: f64,
+) -> (DMatrix<f64>, DMatrix<f64>, DMatrix<f64>) {
+    let n = a.nrows();
+    let threshold = tolerance * a.norm().max(b.norm()).max(f64::MIN_POSITIVE);
+
+    let mut a = a.clone();
+    let mut b = b.clone();
+    let mut c = c.clone();
+    let mut reached = 0;
+    let mut block = b.clone();
+
+    while reached < n {
+        let remaining = n - reached;
+        // Zero columns leave the singular values alone, but make the left
+        // singular vectors a full rotation of the remaining states
+        let columns = block.ncols().max(remaining);
+        let svd = block.resize_horizontally(columns, 0.0).svd(true, false);
+        let rank = svd
+            .singular_values
+            .iter()
+            .filter(|&&s| s > threshold)
+            .count();
+        if rank == 0 {
+            break;
+        }
+
+        // Rotate the remaining states so the reached directions come first
+        let rotation = svd.u.expect("Left singular vectors were requested");
+        let mut transform = DMatrix::identity(n, n);
+        transform
+            .view_mut((reached, reached), (remaining, remaining))
+            .copy_from(&rotation);
+        a = transform.transpose() * &a * &transform;
+        b = transform.transpose() * &b;
+        c = &c * &transform;
+
+        reached += rank;
+        block = a
+            .view((reached, reached - rank), (n - reached, rank))
+            .into_owned();
+    }
+
+    (
+        a.view((0, 0), (reached, reached)).into_owned(),
+        b.rows(0, reached).into_owned(),
+        c.columns(0, reached).into_owned(),
+    )
+}
+
+/// Removes the uncontrollable states of $(A, B, C)$, then the unobservable
+/// ones by duality.
+pub(crate) fn minimal_part(
+    a: &DMatrix<f64>,
+    b: &DMatrix<f64>,
+    c: &DMatrix<f64>,
+    tolerance: f64,
+) -> (DMatrix<f64>, DMatrix<f64>, DMatrix<f64>) {
+    let (a, b, c) = controllable_part(a, b, c, tolerance);
+    let (a, c, b) = controllable_part(&a.transpose(), &c.transpose(), &b.transpose(), tolerance);
+    (a.transpose(), b.transpose(), c.transpose())
+}
+
+/// The roots of $\sum_i p_i q^i$, with $p$ of exact degree.
+fn roots(coefficients: &[f64]) -> Vec<Complex<f64>> {
+    let degree = coefficients.len() - 1;
+    if degree == 0 {
+        return vec![];
+    }
+
+    let leading = coefficients[degree];
+    let companion = DMatrix::from_fn(degree, degree, |r, c| {
+        if r == 0 {
+            -coefficients[degree - 1 - c] / leading
+        } else if r == c + 1 {
+            1.0
+        } else {
+            0.0
+        }
+    });
+    companion.complex_eigenvalues().iter().copied().collect()
+}
+
+/// The coefficients of $\prod_i (q - r_i)$, lowest power first.
+fn from_roots(roots: &[Complex<f64>]) -> Vec<f64> {
+    let mut polynomial = vec![Complex::new(1.0, 0.0)];
+    for root in roots {
+        let mut next = vec![Complex::new(0.0, 0.0); polynomial.len() + 1];
+        for (i, p) in polynomial.iter().enumerate() {
+            next[i + 1] += p;
+            next[i] -= p * root;
+        }
+        polynomial = next;
+    }
+    // Complex roots come in conjugate pairs, so the imaginary parts cancel
+    polynomial.iter().map(|p| p.re).collect()
+}
+
+/// A polynomial split into a power of $q$, a nonzero leading coefficient and
+/// the roots of the rest.
+struct Factored {
+    delay: usize,
+    leading: f64,
+    roots: Vec<Complex<f64>>,
+}
+
+impl Factored {
+    fn new(coefficients: &[f64]) -> Self {
+        let delay = coefficients.iter().take_while(|&&p| p == 0.0).count();
+        let end = coefficients
+            .iter()
+            .rposition(|&p| p != 0.0)
+            .map_or(0, |i| i + 1);
+        let rest = &coefficients[delay.min(end)..end];
+        Self {
+            delay,
+            leading: rest.last().copied().unwrap_or(0.0),
+            roots: if rest.is_empty() { vec![] } else { roots(rest) },
+        }
+    }
+
+    fn coefficients(&self) -> Vec<f64> {
+        std::iter::repeat_n(0.0, self.delay)
+            .chain(from_roots(&self.roots).iter().map(|p| p * self.leading))
+            .collect()
+    }
+}
+
+impl DiscreteTransferFunction {
+    /// Cancels the poles that match a zero within `tolerance`, relative to
+    /// the larger of one and the size of the pole in $z^{-1}$, keeping the
+    /// gain at all other frequencies. The result starts at rest.
+    pub fn minimal_realization(&self, tolerance: f64) -> Self {
+        let mut numerator = Factored::new(self.numerator());
+        let mut denominator = Factored::new(self.denominator());
+        if numerator.leading == 0.0 {
+            return Self::new(vec![0.0], vec![1.0], self.timestep());
+        }
+
+        // Roots are compared in the backward shift q = 1 / z, where poles
+        // and zeros at z = 0 are kept as delays rather than roots
+        let mut kept = Vec::with_capacity(denominator.roots.len());
+        for pole in denominator.roots {
+            let closest = numerator
+                .roots
+                .iter()
+                .enumerate()
+                .map(|(i, zero)| (i, (zero - pole).norm()))
+                .min_by(|x, y| x.1.total_cmp(&y.1));
+            match closest {
+                Some((i, distance)) if distance <= tolerance * pole.norm().max(1.0) => {
+                    numerator.roots.swap_remove(i);
+                }
+                _ => kept.push(pole),
+            }
+        }
+        denominator.roots = kept;
+
+        Self::new(
+            numerator.coefficients(),
+            denominator.coefficients(),
+            self.timestep(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::dmatrix;
+
+    use super::*;
+    use crate::linear::{DiscreteStateSpace, LinearModel, StateSpace};
+
+    fn lag(k: f64, p: f64) -> StateSpace {
+        StateSpace::new(dmatrix![-p], dmatrix![1.0], dmatrix![k], dmatrix![0.0])
+    }
+
+    fn assert_same_response(first: &impl LinearModel, second: &impl LinearModel) {
+        for omega in [0.0, 0.3, 1.0, 5.0, 20.0] {
+            let error = first.frequency_response(omega).unwrap()
+                - second.frequency_response(omega).unwrap();
+            assert!(error.norm() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_cancelled_pole_is_removed() {
+        // (s + 1) / (s + 2) after 1 / (s + 1) is just 1 / (s + 2)
+        let lead = StateSpace::new(dmatrix![-2.0], dmatrix![1.0], dmatrix![-1.0], dmatrix![1.0]);
+        let product = &lead * &lag(1.0, 1.0);
+        let minimal = product.minimal_realization(1e-9);
+
+        assert_eq!(minimal.states(), 1);
+        assert!((minimal.poles()[0].re + 2.0).abs() < 1e-9);
+        assert_same_response(&minimal, &lag(1.0, 2.0));
+    }
+
+    #[test]
+    fn test_difference_of_equal_models_vanishes() {
+        let g = lag(2.0, 1.0);
+        let negated = StateSpace::new(dmatrix![-1.0], dmatrix![1.0], dmatrix![-2.0], dmatrix![0.0]);
+        let zero = (&g + &negated).minimal_realization(1e-9);
+
+        assert_eq!(zero.states(), 0);
+        assert_eq!(zero.d, dmatrix![0.0]);
+    }
+
+    #[test]
+    fn test_minimal_model_is_kept() {
+        // Two inputs and outputs, coupled through a lightly damped mode
+        let model = StateSpace::new(
+            dmatrix![0.0, 1.0, 0.0; -4.0, -0.4, 0.0; 0.0, 0.0, -3.0],
+            dmatrix![0.0, 1.0; 1.0, 0.0; 1.0, 1.0],
+            dmatrix![1.0, 0.0, 1.0; 0.0, 1.0, 0.0],
+            dmatrix![0.0, 0.5; 0.0, 0.0],
+        );
+        let minimal = model.minimal_realization(1e-9);
+
+        assert_eq!(minimal.states(), 3);
+        assert_same_response(&model, &minimal);
+    }
+
+    #[test]
+    fn test_discrete_appended_copies() {
+        // The same mode driven twice from one input is only controllable once
+        let g = DiscreteStateSpace::new(
+            dmatrix![0.5],
+            dmatrix![1.0],
+            dmatrix![1.0],
+            dmatrix![0.0],
+            0.1,
+        );
+        let twice = &g + &g;
+        let minimal = twice.minimal_realization(1e-9);
+
+        assert_eq!(minimal.states(), 1);
+        assert_eq!(minimal.timestep(), 0.1);
+        assert_same_response(&twice, &minimal);
+    }
+
+    #[test]
+    fn test_transfer_function_cancellation() {
+        // q (1 - 0.5 q)(1 + 0.2 q) / ((1 - 0.5 q)(1 - 0.9 q)), with q = 1/z
+        let tf =
+            DiscreteTransferFunction::new(vec![0.0, 1.0, -0.3, -0.1], vec![1.0, -1.4, 0.45], 0.1);
+        let reduced = tf.minimal_realization(1e-9);
+        let expected = [0.0, 1.0, 0.2];
+        assert_eq!(reduced.numerator().len(), 3);
+        for (b, e) in reduced.numerator().iter().zip(expected) {
+            assert!((b - e).abs() < 1e-9);
+        }
+        assert_eq!(reduced.denominator().len(), 2);
+        assert!((reduced.denominator()[1] + 0.9).abs() < 1e-9);
+        assert!((reduced.dc_gain() - tf.dc_gain()).abs() < 1e-9);
+
+        // A nearby but distinct zero only cancels with a loose tolerance
+        let near = DiscreteTransferFunction::new(vec![1.0, -0.5001], vec![1.0, -0.5], 0.1);
+        assert_eq!(near.minimal_realization(1e-6).denominator().len(), 2);
+        assert_eq!(near.minimal_realization(1e-3).denominator(), [1.0]);
+    }
+}
@@ -0,0 +1,321 @@
+use nalgebra::{DMatrix, DVector};
+
+use crate::{continuous::ContinuousSystem, discrete::DiscreteSystem};
+
+pub mod fopdt;
+pub mod linearize;
+pub mod lti;
+pub mod lyapunov;
+mod minimal;
+pub mod reduction;
+pub mod riccati;
+pub mod transfer_function;
+pub mod trim;
+
+pub use self::{
+    fopdt::Fopdt,
+    linearize::{Linearization, linearize},
+    lti::{FeedbackSign, LinearModel, feedback},
+    lyapunov::{dlyap, lyap},
+    reduction::ReducedModel,
+    transfer_function::DiscreteTransferFunction,
+    trim::{Trim, TrimError, TrimPoint},
+};
+
+/// A continuous-time linear time-invariant model
+///
+/// $$\dot{x} = A x + B u$$
+/// $$y = C x + D u$$
+///
+/// The feedthrough term uses the last input given to `set_input`.
+#[derive(Clone, Debug)]
+pub struct StateSpace {
+    pub a: DMatrix<f64>,
+    pub b: DMatrix<f64>,
+    pub c: DMatrix<f64>,
+    pub d: DMatrix<f64>,
+    state: DVector<f64>,
+    input: DVector<f64>,
+    max_timestep: f64,
+}
+
+/// A discrete-time linear time-invariant model
+///
+/// $$x_{k+1} = A x_k + B u_k$$
+/// $$y_k = C x_k + D u_k$$
+///
+/// A step from $x_k$ with input $u_k$ outputs $y_k$, so a held model behaves
+/// like the equivalent [`DiscreteTransferFunction`].
+#[derive(Clone, Debug)]
+pub struct DiscreteStateSpace {
+    pub a: DMatrix<f64>,
+    pub b: DMatrix<f64>,
+    pub c: DMatrix<f64>,
+    pub d: DMatrix<f64>,
+    state: LinearState,
+    timestep: f64,
+}
+
+/// The state of a [`DiscreteStateSpace`] between two steps
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinearState {
+    pub state: DVector<f64>,
+    /// The output $C x_k + D u_k$ of the step that led to `state`.
+    pub output: DVector<f64>,
+}
+
+fn check_dimensions(a: &DMatrix<f64>, b: &DMatrix<f64>, c: &DMatrix<f64>, d: &DMatrix<f64>) {
+    let n = a.nrows();
+    assert_eq!(a.ncols(), n, "A must be square");
+    assert_eq!(b.nrows(), n, "B must have as many rows as A");
+    assert_eq!(c.ncols(), n, "C must have as many columns as A");
+    assert_eq!(d.nrows(), c.nrows(), "D must have as many rows as C");
+    assert_eq!(d.ncols(), b.ncols(), "D must have as many columns as B");
+}
+
+impl StateSpace {
+    pub fn new(a: DMatrix<f64>, b: DMatrix<f64>, c: DMatrix<f64>, d: DMatrix<f64>) -> Self {
+        check_dimensions(&a, &b, &c, &d);
+
+        Self {
+            state: DVector::zeros(a.nrows()),
+            input: DVector::zeros(b.ncols()),
+            max_timestep: f64::INFINITY,
+            a,
+            b,
+            c,
+            d,
+        }
+    }
+
+    /// Sets the largest step the model may be integrated with.
+    pub fn max_timestep(mut self, max_timestep: f64) -> Self {
+        self.max_timestep = max_timestep;
+        self
+    }
+
+    /// Sets the initial state of the model.
+    pub fn initial_state(mut self, state: DVector<f64>) -> Self {
+        assert_eq!(state.len(), self.states(), "State has the wrong dimension");
+        self.state = state;
+        self
+    }
+
+    pub fn states(&self) -> usize {
+        self.a.nrows()
+    }
+
+    pub fn inputs(&self) -> usize {
+        self.b.ncols()
+    }
+
+    pub fn outputs(&self) -> usize {
+        self.c.nrows()
+    }
+}
+
+impl DiscreteStateSpace {
+    pub fn new(
+        a: DMatrix<f64>,
+        b: DMatrix<f64>,
+        c: DMatrix<f64>,
+        d: DMatrix<f64>,
+        timestep: f64,
+    ) -> Self {
+        check_dimensions(&a, &b, &c, &d);
+
+        Self {
+            state: LinearState {
+                state: DVector::zeros(a.nrows()),
+                output: DVector::zeros(c.nrows()),
+            },
+            timestep,
+            a,
+            b,
+            c,
+            d,
+        }
+    }
+
+    /// Sets the initial state of the model, which outputs $C x_0$ until its
+    /// first step.
+    pub fn initial_state(mut self, state: DVector<f64>) -> Self {
+        assert_eq!(state.len(), self.states(), "State has the wrong dimension");
+        self.state.output = &self.c * &state;
+        self.state.state = state;
+        self
+    }
+
+    pub fn states(&self) -> usize {
+        self.a.nrows()
+    }
+
+    pub fn inputs(&self) -> usize {
+        self.b.ncols()
+    }
+
+    pub fn outputs(&self) -> usize {
+        self.c.nrows()
+    }
+}
+
+impl ContinuousSystem<DVector<f64>, DVector<f64>, DVector<f64>> for StateSpace {
+    fn get_derivative(
+        &self,
+        _time: f64,
+        state: &DVector<f64>,
+        input: &DVector<f64>,
+    ) -> DVector<f64> {
+        &self.a * state + &self.b * input
+    }
+
+    fn get_output(&self, _time: f64) -> DVector<f64> {
+        &self.c * &self.state + &self.d * &self.input
+    }
+
+    fn state(&self) -> &DVector<f64> {
+        &self.state
+    }
+
+    fn set_state(&mut self, new_state: &DVector<f64>) {
+        self.state.copy_from(new_state);
+    }
+
+    fn max_timestep(&self) -> f64 {
+        self.max_timestep
+    }
+
+    fn set_input(&mut self, input: &DVector<f64>) {
+        assert_eq!(input.len(), self.inputs(), "Input has the wrong dimension");
+        self.input.copy_from(input);
+    }
+
+    fn has_feedthrough(&self) -> bool {
+        self.d.iter().any(|&d| d != 0.0)
+    }
+}
+
+impl DiscreteSystem<DVector<f64>, LinearState, DVector<f64>> for DiscreteStateSpace {
+    fn next_state(&self, _time: f64, state: &LinearState, input: &DVector<f64>) -> LinearState {
+        LinearState {
+            state: &self.a * &state.state + &self.b * input,
+            output: &self.c * &state.state + &self.d * input,
+        }
+    }
+
+    fn get_output(&self) -> DVector<f64> {
+        self.state.output.clone()
+    }
+
+    fn state(&self) -> &LinearState {
+        &self.state
+    }
+
+    fn set_state(&mut self, new_state: &LinearState) {
+        self.state.clone_from(new_state);
+    }
+
+    fn timestep(&self) -> f64 {
+        self.timestep
+    }
+
+    fn has_feedthrough(&self) -> bool {
+        self.d.iter().any(|&d| d != 0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        continuous::integrator::RungeKutta4, discrete::holder::ZeroOrderHold, system::System,
+        utils::Param,
+    };
+    use nalgebra::{dmatrix, dvector};
+
+    #[test]
+    fn test_state_space_first_order_step() {
+        // dx/dt = -x + u, y = x
+        let plant = StateSpace::new(dmatrix![-1.0], dmatrix![1.0], dmatrix![1.0], dmatrix![0.0])
+            .max_timestep(0.01);
+        let mut sys = plant.with_integrator(RungeKutta4);
+
+        let mut last = 0.0;
+        sys.simulate(5.0, 0.01, Param::new(dvector![1.0]), &mut |x| {
+            last = x.output[0]
+        });
+
+        assert!((last - (1.0 - (-5.0f64).exp())).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_state_space_feedthrough() {
+        let mut plant =
+            StateSpace::new(dmatrix![-1.0], dmatrix![1.0], dmatrix![0.0], dmatrix![2.0]);
+        plant.set_input(&dvector![3.0]);
+
+        // Evaluating the derivative elsewhere leaves the recorded input alone
+        let d = plant.get_derivative(0.0, plant.state(), &dvector![5.0]);
+
+        assert_eq!(d, dvector![5.0]);
+        assert_eq!(ContinuousSystem::get_output(&plant, 0.0), dvector![6.0]);
+    }
+
+    #[test]
+    fn test_discrete_state_space_step() {
+        let mut plant = DiscreteStateSpace::new(
+            dmatrix![1.0, 1.0; 0.0, 1.0],
+            dmatrix![0.0; 1.0],
+            dmatrix![1.0, 0.0],
+            dmatrix![0.0],
+            0.1,
+        );
+
+        for _ in 0..3 {
+            let next = plant.next_state(0.0, plant.state(), &dvector![1.0]);
+            plant.set_state(&next);
+        }
+
+        // The third step outputs the state it started from
+        assert_eq!(plant.state().state, dvector![3.0, 3.0]);
+        assert_eq!(DiscreteSystem::get_output(&plant), dvector![1.0]);
+        assert_eq!(plant.timestep(), 0.1);
+    }
+
+    #[test]
+    fn test_held_discrete_state_space_matches_transfer_function() {
+        // x+ = 0.5 x + u, y = x + u is (1 + 0.5 z^-1) / (1 - 0.5 z^-1)
+        let plant = DiscreteStateSpace::new(
+            dmatrix![0.5],
+            dmatrix![1.0],
+            dmatrix![1.0],
+            dmatrix![1.0],
+            1.0,
+        );
+        let tf = DiscreteTransferFunction::new(vec![1.0, 0.5], vec![1.0, -0.5], 1.0);
+        let mut held = plant.with_holder(ZeroOrderHold::new());
+        let mut held_tf = tf.with_holder(ZeroOrderHold::new());
+
+        let impulse = [1.0, 0.0, 0.0, 0.0];
+        let mut outputs = vec![];
+        for (k, &u) in impulse.iter().enumerate() {
+            let time = (k + 1) as f64;
+            held.update(time, &dvector![u]);
+            held_tf.update(time, &u);
+            assert_eq!(held.get_output(time)[0], held_tf.get_output(time));
+            outputs.push(held.get_output(time)[0]);
+        }
+        assert_eq!(outputs, vec![1.0, 1.0, 0.5, 0.25]);
+    }
+
+    #[test]
+    #[should_panic(expected = "B must have as many rows as A")]
+    fn test_state_space_dimension_check() {
+        StateSpace::new(
+            dmatrix![1.0, 0.0; 0.0, 1.0],
+            dmatrix![1.0],
+            dmatrix![1.0, 0.0],
+            dmatrix![0.0],
+        );
+    }
+}
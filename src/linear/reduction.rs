@@ -0,0 +1,378 @@
+use nalgebra::{DMatrix, DVector};
+
+use crate::{
+    discrete::DiscreteSystem,
+    linear::{
+        DiscreteStateSpace, StateSpace,
+        lyapunov::{dlyap, lyap},
+    },
+};
+
+/// Hankel singular values below this fraction of the largest one are taken
+/// to belong to uncontrollable or unobservable states.
+const RANK_TOLERANCE: f64 = 1e-12;
+
+/// A reduced-order model, with the Hankel singular values of the model it
+/// was reduced from
+#[derive(Clone, Debug)]
+pub struct ReducedModel<Model> {
+    pub model: Model,
+    /// The Hankel singular values of the full model, in decreasing order.
+    /// Each measures how much its balanced state contributes to the
+    /// input/output behaviour.
+    pub hankel_singular_values: Vec<f64>,
+    order: usize,
+}
+
+impl<Model> ReducedModel<Model> {
+    /// The number of states kept.
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    /// Twice the sum of the discarded Hankel singular values, which bounds
+    /// the peak gain of the error between the full and reduced models.
+    pub fn error_bound(&self) -> f64 {
+        2.0 * self.hankel_singular_values[self.order..]
+            .iter()
+            .sum::<f64>()
+    }
+}
+
+/// How the weakest balanced states are removed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Method {
+    /// Drop them, which keeps the response exact at high frequencies.
+    Truncation,
+    /// Set them to their steady state, which keeps the DC gain exact.
+    Residualization,
+}
+
+/// Matrices of a state-space model, continuous or discrete.
+struct Realization {
+    a: DMatrix<f64>,
+    b: DMatrix<f64>,
+    c: DMatrix<f64>,
+    d: DMatrix<f64>,
+    discrete: bool,
+}
+
+/// A square root $L$ with $L L^T = X$, for a positive semi-definite $X$.
+fn square_root(x: DMatrix<f64>) -> DMatrix<f64> {
+    let eigen = x.symmetric_eigen();
+    let roots = eigen.eigenvalues.map(|l| l.max(0.0).sqrt());
+    eigen.eigenvectors * DMatrix::from_diagonal(&roots)
+}
+
+impl Realization {
+    /// The Gramians, or `None` if the model is not stable.
+    fn gramians(&self) -> Option<(DMatrix<f64>, DMatrix<f64>)> {
+        let solve = if self.discrete { dlyap } else { lyap };
+        let controllability = solve(&self.a, &(&self.b * self.b.transpose()))?;
+        let observability = solve(&self.a.transpose(), &(self.c.transpose() * &self.c))?;
+        Some((controllability, observability))
+    }
+
+    /// The Hankel singular values and the balancing projections onto the
+    /// states with a nonzero one, by the square root method.
+    fn balance(&self) -> Option<(Vec<f64>, DMatrix<f64>, DMatrix<f64>)> {
+        let (p, q) = self.gramians()?;
+        let lc = square_root(p);
+        let lo = square_root(q);
+
+        let svd = (lo.transpose() * &lc).svd(true, true);
+        let (u, v_t) = (svd.u?, svd.v_t?);
+        let values: Vec<f64> = svd.singular_values.iter().copied().collect();
+
+        let largest = values.first().copied().unwrap_or(0.0);
+        let rank = values
+            .iter()
+            .take_while(|&&s| s > RANK_TOLERANCE * largest)
+            .count();
+        let scale = DVector::from_iterator(rank, values[..rank].iter().map(|s| 1.0 / s.sqrt()));
+        let scale = DMatrix::from_diagonal(&scale);
+
+        let to_balanced = &scale * u.columns(0, rank).transpose() * lo.transpose();
+        let from_balanced = lc * v_t.rows(0, rank).transpose() * &scale;
+        Some((values, to_balanced, from_balanced))
+    }
+
+    /// Reduces the model to `order` states, returning the reduced matrices
+    /// and the Hankel singular values of the full model.
+    fn reduce(&self, order: usize, method: Method) -> Option<(Realization, Vec<f64>)> {
+        assert!(
+            order <= self.a.nrows(),
+            "The order must not exceed the number of states"
+        );
+        let (values, to_balanced, from_balanced) = self.balance()?;
+
+        // States with a zero Hankel singular value are dropped exactly
+        let rank = to_balanced.nrows();
+        let a = &to_balanced * &self.a * &from_balanced;
+        let b = &to_balanced * &self.b;
+        let c = &self.c * &from_balanced;
+        let r = order.min(rank);
+
+        let a11 = a.view((0, 0), (r, r));
+        let b1 = b.rows(0, r);
+        let c1 = c.columns(0, r);
+        let reduced = if method == Method::Truncation || r == rank {
+            Realization {
+                a: a11.into_owned(),
+                b: b1.into_owned(),
+                c: c1.into_owned(),
+                d: self.d.clone(),
+                discrete: self.discrete,
+            }
+        } else {
+            // Solve the fast states' equation for their steady state:
+            // 0 = A21 x1 + A22 x2 + B2 u, or x2 = A21 x1 + A22 x2 + B2 u
+            let a12 = a.view((0, r), (r, rank - r));
+            let a21 = a.view((r, 0), (rank - r, r));
+            let mut a22 = a.view((r, r), (rank - r, rank - r)).into_owned();
+            if self.discrete {
+                a22 -= DMatrix::identity(rank - r, rank - r);
+            }
+            let inverse = a22.try_inverse()?;
+            let b2 = b.rows(r, rank - r);
+            let c2 = c.columns(r, rank - r);
+
+            Realization {
+                a: a11 - a12 * &inverse * a21,
+                b: b1 - a12 * &inverse * b2,
+                c: c1 - c2 * &inverse * a21,
+                d: &self.d - c2 * &inverse * b2,
+                discrete: self.discrete,
+            }
+        };
+        Some((reduced, values))
+    }
+}
+
+impl StateSpace {
+    fn realization(&self) -> Realization {
+        Realization {
+            a: self.a.clone(),
+            b: self.b.clone(),
+            c: self.c.clone(),
+            d: self.d.clone(),
+            discrete: false,
+        }
+    }
+
+    fn reduce(&self, order: usize, method: Method) -> Option<ReducedModel<StateSpace>> {
+        let (reduced, hankel_singular_values) = self.realization().reduce(order, method)?;
+        let mut model = StateSpace::new(reduced.a, reduced.b, reduced.c, reduced.d);
+        model.max_timestep = self.max_timestep;
+        Some(ReducedModel {
+            order: model.states(),
+            model,
+            hankel_singular_values,
+        })
+    }
+
+    /// The Hankel singular values, in decreasing order, or `None` if the
+    /// model is not stable.
+    pub fn hankel_singular_values(&self) -> Option<Vec<f64>> {
+        Some(self.realization().balance()?.0)
+    }
+
+    /// Reduces a stable model to `order` states by balanced truncation,
+    /// dropping the states with the smallest Hankel singular values
+    ///
+    /// The reduced model is stable, matches the full one at high
+    /// frequencies, and its error is within `error_bound`. States
+    /// that are uncontrollable or unobservable are always dropped, so the
+    /// model may have fewer than `order` states. Returns `None` if the model
+    /// is not stable.
+    pub fn balanced_truncation(&self, order: usize) -> Option<ReducedModel<StateSpace>> {
+        self.reduce(order, Method::Truncation)
+    }
+
+    /// Reduces a stable model to `order` states by singular perturbation of
+    /// its balanced realization, which sets the weakest states to their
+    /// steady state
+    ///
+    /// Unlike `balanced_truncation`, it keeps the DC gain exact, at the cost
+    /// of accuracy at high frequencies, with the same error bound.
+    pub fn residualization(&self, order: usize) -> Option<ReducedModel<StateSpace>> {
+        self.reduce(order, Method::Residualization)
+    }
+}
+
+impl DiscreteStateSpace {
+    fn realization(&self) -> Realization {
+        Realization {
+            a: self.a.clone(),
+            b: self.b.clone(),
+            c: self.c.clone(),
+            d: self.d.clone(),
+            discrete: true,
+        }
+    }
+
+    fn reduce(&self, order: usize, method: Method) -> Option<ReducedModel<DiscreteStateSpace>> {
+        let (reduced, hankel_singular_values) = self.realization().reduce(order, method)?;
+        Some(ReducedModel {
+            order: reduced.a.nrows(),
+            model: DiscreteStateSpace::new(
+                reduced.a,
+                reduced.b,
+                reduced.c,
+                reduced.d,
+                self.timestep(),
+            ),
+            hankel_singular_values,
+        })
+    }
+
+    /// The Hankel singular values, in decreasing order, or `None` if the
+    /// model is not stable.
+    pub fn hankel_singular_values(&self) -> Option<Vec<f64>> {
+        Some(self.realization().balance()?.0)
+    }
+
+    /// Reduces a stable model to `order` states by balanced truncation, as
+    /// for `StateSpace`.
+    pub fn balanced_truncation(&self, order: usize) -> Option<ReducedModel<DiscreteStateSpace>> {
+        self.reduce(order, Method::Truncation)
+    }
+
+    /// Reduces a stable model to `order` states by singular perturbation of
+    /// its balanced realization, keeping the DC gain exact, as for
+    /// `StateSpace`.
+    pub fn residualization(&self, order: usize) -> Option<ReducedModel<DiscreteStateSpace>> {
+        self.reduce(order, Method::Residualization)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{Complex, dmatrix};
+
+    use super::*;
+
+    /// A flexible structure: a rigid-body lag and lightly damped modes of
+    /// decreasing participation.
+    fn flexible() -> StateSpace {
+        let modes = [
+            (2.0, 0.05, 1.0),
+            (7.0, 0.03, 0.3),
+            (15.0, 0.02, 0.05),
+            (40.0, 0.02, 0.01),
+        ];
+        let n = 1 + 2 * modes.len();
+        let mut a = DMatrix::zeros(n, n);
+        let mut b = DMatrix::zeros(n, 1);
+        let mut c = DMatrix::zeros(1, n);
+        a[(0, 0)] = -0.5;
+        b[0] = 1.0;
+        c[0] = 0.5;
+        for (i, &(omega, zeta, gain)) in modes.iter().enumerate() {
+            let k = 1 + 2 * i;
+            a[(k, k + 1)] = 1.0;
+            a[(k + 1, k)] = -omega * omega;
+            a[(k + 1, k + 1)] = -2.0 * zeta * omega;
+            b[k + 1] = 1.0;
+            c[k] = gain * omega * omega;
+        }
+        StateSpace::new(a, b, c, DMatrix::zeros(1, 1))
+    }
+
+    /// $C (s I - A)^{-1} B + D$ at `s`, for a single-input single-output model.
+    fn gain(
+        a: &DMatrix<f64>,
+        b: &DMatrix<f64>,
+        c: &DMatrix<f64>,
+        d: f64,
+        s: Complex<f64>,
+    ) -> Complex<f64> {
+        let n = a.nrows();
+        let resolvent = (DMatrix::<Complex<f64>>::identity(n, n) * s - a.map(Complex::from))
+            .try_inverse()
+            .unwrap();
+        (c.map(Complex::from) * resolvent * b.map(Complex::from))[0] + d
+    }
+
+    #[test]
+    fn test_first_order_hankel_singular_value() {
+        // 1 / (s + 1) has P = Q = 1/2
+        let model = StateSpace::new(dmatrix![-1.0], dmatrix![1.0], dmatrix![1.0], dmatrix![0.0]);
+        let values = model.hankel_singular_values().unwrap();
+        assert!((values[0] - 0.5).abs() < 1e-12);
+
+        let unstable = StateSpace::new(dmatrix![1.0], dmatrix![1.0], dmatrix![1.0], dmatrix![0.0]);
+        assert!(unstable.balanced_truncation(1).is_none());
+    }
+
+    #[test]
+    fn test_reduced_flexible_structure_within_bound() {
+        let full = flexible();
+        for reduced in [
+            full.balanced_truncation(5).unwrap(),
+            full.residualization(5).unwrap(),
+        ] {
+            assert_eq!(reduced.model.states(), 5);
+            let values = &reduced.hankel_singular_values;
+            assert!(values.windows(2).all(|w| w[0] >= w[1]));
+
+            let bound = reduced.error_bound();
+            let m = &reduced.model;
+            for k in 0..400 {
+                let s = Complex::new(0.0, 0.01 * 1.03f64.powi(k));
+                let error =
+                    gain(&full.a, &full.b, &full.c, 0.0, s) - gain(&m.a, &m.b, &m.c, m.d[0], s);
+                assert!(error.norm() <= bound * (1.0 + 1e-9));
+            }
+        }
+    }
+
+    #[test]
+    fn test_residualization_keeps_dc_gain() {
+        let full = flexible();
+        let dc = |m: &StateSpace| gain(&m.a, &m.b, &m.c, m.d[0], Complex::new(0.0, 0.0)).re;
+
+        let truncated = full.balanced_truncation(3).unwrap().model;
+        let residualized = full.residualization(3).unwrap().model;
+        assert!((dc(&residualized) - dc(&full)).abs() < 1e-9);
+        assert!((dc(&truncated) - dc(&full)).abs() > 1e-3);
+    }
+
+    #[test]
+    fn test_drops_unobservable_states() {
+        // The second state never reaches the output
+        let model = StateSpace::new(
+            dmatrix![-1.0, 0.0; 0.0, -2.0],
+            dmatrix![1.0; 1.0],
+            dmatrix![1.0, 0.0],
+            dmatrix![0.0],
+        );
+        let reduced = model.balanced_truncation(2).unwrap();
+        assert_eq!(reduced.model.states(), 1);
+        assert_eq!(reduced.order(), 1);
+        assert!(reduced.error_bound() < 1e-9);
+    }
+
+    #[test]
+    fn test_discrete_reduction() {
+        let plant = DiscreteStateSpace::new(
+            dmatrix![0.9, 0.0, 0.0; 0.0, 0.5, 0.0; 0.0, 0.0, -0.2],
+            dmatrix![1.0; 0.3; 0.05],
+            dmatrix![1.0, 1.0, 1.0],
+            dmatrix![0.0],
+            0.1,
+        );
+        let at = |m: &DiscreteStateSpace, z: Complex<f64>| gain(&m.a, &m.b, &m.c, m.d[0], z);
+        let one = Complex::new(1.0, 0.0);
+
+        let residualized = plant.residualization(1).unwrap();
+        assert!((at(&residualized.model, one) - at(&plant, one)).norm() < 1e-9);
+
+        let truncated = plant.balanced_truncation(1).unwrap();
+        let bound = truncated.error_bound();
+        for k in 0..100 {
+            let z = Complex::from_polar(1.0, std::f64::consts::PI * k as f64 / 99.0);
+            assert!((at(&truncated.model, z) - at(&plant, z)).norm() <= bound * (1.0 + 1e-9));
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use nalgebra::DMatrix;
+
+const MAX_ITERATIONS: usize = 100;
+const TOLERANCE: f64 = 1e-12;
+
+/// Solves the discrete algebraic Riccati equation
+///
+/// $$X = A^T X A - A^T X B (R + B^T X B)^{-1} B^T X A + Q$$
+///
+/// with the structure-preserving doubling algorithm. Returns `None` if the
+/// iteration does not converge, which happens when $(A, B)$ is not
+/// stabilizable or $(A, Q)$ has unobservable modes on the unit circle.
+pub fn dare(
+    a: &DMatrix<f64>,
+    b: &DMatrix<f64>,
+    q: &DMatrix<f64>,
+    r: &DMatrix<f64>,
+) -> Option<DMatrix<f64>> {
+    let n = a.nrows();
+    let identity = DMatrix::identity(n, n);
+
+    let mut ak = a.clone();
+    let mut gk = b * r.clone().try_inverse()? * b.transpose();
+    let mut hk = q.clone();
+
+    for _ in 0..MAX_ITERATIONS {
+        let w = (&identity + &gk * &hk).try_inverse()?;
+        let aw = &ak * &w;
+
+        let next_g = &gk + &aw * &gk * ak.transpose();
+        let next_h = &hk + ak.transpose() * &hk * &w * &ak;
+        ak = &aw * &ak;
+        gk = next_g;
+
+        let change = (&next_h - &hk).norm();
+        hk = next_h;
+
+        if change <= TOLERANCE * hk.norm().max(1.0) {
+            return Some((&hk + hk.transpose()) * 0.5);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::dmatrix;
+
+    #[test]
+    fn test_dare_scalar() {
+        // x = a^2 x - a^2 x^2 / (r + x) + q has a closed form for a = r = q = 1
+        let x = dare(
+            &dmatrix![1.0],
+            &dmatrix![1.0],
+            &dmatrix![1.0],
+            &dmatrix![1.0],
+        )
+        .unwrap();
+
+        assert!((x[0] - (1.0 + 5f64.sqrt()) / 2.0).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_dare_residual() {
+        let a = dmatrix![1.0, 0.1; 0.0, 1.0];
+        let b = dmatrix![0.005; 0.1];
+        let q = dmatrix![1.0, 0.0; 0.0, 0.5];
+        let r = dmatrix![0.1];
+
+        let x = dare(&a, &b, &q, &r).unwrap();
+        let gain = (&r + b.transpose() * &x * &b).try_inverse().unwrap() * b.transpose() * &x * &a;
+        let residual = a.transpose() * &x * &a - a.transpose() * &x * &b * gain + &q - &x;
+
+        assert!(residual.norm() < 1e-9);
+    }
+}
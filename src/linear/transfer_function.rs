@@ -0,0 +1,193 @@
+use nalgebra::DVector;
+
+use crate::discrete::DiscreteSystem;
+
+/// A discrete-time single-input single-output transfer function, in powers
+/// of the backward shift $z^{-1}$
+///
+/// $$H(z) = \frac{b_0 + b_1 z^{-1} + \dots + b_m z^{-m}}{1 + a_1 z^{-1} + \dots + a_n z^{-n}}$$
+///
+/// As a `DiscreteSystem`, each step takes the input $u_k$ and outputs $y_k$,
+/// so $b_0$ is a feedthrough. Its state holds the past outputs, then the
+/// past inputs. Transfer functions compare equal when their coefficients and
+/// timesteps do, whatever their state.
+#[derive(Clone, Debug)]
+pub struct DiscreteTransferFunction {
+    numerator: Vec<f64>,
+    denominator: Vec<f64>,
+    state: DVector<f64>,
+    timestep: f64,
+}
+
+impl DiscreteTransferFunction {
+    /// Creates a transfer function, scaling both polynomials so that the
+    /// leading coefficient of the denominator is one.
+    pub fn new(numerator: Vec<f64>, denominator: Vec<f64>, timestep: f64) -> Self {
+        assert!(!numerator.is_empty(), "The numerator must not be empty");
+        let leading = denominator.first().copied().unwrap_or(0.0);
+        assert!(
+            leading != 0.0,
+            "The denominator must have a nonzero leading coefficient"
+        );
+
+        let numerator: Vec<f64> = numerator.iter().map(|b| b / leading).collect();
+        let denominator: Vec<f64> = denominator.iter().map(|a| a / leading).collect();
+        let memory = (denominator.len() - 1).max(1) + numerator.len() - 1;
+
+        Self {
+            numerator,
+            denominator,
+            state: DVector::zeros(memory),
+            timestep,
+        }
+    }
+
+    /// Coefficients $b_0, \dots, b_m$.
+    pub fn numerator(&self) -> &[f64] {
+        &self.numerator
+    }
+
+    /// Coefficients $1, a_1, \dots, a_n$.
+    pub fn denominator(&self) -> &[f64] {
+        &self.denominator
+    }
+
+    /// Number of past outputs the state holds.
+    fn output_memory(&self) -> usize {
+        (self.denominator.len() - 1).max(1)
+    }
+
+    /// The steady-state gain $H(1)$.
+    pub fn dc_gain(&self) -> f64 {
+        self.numerator.iter().sum::<f64>() / self.denominator.iter().sum::<f64>()
+    }
+
+    /// The response to `inputs`, one per step, starting from rest.
+    pub fn response(&self, inputs: &[f64]) -> Vec<f64> {
+        let mut outputs: Vec<f64> = Vec::with_capacity(inputs.len());
+        for k in 0..inputs.len() {
+            let forced: f64 = self
+                .numerator
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i <= k)
+                .map(|(i, b)| b * inputs[k - i])
+                .sum();
+            let free: f64 = self.denominator[1..]
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i < k)
+                .map(|(i, a)| a * outputs[k - i - 1])
+                .sum();
+            outputs.push(forced - free);
+        }
+        outputs
+    }
+}
+
+impl PartialEq for DiscreteTransferFunction {
+    fn eq(&self, other: &Self) -> bool {
+        self.numerator == other.numerator
+            && self.denominator == other.denominator
+            && self.timestep == other.timestep
+    }
+}
+
+impl DiscreteSystem<f64, DVector<f64>, f64> for DiscreteTransferFunction {
+    fn next_state(&self, _time: f64, state: &DVector<f64>, input: &f64) -> DVector<f64> {
+        let outputs = self.output_memory();
+        let past_outputs = state.rows(0, outputs);
+        let past_inputs = state.rows(outputs, state.len() - outputs);
+
+        let output = self.numerator[0] * input
+            + self.numerator[1..]
+                .iter()
+                .zip(past_inputs.iter())
+                .map(|(b, u)| b * u)
+                .sum::<f64>()
+            - self.denominator[1..]
+                .iter()
+                .zip(past_outputs.iter())
+                .map(|(a, y)| a * y)
+                .sum::<f64>();
+
+        // Shift both histories by one step
+        let mut next = DVector::zeros(state.len());
+        next[0] = output;
+        for i in 1..outputs {
+            next[i] = state[i - 1];
+        }
+        if !past_inputs.is_empty() {
+            next[outputs] = *input;
+            for i in 1..past_inputs.len() {
+                next[outputs + i] = past_inputs[i - 1];
+            }
+        }
+        next
+    }
+
+    fn get_output(&self) -> f64 {
+        self.state[0]
+    }
+
+    fn state(&self) -> &DVector<f64> {
+        &self.state
+    }
+
+    fn set_state(&mut self, new_state: &DVector<f64>) {
+        self.state.copy_from(new_state);
+    }
+
+    fn timestep(&self) -> f64 {
+        self.timestep
+    }
+
+    fn has_feedthrough(&self) -> bool {
+        self.numerator[0] != 0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{discrete::holder::ZeroOrderHold, system::System};
+
+    #[test]
+    fn test_normalization_and_gain() {
+        let tf = DiscreteTransferFunction::new(vec![0.0, 1.0], vec![2.0, -1.0], 0.1);
+
+        assert_eq!(tf.numerator, vec![0.0, 0.5]);
+        assert_eq!(tf.denominator, vec![1.0, -0.5]);
+        assert_eq!(tf.dc_gain(), 1.0);
+    }
+
+    #[test]
+    fn test_equality_ignores_state() {
+        let tf = DiscreteTransferFunction::new(vec![1.0, 0.5], vec![1.0, -0.5], 0.1);
+        let mut stepped = tf.clone();
+        let next = stepped.next_state(0.1, stepped.state(), &1.0);
+        stepped.set_state(&next);
+
+        assert_eq!(stepped, tf);
+        assert_ne!(
+            DiscreteTransferFunction::new(vec![1.0, 0.5], vec![1.0, -0.5], 0.2),
+            tf
+        );
+    }
+
+    #[test]
+    fn test_held_system_matches_response() {
+        // y_k = 1.5 y_(k-1) - 0.7 y_(k-2) + u_k + 0.5 u_(k-1)
+        let tf = DiscreteTransferFunction::new(vec![1.0, 0.5], vec![1.0, -1.5, 0.7], 0.1);
+        let inputs: Vec<f64> = (0..30).map(|k| ((k * 7) % 5) as f64 - 2.0).collect();
+        let expected = tf.response(&inputs);
+
+        let mut held = tf.with_holder(ZeroOrderHold::new());
+        for (k, (u, y)) in inputs.iter().zip(&expected).enumerate() {
+            let time = (k + 1) as f64 * 0.1;
+            held.update(time, u);
+            assert!((held.get_output(time) - y).abs() < 1e-12);
+        }
+        assert!(held.has_feedthrough());
+    }
+}
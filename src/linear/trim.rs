@@ -0,0 +1,273 @@
+use std::fmt;
+
+use nalgebra::{DMatrix, DVector};
+
+use crate::{continuous::ContinuousSystem, linear::linearize::jacobian};
+
+/// An operating point found by `Trim`.
+#[derive(Clone, Debug)]
+pub struct TrimPoint {
+    pub state: DVector<f64>,
+    pub input: DVector<f64>,
+    /// The constrained derivatives at the operating point.
+    pub residual: DVector<f64>,
+    pub iterations: usize,
+}
+
+/// Returned when `Trim` cannot bring the residual within tolerance, holding
+/// the best point it found.
+#[derive(Clone, Debug)]
+pub struct TrimError {
+    pub best: TrimPoint,
+}
+
+impl fmt::Display for TrimError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "trim did not converge after {} iterations, largest residual is {:e}",
+            self.best.iterations,
+            self.best.residual.amax()
+        )
+    }
+}
+
+impl std::error::Error for TrimError {}
+
+/// A steady-state operating point solver for `ContinuousSystem`s
+///
+/// Searches the states and inputs for which `get_derivative` vanishes with
+/// the Levenberg–Marquardt method, starting from an initial guess. Components
+/// can be fixed to their guessed value (e.g. a fixed speed), and derivatives
+/// can be left unconstrained (e.g. a position that grows at that speed).
+#[derive(Clone, Debug)]
+pub struct Trim {
+    state: DVector<f64>,
+    input: DVector<f64>,
+    fixed_states: Vec<usize>,
+    fixed_inputs: Vec<usize>,
+    free_derivatives: Vec<usize>,
+    tolerance: f64,
+    max_iterations: usize,
+}
+
+impl Trim {
+    /// Starts the search from state `state` and input `input`.
+    pub fn new(state: DVector<f64>, input: DVector<f64>) -> Self {
+        Self {
+            state,
+            input,
+            fixed_states: Vec::new(),
+            fixed_inputs: Vec::new(),
+            free_derivatives: Vec::new(),
+            tolerance: 1e-9,
+            max_iterations: 100,
+        }
+    }
+
+    /// Keeps state `index` at `value`.
+    pub fn fix_state(mut self, index: usize, value: f64) -> Self {
+        self.state[index] = value;
+        self.fixed_states.push(index);
+        self
+    }
+
+    /// Keeps input `index` at `value`.
+    pub fn fix_input(mut self, index: usize, value: f64) -> Self {
+        self.input[index] = value;
+        self.fixed_inputs.push(index);
+        self
+    }
+
+    /// Allows the derivative of state `index` to be nonzero at the operating point.
+    pub fn free_derivative(mut self, index: usize) -> Self {
+        self.free_derivatives.push(index);
+        self
+    }
+
+    /// Sets the largest absolute residual accepted as converged.
+    pub fn tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    /// Solves for the operating point of `sys` at instant `time`.
+    pub fn solve<Sys>(&self, sys: &Sys, time: f64) -> Result<TrimPoint, TrimError>
+    where
+        Sys: ContinuousSystem<DVector<f64>, DVector<f64>, DVector<f64>>,
+    {
+        let n = self.state.len();
+        let m = self.input.len();
+
+        let free: Vec<usize> = (0..n)
+            .filter(|i| !self.fixed_states.contains(i))
+            .chain(
+                (0..m)
+                    .filter(|j| !self.fixed_inputs.contains(j))
+                    .map(|j| n + j),
+            )
+            .collect();
+        let constrained: Vec<usize> = (0..n)
+            .filter(|i| !self.free_derivatives.contains(i))
+            .collect();
+
+        let mut point =
+            DVector::from_iterator(n + m, self.state.iter().chain(self.input.iter()).copied());
+
+        let residual = |point: &DVector<f64>| {
+            let x = point.rows(0, n).into_owned();
+            let u = point.rows(n, m).into_owned();
+            let derivative = sys.get_derivative(time, &x, &u);
+            DVector::from_iterator(
+                constrained.len(),
+                constrained.iter().map(|&i| derivative[i]),
+            )
+        };
+        let with_free = |point: &DVector<f64>, z: &DVector<f64>| {
+            let mut point = point.clone();
+            for (k, &i) in free.iter().enumerate() {
+                point[i] = z[k];
+            }
+            point
+        };
+
+        let mut r = residual(&point);
+        let mut lambda = 1e-3;
+        let mut iterations = 0;
+
+        while r.amax() > self.tolerance && iterations < self.max_iterations && !free.is_empty() {
+            iterations += 1;
+
+            let z = DVector::from_iterator(free.len(), free.iter().map(|&i| point[i]));
+            let j = jacobian(&z, None, |z| residual(&with_free(&point, z)));
+            let jtj = j.transpose() * &j;
+            let gradient = j.transpose() * &r;
+
+            // Increases the damping until a step reduces the residual
+            let mut improved = false;
+            while lambda < 1e12 {
+                let damping = DMatrix::from_diagonal(&jtj.diagonal().map(|d| d.max(1e-12)));
+                let Some(step) = (&jtj + damping * lambda).lu().solve(&-&gradient) else {
+                    lambda *= 10.0;
+                    continue;
+                };
+
+                let candidate = with_free(&point, &(&z + step));
+                let candidate_r = residual(&candidate);
+                if candidate_r.norm() < r.norm() {
+                    point = candidate;
+                    r = candidate_r;
+                    lambda = (lambda / 10.0).max(1e-12);
+                    improved = true;
+                    break;
+                }
+                lambda *= 10.0;
+            }
+
+            if !improved {
+                break;
+            }
+        }
+
+        let trimmed = TrimPoint {
+            state: point.rows(0, n).into_owned(),
+            input: point.rows(n, m).into_owned(),
+            residual: r,
+            iterations,
+        };
+
+        if trimmed.residual.amax() <= self.tolerance {
+            Ok(trimmed)
+        } else {
+            Err(TrimError { best: trimmed })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::dvector;
+
+    // Longitudinal vehicle: position, speed; inputs are throttle and grade
+    struct Vehicle {
+        state: DVector<f64>,
+    }
+
+    impl ContinuousSystem<DVector<f64>, DVector<f64>, DVector<f64>> for Vehicle {
+        fn get_derivative(&self, _time: f64, x: &DVector<f64>, u: &DVector<f64>) -> DVector<f64> {
+            let drag = 0.4 * x[1] * x[1];
+            dvector![x[1], 2000.0 * u[0] - drag - 9.81 * 1000.0 * u[1].sin()] / 1000.0
+        }
+
+        fn get_output(&self, _time: f64) -> DVector<f64> {
+            dvector![self.state[1]]
+        }
+
+        fn state(&self) -> &DVector<f64> {
+            &self.state
+        }
+
+        fn set_state(&mut self, new_state: &DVector<f64>) {
+            self.state.copy_from(new_state);
+        }
+
+        fn max_timestep(&self) -> f64 {
+            0.01
+        }
+    }
+
+    fn vehicle() -> Vehicle {
+        Vehicle {
+            state: dvector![0.0, 0.0],
+        }
+    }
+
+    #[test]
+    fn test_trim_fixed_speed_free_throttle() {
+        let trim = Trim::new(dvector![0.0, 0.0], dvector![0.0, 0.0])
+            .fix_state(1, 30.0)
+            .fix_input(1, 0.02)
+            .free_derivative(0)
+            .solve(&vehicle(), 0.0)
+            .unwrap();
+
+        let expected = (0.4 * 900.0 + 9810.0 * 0.02f64.sin()) / 2000.0;
+        assert!((trim.input[0] - expected).abs() < 1e-9);
+        assert_eq!(trim.state[1], 30.0);
+        assert_eq!(trim.input[1], 0.02);
+    }
+
+    #[test]
+    fn test_trim_fixed_throttle_free_speed() {
+        let trim = Trim::new(dvector![0.0, 10.0], dvector![0.0, 0.0])
+            .fix_input(0, 0.5)
+            .fix_input(1, 0.0)
+            .fix_state(0, 0.0)
+            .free_derivative(0)
+            .solve(&vehicle(), 0.0)
+            .unwrap();
+
+        assert!((trim.state[1] - 2500f64.sqrt()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_trim_reports_residual_when_infeasible() {
+        // Stopped, with the throttle fixed, nothing can balance the grade
+        let error = Trim::new(dvector![0.0, 0.0], dvector![0.0, 0.0])
+            .fix_state(0, 0.0)
+            .fix_state(1, 0.0)
+            .fix_input(0, 0.0)
+            .fix_input(1, 0.1)
+            .solve(&vehicle(), 0.0)
+            .unwrap_err();
+
+        assert!((error.best.residual[1] + 9.81 * 0.1f64.sin()).abs() < 1e-9);
+        assert!(error.to_string().contains("did not converge"));
+    }
+}
@@ -0,0 +1,215 @@
+use nalgebra::{DMatrix, DVector, SymmetricEigen};
+
+use crate::{
+    optimization::derivative_free::{Bounds, Minimizer, Minimum, evaluate},
+    utils::Rng,
+};
+
+/// The covariance matrix adaptation evolution strategy, in its basic
+/// (μ/μ_w, λ) form
+///
+/// Samples falling outside the bounds are evaluated at their projection
+/// onto them, with a quadratic penalty on the distance moved, which steers
+/// the search back inside.
+#[derive(Clone, Debug)]
+pub struct CmaEs {
+    seed: u64,
+    step_size: f64,
+    population: Option<usize>,
+    max_iterations: usize,
+    tolerance: f64,
+}
+
+impl CmaEs {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            step_size: 0.5,
+            population: None,
+            max_iterations: 1000,
+            tolerance: 1e-8,
+        }
+    }
+
+    /// Sets the initial standard deviation of the samples.
+    pub fn step_size(mut self, step_size: f64) -> Self {
+        self.step_size = step_size;
+        self
+    }
+
+    /// Sets the number of samples per iteration, which defaults to
+    /// `4 + 3 ln(n)` for `n` variables.
+    pub fn population(mut self, population: usize) -> Self {
+        assert!(population >= 2, "The population needs at least two samples");
+        self.population = Some(population);
+        self
+    }
+
+    pub fn max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    /// Stops once the spread of the samples falls below `tolerance`.
+    pub fn tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+}
+
+impl Minimizer for CmaEs {
+    fn minimize(
+        &self,
+        cost: &mut dyn FnMut(&DVector<f64>) -> f64,
+        initial: &DVector<f64>,
+        bounds: &Bounds,
+    ) -> Minimum {
+        let n = initial.len();
+        assert!(n > 0, "There must be at least one variable");
+        assert_eq!(
+            bounds.len(),
+            n,
+            "Bounds must have as many entries as the initial point"
+        );
+        let nf = n as f64;
+
+        // Default strategy parameters, from Hansen's tutorial
+        let lambda = self
+            .population
+            .unwrap_or(4 + (3.0 * nf.ln()).floor() as usize);
+        let mu = lambda / 2;
+        let weights = DVector::from_fn(mu, |i, _| (mu as f64 + 0.5).ln() - ((i + 1) as f64).ln());
+        let weights = &weights / weights.sum();
+        let mueff = 1.0 / weights.norm_squared();
+
+        let cc = (4.0 + mueff / nf) / (nf + 4.0 + 2.0 * mueff / nf);
+        let cs = (mueff + 2.0) / (nf + mueff + 5.0);
+        let c1 = 2.0 / ((nf + 1.3).powi(2) + mueff);
+        let cmu = (1.0 - c1).min(2.0 * (mueff - 2.0 + 1.0 / mueff) / ((nf + 2.0).powi(2) + mueff));
+        let damps = 1.0 + 2.0 * (((mueff - 1.0) / (nf + 1.0)).sqrt() - 1.0).max(0.0) + cs;
+        let chi_n = nf.sqrt() * (1.0 - 1.0 / (4.0 * nf) + 1.0 / (21.0 * nf * nf));
+
+        let mut rng = Rng::new(self.seed);
+        let mut mean = bounds.clamp(initial);
+        let mut sigma = self.step_size;
+        let mut covariance = DMatrix::<f64>::identity(n, n);
+        let mut path_c = DVector::<f64>::zeros(n);
+        let mut path_s = DVector::<f64>::zeros(n);
+
+        let mut best_x = mean.clone();
+        let mut best_cost = evaluate(cost, &mean);
+        let mut evaluations = 1;
+        let mut history = Vec::new();
+        let mut converged = false;
+        let mut iterations = 0;
+
+        while iterations < self.max_iterations {
+            let eigen = SymmetricEigen::new(covariance.clone());
+            let scales = eigen.eigenvalues.map(|e| e.max(0.0).sqrt());
+            if sigma * scales.max() < self.tolerance {
+                converged = true;
+                break;
+            }
+            iterations += 1;
+
+            let mut samples: Vec<(DVector<f64>, f64)> = (0..lambda)
+                .map(|_| {
+                    let z = DVector::from_fn(n, |_, _| rng.normal());
+                    let x = &mean + &eigen.eigenvectors * z.component_mul(&scales) * sigma;
+                    let feasible = bounds.clamp(&x);
+                    let f = evaluate(cost, &feasible);
+                    if f < best_cost {
+                        best_cost = f;
+                        best_x = feasible.clone();
+                    }
+                    (x.clone(), f + (x - feasible).norm_squared())
+                })
+                .collect();
+            evaluations += lambda;
+            samples.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+
+            let old_mean = mean.clone();
+            mean = samples[..mu]
+                .iter()
+                .zip(weights.iter())
+                .fold(DVector::zeros(n), |m, ((x, _), w)| m + x * *w);
+            let step = (&mean - &old_mean) / sigma;
+
+            // C^(-1/2) = B D^(-1) B^T
+            let inverse_scales = scales.map(|s| if s > 0.0 { 1.0 / s } else { 0.0 });
+            let whitened = &eigen.eigenvectors
+                * (eigen.eigenvectors.transpose() * &step).component_mul(&inverse_scales);
+            path_s = path_s * (1.0 - cs) + whitened * (cs * (2.0 - cs) * mueff).sqrt();
+
+            let generations = 2.0 * iterations as f64;
+            let h_sigma = path_s.norm() / (1.0 - (1.0 - cs).powf(generations)).sqrt() / chi_n
+                < 1.4 + 2.0 / (nf + 1.0);
+            let h_sigma = if h_sigma { 1.0 } else { 0.0 };
+            path_c = path_c * (1.0 - cc) + &step * (h_sigma * (cc * (2.0 - cc) * mueff).sqrt());
+
+            let rank_mu = samples[..mu].iter().zip(weights.iter()).fold(
+                DMatrix::zeros(n, n),
+                |c, ((x, _), w)| {
+                    let y = (x - &old_mean) / sigma;
+                    c + &y * y.transpose() * *w
+                },
+            );
+            covariance = &covariance * (1.0 - c1 - cmu)
+                + (&path_c * path_c.transpose()
+                    + &covariance * ((1.0 - h_sigma) * cc * (2.0 - cc)))
+                    * c1
+                + rank_mu * cmu;
+            covariance = (&covariance + covariance.transpose()) * 0.5;
+
+            sigma *= ((cs / damps) * (path_s.norm() / chi_n - 1.0)).exp();
+            history.push(best_cost);
+        }
+
+        Minimum {
+            x: best_x,
+            cost: best_cost,
+            iterations,
+            evaluations,
+            history,
+            converged,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::dvector;
+
+    #[test]
+    fn test_rosenbrock() {
+        let minimum = CmaEs::new(1).minimize(
+            &mut |x| (1.0 - x[0]).powi(2) + 100.0 * (x[1] - x[0] * x[0]).powi(2),
+            &dvector![-1.2, 1.0],
+            &Bounds::unbounded(2),
+        );
+
+        assert!(minimum.converged);
+        assert!((minimum.x - dvector![1.0, 1.0]).amax() < 1e-4);
+    }
+
+    #[test]
+    fn test_multimodal_with_bounds() {
+        // Rastrigin's function, whose global minimum is at the origin, with
+        // its local minima near every integer point
+        let rastrigin = |x: &DVector<f64>| {
+            x.iter()
+                .map(|x| x * x - 10.0 * (std::f64::consts::TAU * x).cos() + 10.0)
+                .sum::<f64>()
+        };
+        let bounds = Bounds::new(dvector![-5.0, -5.0, -5.0], dvector![5.0, 5.0, 5.0]);
+        let minimum = CmaEs::new(4).step_size(2.0).population(40).minimize(
+            &mut |x| rastrigin(x),
+            &dvector![3.0, -2.0, 4.0],
+            &bounds,
+        );
+
+        assert!(minimum.x.amax() < 1e-4);
+        assert!(minimum.history.windows(2).all(|w| w[1] <= w[0]));
+    }
+}
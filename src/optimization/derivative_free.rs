@@ -0,0 +1,77 @@
+use nalgebra::DVector;
+
+/// Box bounds on the variables of a minimization, possibly infinite.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bounds {
+    pub lower: DVector<f64>,
+    pub upper: DVector<f64>,
+}
+
+impl Bounds {
+    pub fn new(lower: DVector<f64>, upper: DVector<f64>) -> Self {
+        assert_eq!(
+            lower.len(),
+            upper.len(),
+            "Lower and upper bounds must have the same size"
+        );
+        assert!(
+            lower.iter().zip(upper.iter()).all(|(l, u)| l <= u),
+            "Lower bounds must not exceed upper bounds"
+        );
+        Self { lower, upper }
+    }
+
+    /// No bounds on `n` variables.
+    pub fn unbounded(n: usize) -> Self {
+        Self::new(
+            DVector::from_element(n, f64::NEG_INFINITY),
+            DVector::from_element(n, f64::INFINITY),
+        )
+    }
+
+    pub fn len(&self) -> usize {
+        self.lower.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lower.is_empty()
+    }
+
+    /// Projects `x` onto the bounds.
+    pub fn clamp(&self, x: &DVector<f64>) -> DVector<f64> {
+        x.zip_zip_map(&self.lower, &self.upper, |x, l, u| x.clamp(l, u))
+    }
+}
+
+/// The best point found by a `Minimizer`.
+#[derive(Clone, Debug)]
+pub struct Minimum {
+    pub x: DVector<f64>,
+    pub cost: f64,
+    pub iterations: usize,
+    /// Number of times the cost was evaluated.
+    pub evaluations: usize,
+    /// The best cost found by the end of each iteration.
+    pub history: Vec<f64>,
+    /// Whether the tolerance was met before the iteration limit.
+    pub converged: bool,
+}
+
+/// A method minimizing a cost function from its values only
+///
+/// A NaN cost is treated as infinite, so that a simulation blowing up only
+/// rules out the point it was run at.
+pub trait Minimizer {
+    fn minimize(
+        &self,
+        cost: &mut dyn FnMut(&DVector<f64>) -> f64,
+        initial: &DVector<f64>,
+        bounds: &Bounds,
+    ) -> Minimum;
+}
+
+/// Evaluates `cost`, mapping NaNs to infinity.
+pub(crate) fn evaluate(cost: &mut dyn FnMut(&DVector<f64>) -> f64, x: &DVector<f64>) -> f64 {
+    let value = cost(x);
+    if value.is_nan() { f64::INFINITY } else { value }
+}
@@ -0,0 +1,11 @@
+pub mod cma_es;
+pub mod derivative_free;
+pub mod nelder_mead;
+pub mod qp;
+
+pub use self::{
+    cma_es::CmaEs,
+    derivative_free::{Bounds, Minimizer, Minimum},
+    nelder_mead::NelderMead,
+    qp::{AdmmSolver, QpSolution, QpStatus, QuadraticProgram},
+};
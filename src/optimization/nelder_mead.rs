@@ -0,0 +1,210 @@
+use nalgebra::DVector;
+
+use crate::optimization::derivative_free::{Bounds, Minimizer, Minimum, evaluate};
+
+/// The Nelder–Mead simplex method
+///
+/// Bounds are enforced by projecting every trial point onto them.
+#[derive(Clone, Debug)]
+pub struct NelderMead {
+    max_iterations: usize,
+    tolerance: f64,
+    initial_step: f64,
+}
+
+impl Default for NelderMead {
+    fn default() -> Self {
+        Self {
+            max_iterations: 1000,
+            tolerance: 1e-8,
+            initial_step: 0.05,
+        }
+    }
+}
+
+impl NelderMead {
+    pub fn max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    /// Stops once both the costs and the vertices of the simplex are within
+    /// `tolerance` of the best ones.
+    pub fn tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Sets the size of the initial simplex, relative to each initial value,
+    /// or absolute for those that are zero.
+    pub fn initial_step(mut self, initial_step: f64) -> Self {
+        self.initial_step = initial_step;
+        self
+    }
+}
+
+impl Minimizer for NelderMead {
+    fn minimize(
+        &self,
+        cost: &mut dyn FnMut(&DVector<f64>) -> f64,
+        initial: &DVector<f64>,
+        bounds: &Bounds,
+    ) -> Minimum {
+        let n = initial.len();
+        assert!(n > 0, "There must be at least one variable");
+        assert_eq!(
+            bounds.len(),
+            n,
+            "Bounds must have as many entries as the initial point"
+        );
+
+        let mut evaluations = 0;
+        let mut eval = |x: DVector<f64>| {
+            let x = bounds.clamp(&x);
+            evaluations += 1;
+            let f = evaluate(cost, &x);
+            (x, f)
+        };
+
+        let x0 = bounds.clamp(initial);
+        let mut simplex = vec![eval(x0.clone())];
+        for i in 0..n {
+            let step = if x0[i] == 0.0 {
+                self.initial_step
+            } else {
+                self.initial_step * x0[i].abs()
+            };
+            let mut x = x0.clone();
+            // Step away from the bound the initial point sits on, if any
+            x[i] += if x0[i] + step > bounds.upper[i] {
+                -step
+            } else {
+                step
+            };
+            simplex.push(eval(x));
+        }
+
+        let mut history = Vec::new();
+        let mut converged = false;
+        let mut iterations = 0;
+
+        while iterations < self.max_iterations {
+            simplex.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+
+            let best = &simplex[0];
+            let spread = simplex[n].1 - best.1;
+            let size = simplex
+                .iter()
+                .map(|(x, _)| (x - &best.0).amax())
+                .fold(0.0, f64::max);
+            if spread <= self.tolerance && size <= self.tolerance {
+                converged = true;
+                break;
+            }
+
+            iterations += 1;
+            let centroid = simplex[..n]
+                .iter()
+                .fold(DVector::zeros(n), |c, (x, _)| c + x)
+                / n as f64;
+            let (worst, f_worst) = simplex[n].clone();
+            let f_second = simplex[n - 1].1;
+
+            let reflected = eval(&centroid * 2.0 - &worst);
+            if reflected.1 < simplex[0].1 {
+                let expanded = eval(&centroid * 3.0 - &worst * 2.0);
+                simplex[n] = if expanded.1 < reflected.1 {
+                    expanded
+                } else {
+                    reflected
+                };
+            } else if reflected.1 < f_second {
+                simplex[n] = reflected;
+            } else {
+                let contracted = if reflected.1 < f_worst {
+                    eval((&centroid + &reflected.0) * 0.5)
+                } else {
+                    eval((&centroid + &worst) * 0.5)
+                };
+
+                if contracted.1 < reflected.1.min(f_worst) {
+                    simplex[n] = contracted;
+                } else {
+                    // Shrink towards the best vertex
+                    let best = simplex[0].0.clone();
+                    for vertex in &mut simplex[1..] {
+                        *vertex = eval((&best + &vertex.0) * 0.5);
+                    }
+                }
+            }
+
+            history.push(
+                simplex
+                    .iter()
+                    .map(|(_, f)| *f)
+                    .fold(f64::INFINITY, f64::min),
+            );
+        }
+
+        simplex.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+        let (x, cost) = simplex.swap_remove(0);
+
+        Minimum {
+            x,
+            cost,
+            iterations,
+            evaluations,
+            history,
+            converged,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::dvector;
+
+    fn rosenbrock(x: &DVector<f64>) -> f64 {
+        (1.0 - x[0]).powi(2) + 100.0 * (x[1] - x[0] * x[0]).powi(2)
+    }
+
+    #[test]
+    fn test_rosenbrock() {
+        let minimum = NelderMead::default().tolerance(1e-10).minimize(
+            &mut rosenbrock,
+            &dvector![-1.2, 1.0],
+            &Bounds::unbounded(2),
+        );
+
+        assert!(minimum.converged);
+        assert!((minimum.x.clone() - dvector![1.0, 1.0]).amax() < 1e-4);
+        assert_eq!(minimum.history.len(), minimum.iterations);
+        assert!(minimum.history.windows(2).all(|w| w[1] <= w[0]));
+    }
+
+    #[test]
+    fn test_active_bound() {
+        // The unconstrained minimum (3, -1) lies outside the box
+        let bounds = Bounds::new(dvector![0.0, 0.0], dvector![2.0, 2.0]);
+        let minimum = NelderMead::default().minimize(
+            &mut |x| (x[0] - 3.0).powi(2) + (x[1] + 1.0).powi(2),
+            &dvector![1.0, 1.0],
+            &bounds,
+        );
+
+        assert!((minimum.x - dvector![2.0, 0.0]).amax() < 1e-6);
+    }
+
+    #[test]
+    fn test_nan_costs_are_avoided() {
+        // The cost is undefined below x = 0.5
+        let minimum = NelderMead::default().minimize(
+            &mut |x| if x[0] < 0.5 { f64::NAN } else { x[0] },
+            &dvector![2.0],
+            &Bounds::unbounded(1),
+        );
+
+        assert!((minimum.x[0] - 0.5).abs() < 1e-6);
+    }
+}
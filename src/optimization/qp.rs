@@ -0,0 +1,405 @@
+use nalgebra::{DMatrix, DVector, linalg::Cholesky};
+
+/// A convex quadratic program
+///
+/// $$\min_x \frac{1}{2} x^T P x + q^T x \quad \text{s.t.} \quad l \le A x \le u$$
+///
+/// Bounds may be infinite, and equality constraints are expressed with $l = u$.
+#[derive(Clone, Debug)]
+pub struct QuadraticProgram {
+    pub p: DMatrix<f64>,
+    pub q: DVector<f64>,
+    pub a: DMatrix<f64>,
+    pub lower: DVector<f64>,
+    pub upper: DVector<f64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QpStatus {
+    /// Primal and dual residuals are within tolerance.
+    Solved,
+    /// The iteration limit was reached before converging.
+    MaxIterations,
+    /// A certificate of primal infeasibility was found.
+    PrimalInfeasible,
+    /// A certificate of dual infeasibility (an unbounded objective) was found.
+    DualInfeasible,
+}
+
+#[derive(Clone, Debug)]
+pub struct QpSolution {
+    pub x: DVector<f64>,
+    /// The constraint values $A x$ projected onto the bounds.
+    pub z: DVector<f64>,
+    /// The Lagrange multipliers of the constraints.
+    pub y: DVector<f64>,
+    pub status: QpStatus,
+    pub iterations: usize,
+}
+
+/// A dense ADMM solver for `QuadraticProgram`s, following the operator
+/// splitting scheme of OSQP, with step size adaptation and infeasibility
+/// detection.
+#[derive(Clone, Debug)]
+pub struct AdmmSolver {
+    rho: f64,
+    sigma: f64,
+    alpha: f64,
+    max_iterations: usize,
+    eps_abs: f64,
+    eps_rel: f64,
+    eps_infeasible: f64,
+}
+
+impl Default for AdmmSolver {
+    fn default() -> Self {
+        Self {
+            rho: 0.1,
+            sigma: 1e-6,
+            alpha: 1.6,
+            max_iterations: 4000,
+            eps_abs: 1e-5,
+            eps_rel: 1e-5,
+            eps_infeasible: 1e-7,
+        }
+    }
+}
+
+fn inf_norm(v: &DVector<f64>) -> f64 {
+    v.amax()
+}
+
+/// Ruiz equilibration of a `QuadraticProgram`, so that the scaled problem
+/// has $\bar{P} = c D P D$, $\bar{q} = c D q$ and $\bar{A} = E A D$.
+struct Scaling {
+    variables: DVector<f64>,
+    constraints: DVector<f64>,
+    cost: f64,
+}
+
+impl Scaling {
+    const ITERATIONS: usize = 10;
+
+    fn new(qp: &QuadraticProgram) -> Self {
+        let (m, n) = qp.a.shape();
+        let mut p = qp.p.clone();
+        let mut a = qp.a.clone();
+        let mut variables = DVector::from_element(n, 1.0);
+        let mut constraints = DVector::from_element(m, 1.0);
+
+        let inverse_sqrt = |norm: f64| if norm < 1e-4 { 1.0 } else { 1.0 / norm.sqrt() };
+
+        for _ in 0..Self::ITERATIONS {
+            let dx = DVector::from_fn(n, |j, _| {
+                let p_norm = p.column(j).amax();
+                let a_norm = if m > 0 { a.column(j).amax() } else { 0.0 };
+                inverse_sqrt(p_norm.max(a_norm))
+            });
+            let dz = DVector::from_fn(m, |i, _| inverse_sqrt(a.row(i).amax()));
+
+            p = DMatrix::from_fn(n, n, |i, j| p[(i, j)] * dx[i] * dx[j]);
+            a = DMatrix::from_fn(m, n, |i, j| a[(i, j)] * dz[i] * dx[j]);
+            variables.component_mul_assign(&dx);
+            constraints.component_mul_assign(&dz);
+        }
+
+        let q = qp.q.component_mul(&variables);
+        let mean_norm = (0..n).map(|j| p.column(j).amax()).sum::<f64>() / n.max(1) as f64;
+        let norm = mean_norm.max(inf_norm(&q));
+        let cost = if norm < 1e-4 { 1.0 } else { 1.0 / norm };
+
+        Self {
+            variables,
+            constraints,
+            cost,
+        }
+    }
+
+    fn apply(&self, qp: &QuadraticProgram) -> QuadraticProgram {
+        let (d, e, c) = (&self.variables, &self.constraints, self.cost);
+        let (m, n) = qp.a.shape();
+
+        QuadraticProgram {
+            p: DMatrix::from_fn(n, n, |i, j| qp.p[(i, j)] * d[i] * d[j] * c),
+            q: qp.q.component_mul(d) * c,
+            a: DMatrix::from_fn(m, n, |i, j| qp.a[(i, j)] * e[i] * d[j]),
+            lower: qp.lower.component_mul(e),
+            upper: qp.upper.component_mul(e),
+        }
+    }
+
+    fn unscale_x(&self, x: &DVector<f64>) -> DVector<f64> {
+        x.component_mul(&self.variables)
+    }
+
+    fn unscale_z(&self, z: &DVector<f64>) -> DVector<f64> {
+        z.component_div(&self.constraints)
+    }
+
+    fn unscale_y(&self, y: &DVector<f64>) -> DVector<f64> {
+        y.component_mul(&self.constraints) / self.cost
+    }
+}
+
+impl AdmmSolver {
+    pub fn max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    pub fn tolerance(mut self, eps_abs: f64, eps_rel: f64) -> Self {
+        self.eps_abs = eps_abs;
+        self.eps_rel = eps_rel;
+        self
+    }
+
+    /// Sets the initial ADMM step size.
+    pub fn rho(mut self, rho: f64) -> Self {
+        self.rho = rho;
+        self
+    }
+
+    fn factorize(&self, qp: &QuadraticProgram, rho: f64) -> Cholesky<f64, nalgebra::Dyn> {
+        let n = qp.q.len();
+        let k = &qp.p + DMatrix::identity(n, n) * self.sigma + qp.a.transpose() * &qp.a * rho;
+        Cholesky::new(k).expect("QP objective is not convex")
+    }
+
+    /// Solves `qp`, starting from `warm_start` if given.
+    pub fn solve(&self, qp: &QuadraticProgram, warm_start: Option<&QpSolution>) -> QpSolution {
+        let n = qp.q.len();
+        let m = qp.lower.len();
+        assert_eq!(
+            qp.p.shape(),
+            (n, n),
+            "P must be square with as many rows as q"
+        );
+        assert_eq!(
+            qp.a.shape(),
+            (m, n),
+            "A must have one row per bound and one column per variable"
+        );
+        assert_eq!(
+            qp.upper.len(),
+            m,
+            "Lower and upper bounds must have the same size"
+        );
+
+        // ADMM iterates on an equilibrated copy of the problem, while
+        // convergence is checked on the original one
+        let scaling = Scaling::new(qp);
+        let scaled = scaling.apply(qp);
+
+        let clip =
+            |v: DVector<f64>| v.zip_zip_map(&scaled.lower, &scaled.upper, |v, l, u| v.clamp(l, u));
+        let at = scaled.a.transpose();
+
+        let (mut x, mut z, mut y) = match warm_start {
+            Some(s) if s.x.len() == n && s.y.len() == m => {
+                let x = s.x.component_div(&scaling.variables);
+                let z = clip(&scaled.a * &x);
+                let y = s.y.component_div(&scaling.constraints) * scaling.cost;
+                (x, z, y)
+            }
+            _ => (
+                DVector::zeros(n),
+                clip(DVector::zeros(m)),
+                DVector::zeros(m),
+            ),
+        };
+
+        let mut rho = self.rho;
+        let mut factor = self.factorize(&scaled, rho);
+        let mut status = QpStatus::MaxIterations;
+        let mut iterations = self.max_iterations;
+
+        for iteration in 1..=self.max_iterations {
+            let rhs = &x * self.sigma - &scaled.q + &at * (&z * rho - &y);
+            let x_tilde = factor.solve(&rhs);
+            let z_tilde = &scaled.a * &x_tilde;
+
+            let x_next = &x_tilde * self.alpha + &x * (1.0 - self.alpha);
+            let z_relaxed = &z_tilde * self.alpha + &z * (1.0 - self.alpha);
+            let z_next = clip(&z_relaxed + &y / rho);
+            let y_next = &y + (&z_relaxed - &z_next) * rho;
+
+            let dx = scaling.unscale_x(&(&x_next - &x));
+            let dy = scaling.unscale_y(&(&y_next - &y));
+            x = x_next;
+            z = z_next;
+            y = y_next;
+
+            let (xu, zu, yu) = (
+                scaling.unscale_x(&x),
+                scaling.unscale_z(&z),
+                scaling.unscale_y(&y),
+            );
+            let ax = &qp.a * &xu;
+            let px = &qp.p * &xu;
+            let aty = qp.a.transpose() * &yu;
+            let primal_residual = inf_norm(&(&ax - &zu));
+            let dual_residual = inf_norm(&(&px + &qp.q + &aty));
+
+            let primal_scale = inf_norm(&ax).max(inf_norm(&zu));
+            let dual_scale = inf_norm(&px).max(inf_norm(&aty)).max(inf_norm(&qp.q));
+
+            if primal_residual <= self.eps_abs + self.eps_rel * primal_scale
+                && dual_residual <= self.eps_abs + self.eps_rel * dual_scale
+            {
+                status = QpStatus::Solved;
+            } else if self.primal_infeasible(qp, &dy) {
+                status = QpStatus::PrimalInfeasible;
+            } else if self.dual_infeasible(qp, &dx) {
+                status = QpStatus::DualInfeasible;
+            }
+
+            if status != QpStatus::MaxIterations {
+                iterations = iteration;
+                break;
+            }
+
+            // Rebalance the step size between the primal and dual residuals
+            if iteration % 25 == 0 {
+                let ratio = (primal_residual / primal_scale.max(1e-12))
+                    / (dual_residual / dual_scale.max(1e-12)).max(1e-12);
+                let next_rho = (rho * ratio.sqrt()).clamp(1e-6, 1e6);
+                if next_rho > 5.0 * rho || next_rho < 0.2 * rho {
+                    rho = next_rho;
+                    factor = self.factorize(&scaled, rho);
+                }
+            }
+        }
+
+        QpSolution {
+            x: scaling.unscale_x(&x),
+            z: scaling.unscale_z(&z),
+            y: scaling.unscale_y(&y),
+            status,
+            iterations,
+        }
+    }
+
+    fn primal_infeasible(&self, qp: &QuadraticProgram, dy: &DVector<f64>) -> bool {
+        let norm = inf_norm(dy);
+        if norm < 1e-12 {
+            return false;
+        }
+
+        let support: f64 = dy
+            .iter()
+            .zip(qp.lower.iter().zip(qp.upper.iter()))
+            .map(|(&d, (&l, &u))| {
+                if d > 0.0 {
+                    u * d
+                } else if d < 0.0 {
+                    l * d
+                } else {
+                    0.0
+                }
+            })
+            .sum();
+
+        inf_norm(&(qp.a.transpose() * dy)) <= self.eps_infeasible * norm
+            && support < -self.eps_infeasible * norm
+    }
+
+    fn dual_infeasible(&self, qp: &QuadraticProgram, dx: &DVector<f64>) -> bool {
+        let norm = inf_norm(dx);
+        if norm < 1e-12 {
+            return false;
+        }
+        let eps = self.eps_infeasible * norm;
+
+        let bounded = (&qp.a * dx)
+            .iter()
+            .zip(qp.lower.iter().zip(qp.upper.iter()))
+            .all(|(&v, (&l, &u))| (u.is_infinite() || v <= eps) && (l.is_infinite() || v >= -eps));
+
+        bounded && inf_norm(&(&qp.p * dx)) <= eps && qp.q.dot(dx) < -eps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{dmatrix, dvector};
+
+    #[test]
+    fn test_unconstrained() {
+        let qp = QuadraticProgram {
+            p: dmatrix![2.0, 0.0; 0.0, 4.0],
+            q: dvector![-2.0, -4.0],
+            a: DMatrix::zeros(0, 2),
+            lower: dvector![],
+            upper: dvector![],
+        };
+        let solution = AdmmSolver::default().solve(&qp, None);
+
+        assert_eq!(solution.status, QpStatus::Solved);
+        assert!((solution.x - dvector![1.0, 1.0]).norm() < 1e-4);
+    }
+
+    #[test]
+    fn test_box_and_equality_constraints() {
+        // min x² + y² - 2x - 6y  s.t.  x + y = 2, 0 <= x <= 0.25
+        let qp = QuadraticProgram {
+            p: dmatrix![2.0, 0.0; 0.0, 2.0],
+            q: dvector![-2.0, -6.0],
+            a: dmatrix![1.0, 1.0; 1.0, 0.0],
+            lower: dvector![2.0, 0.0],
+            upper: dvector![2.0, 0.25],
+        };
+        let solution = AdmmSolver::default().solve(&qp, None);
+
+        assert_eq!(solution.status, QpStatus::Solved);
+        assert!((solution.x - dvector![0.0, 2.0]).norm() < 1e-4);
+    }
+
+    #[test]
+    fn test_warm_start_reduces_iterations() {
+        let qp = QuadraticProgram {
+            p: dmatrix![4.0, 1.0; 1.0, 2.0],
+            q: dvector![1.0, 1.0],
+            a: dmatrix![1.0, 1.0; 1.0, 0.0; 0.0, 1.0],
+            lower: dvector![1.0, 0.0, 0.0],
+            upper: dvector![1.0, 0.7, 0.7],
+        };
+        let solver = AdmmSolver::default();
+        let cold = solver.solve(&qp, None);
+        let warm = solver.solve(&qp, Some(&cold));
+
+        assert_eq!(cold.status, QpStatus::Solved);
+        assert!((cold.x - dvector![0.3, 0.7]).norm() < 1e-4);
+        assert!(warm.iterations < cold.iterations);
+    }
+
+    #[test]
+    fn test_primal_infeasible() {
+        // x >= 1 and x <= -1
+        let qp = QuadraticProgram {
+            p: dmatrix![1.0],
+            q: dvector![0.0],
+            a: dmatrix![1.0; 1.0],
+            lower: dvector![1.0, f64::NEG_INFINITY],
+            upper: dvector![f64::INFINITY, -1.0],
+        };
+        let solution = AdmmSolver::default().solve(&qp, None);
+
+        assert_eq!(solution.status, QpStatus::PrimalInfeasible);
+    }
+
+    #[test]
+    fn test_dual_infeasible() {
+        // min -x  s.t.  x >= 0
+        let qp = QuadraticProgram {
+            p: dmatrix![0.0],
+            q: dvector![-1.0],
+            a: dmatrix![1.0],
+            lower: dvector![0.0],
+            upper: dvector![f64::INFINITY],
+        };
+        let solution = AdmmSolver::default().solve(&qp, None);
+
+        assert_eq!(solution.status, QpStatus::DualInfeasible);
+    }
+}
@@ -1,8 +1,33 @@
 pub use crate::{
+    analysis::{Distribution, MonteCarlo, StepResponse, SteppedSine, Sweep, Welch},
     continuous::{
         ContinuousSystem, IntegratedSystem, PureIntegrator, PureIntegratorSystem, integrator::*,
     },
-    discrete::{DiscreteSystem, HeldSystem, holder::*},
-    system::{Sample, System, UnitSystem, cloop::ClosedLoop, gain::Gain},
-    utils::{Param, ParamWith},
+    control::{GainTuner, ModelPredictiveController, Pid, PidStructure, RelayExperiment},
+    discrete::{
+        DiscreteSystem, HeldSystem,
+        holder::*,
+        transition::{RateTransition, Transition},
+    },
+    estimation::{
+        DiscreteObserver, ExtendedKalmanFilter, KalmanFilter, Observer, UnscentedKalmanFilter,
+    },
+    identification::{IoData, MimoData, Orders, RecursiveLeastSquares, StepData},
+    linear::{
+        DiscreteStateSpace, DiscreteTransferFunction, FeedbackSign, Fopdt, LinearModel, StateSpace,
+        Trim, feedback, linearize,
+    },
+    optimization::{CmaEs, Minimizer, NelderMead},
+    system::{
+        Sample, System, UnitSystem,
+        cloop::{ClosedLoop, LoopSolver},
+        diagram::{Diagram, DiagramBuilder, Node},
+        gain::{Gain, MatrixGain, ScheduledGain},
+        lookup::{Extrapolation, Interpolation, LookupTable1D, LookupTable2D},
+        noise::{BandLimitedNoise, Prbs, RandomWalk, WhiteNoise},
+        parallel::ParallelSystem,
+        routing::{Adapter, Demux, Mux, Selector},
+        series::SeriesSystem,
+    },
+    utils::{Param, ParamWith, Rng},
 };
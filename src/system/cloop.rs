@@ -1,7 +1,39 @@
 use std::{marker::PhantomData, ops::Sub};
 
-use crate::{system::System};
+use nalgebra::DVector;
 
+use crate::{
+    linear::linearize::jacobian,
+    system::{System, routing::Signal},
+};
+
+/// How `ClosedLoop` handles an algebraic loop, i.e. when both of its paths
+/// have feedthrough.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LoopSolver {
+    /// Feeds back the output of the previous event, delaying the loop by one event.
+    #[default]
+    Delay,
+    /// Iterates the loop until the error settles, which converges when the
+    /// loop gain is below one.
+    FixedPoint,
+    /// Solves for the error with Newton's method, differentiating the loop by
+    /// finite differences.
+    Newton,
+}
+
+/// Describes a system whose output is fed back and subtracted from its input.
+///
+/// An algebraic loop is delayed by one event unless an iterative
+/// [`LoopSolver`] is chosen; `delayed_updates` counts how often that happened.
+///
+///                 +-----------+
+///  INPUT ---(+)---+  forward  +---+--- OUTPUT
+///            |-   +-----------+   |
+///            |    +-----------+   |
+///            +----+  feedback +---+
+///                 +-----------+
+///
 pub struct ClosedLoop<Input, Output, SysFw, SysFb>
 where
     SysFw: System<Input = Input, Output = Output>,
@@ -9,6 +41,10 @@ where
 {
     forward: SysFw,
     feedback: SysFb,
+    iterate: Option<Iterate<Self, Input>>,
+    tolerance: f64,
+    max_iterations: usize,
+    delayed_updates: usize,
     _dummy: PhantomData<(Input, Output)>,
 }
 
@@ -21,9 +57,111 @@ where
         Self {
             forward,
             feedback,
+            iterate: None,
+            tolerance: 1e-10,
+            max_iterations: 100,
+            delayed_updates: 0,
             _dummy: PhantomData,
         }
     }
+
+    /// Sets the largest change of the error accepted as converged.
+    pub fn tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    /// Whether both paths have feedthrough, so that the output depends on itself.
+    pub fn has_algebraic_loop(&self) -> bool {
+        self.forward.has_feedthrough() && self.feedback.has_feedthrough()
+    }
+
+    /// How many updates broke an algebraic loop by delaying the feedback,
+    /// which is zero if the loop is solved or there is none.
+    pub fn delayed_updates(&self) -> usize {
+        self.delayed_updates
+    }
+}
+
+impl<Input, Output, SysFw, SysFb> ClosedLoop<Input, Output, SysFw, SysFb>
+where
+    SysFw: System<Input = Input, Output = Output>,
+    SysFb: System<Input = Output, Output = Input>,
+    for<'a> &'a Input: Sub<Input, Output = Input>,
+{
+    /// Updates both paths with `error`, returning the next instant and the
+    /// error it leads to.
+    fn step(&mut self, time: f64, input: &Input, error: &Input) -> (f64, Input) {
+        let next1 = self.forward.update(time, error);
+        let next2 = self.feedback.update(time, &self.forward.get_output(time));
+
+        (next1.min(next2), input - self.feedback.get_output(time))
+    }
+}
+
+impl<Input, Output, SysFw, SysFb> ClosedLoop<Input, Output, SysFw, SysFb>
+where
+    SysFw: System<Input = Input, Output = Output>,
+    SysFb: System<Input = Output, Output = Input>,
+    Input: Signal,
+    for<'a> &'a Input: Sub<Input, Output = Input>,
+{
+    /// Sets how algebraic loops are handled. Loops without one are never delayed.
+    pub fn solver(mut self, solver: LoopSolver) -> Self {
+        self.iterate = match solver {
+            LoopSolver::Delay => None,
+            LoopSolver::FixedPoint => Some(Self::fixed_point),
+            LoopSolver::Newton => Some(Self::newton),
+        };
+        self
+    }
+
+    fn fixed_point(&mut self, time: f64, input: &Input, mut error: Input) -> f64 {
+        for _ in 0..self.max_iterations {
+            let (next, new_error) = self.step(time, input, &error);
+            if (to_vector(&new_error) - to_vector(&error)).amax() <= self.tolerance {
+                return next;
+            }
+            error = new_error;
+        }
+
+        panic!("Algebraic loop did not converge");
+    }
+
+    fn newton(&mut self, time: f64, input: &Input, error: Input) -> f64 {
+        let residual = |this: &mut Self, e: &DVector<f64>| {
+            let (next, new_error) = this.step(time, input, &Input::read(e.as_slice()));
+            (next, e - to_vector(&new_error))
+        };
+
+        let mut e = to_vector(&error);
+        for _ in 0..self.max_iterations {
+            let (next, r) = residual(self, &e);
+            if r.amax() <= self.tolerance {
+                return next;
+            }
+
+            let j = jacobian(&e, None, |e| residual(self, e).1);
+            let step = j.lu().solve(&-r).expect("Algebraic loop is singular");
+            e += step;
+        }
+
+        panic!("Algebraic loop did not converge");
+    }
+}
+
+/// An iterative solver of the algebraic loop of `Loop`, starting from an error.
+type Iterate<Loop, Input> = fn(&mut Loop, f64, &Input, Input) -> f64;
+
+fn to_vector<Data: Signal>(signal: &Data) -> DVector<f64> {
+    let mut components = Vec::with_capacity(signal.len());
+    signal.write(&mut components);
+    DVector::from_vec(components)
 }
 
 impl<Input, Output, SysFw, SysFb> System for ClosedLoop<Input, Output, SysFw, SysFb>
@@ -38,23 +176,41 @@ where
     fn update(&mut self, time: f64, input: &Input) -> f64 {
         let error = input - self.feedback.get_output(time);
 
-        let next1 = self.forward.update(time, &error);
-        let next2 = self.feedback.update(time, &self.forward.get_output(time));
+        if !self.has_algebraic_loop() {
+            return self.step(time, input, &error).0;
+        }
 
-        next1.min(next2)
+        match self.iterate {
+            Some(iterate) => iterate(self, time, input, error),
+            None => {
+                self.delayed_updates += 1;
+                self.step(time, input, &error).0
+            }
+        }
     }
 
     fn get_output(&self, time: f64) -> Output {
         self.forward.get_output(time)
     }
+
+    fn has_feedthrough(&self) -> bool {
+        self.forward.has_feedthrough()
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{prelude::Gain, system::UnitSystem, utils::Param};
     use super::*;
-    use nalgebra::{vector, Const, Owned, Vector};
-    
+    use crate::{
+        continuous::{ContinuousSystem, integrator::RungeKutta4},
+        discrete::{DiscreteSystem, holder::ZeroOrderHold},
+        linear::{DiscreteTransferFunction, StateSpace},
+        prelude::Gain,
+        system::UnitSystem,
+        utils::Param,
+    };
+    use nalgebra::{Const, DMatrix, Owned, Vector, dmatrix, dvector, vector};
+
     pub type VecN<const N: usize, S = Owned<f64, Const<N>, Const<1>>> = Vector<f64, Const<N>, S>;
 
     #[test]
@@ -68,6 +224,100 @@ mod tests {
 
         cloop.simulate(1.0, 0.2, input, &mut |x| out.push(x.output[0]));
 
-        assert_eq!(out, &[0.5, 0.25, 0.375, 0.3125, 0.34375])
+        assert_eq!(out, &[0.5, 0.25, 0.375, 0.3125, 0.34375]);
+        assert_eq!(cloop.delayed_updates(), 5);
+    }
+
+    #[test]
+    fn test_algebraic_loop_detection() {
+        let direct = ClosedLoop::new(Gain::<f64>::new(0.5), UnitSystem::default());
+        assert!(direct.has_algebraic_loop());
+
+        let plant = StateSpace::new(dmatrix![-1.0], dmatrix![1.0], dmatrix![1.0], dmatrix![0.0])
+            .with_integrator(RungeKutta4);
+        let dynamic = ClosedLoop::new(plant, UnitSystem::<DVector<f64>>::default());
+        assert!(!dynamic.has_algebraic_loop());
+        assert!(!dynamic.has_feedthrough());
+    }
+
+    #[test]
+    fn test_held_block_loop_detection() {
+        // A discrete gain acts on the error of the same instant
+        let gain = DiscreteTransferFunction::new(vec![0.5], vec![1.0], 0.2);
+        let direct = ClosedLoop::new(
+            gain.with_holder(ZeroOrderHold::new()),
+            UnitSystem::default(),
+        );
+        assert!(direct.has_algebraic_loop());
+
+        let delayed = DiscreteTransferFunction::new(vec![0.0, 0.5], vec![1.0], 0.2);
+        let dynamic = ClosedLoop::new(
+            delayed.with_holder(ZeroOrderHold::new()),
+            UnitSystem::default(),
+        );
+        assert!(!dynamic.has_algebraic_loop());
+    }
+
+    #[test]
+    fn test_fixed_point_solves_loop() {
+        let mut cloop = ClosedLoop::new(Gain::<VecN<1>>::new(0.5), UnitSystem::default())
+            .solver(LoopSolver::FixedPoint);
+        let mut out = vec![];
+
+        cloop.simulate(1.0, 0.2, Param::new(vector![1.0]), &mut |x| {
+            out.push(x.output[0])
+        });
+
+        // y = 0.5 (1 - y)
+        assert!(out.iter().all(|y| (y - 1.0 / 3.0).abs() < 1e-9));
+        assert_eq!(cloop.delayed_updates(), 0);
+    }
+
+    #[test]
+    fn test_newton_solves_loop() {
+        // A loop gain of 3 makes the fixed-point iteration diverge
+        let mut cloop = ClosedLoop::new(Gain::<VecN<1>>::new(3.0), UnitSystem::default())
+            .solver(LoopSolver::Newton);
+        let mut out = vec![];
+
+        cloop.simulate(0.4, 0.2, Param::new(vector![1.0]), &mut |x| {
+            out.push(x.output[0])
+        });
+
+        assert!(out.iter().all(|y| (y - 0.75).abs() < 1e-9));
+    }
+
+    #[test]
+    #[should_panic(expected = "Algebraic loop did not converge")]
+    fn test_fixed_point_divergence() {
+        let mut cloop = ClosedLoop::new(Gain::<f64>::new(3.0), UnitSystem::default())
+            .solver(LoopSolver::FixedPoint);
+
+        cloop.update(0.0, &1.0);
+    }
+
+    #[test]
+    fn test_solved_loop_with_feedthrough_plant() {
+        // dx/dt = -x + u, y = x + u, with unit feedback
+        let plant = StateSpace::new(dmatrix![-1.0], dmatrix![1.0], dmatrix![1.0], dmatrix![1.0])
+            .max_timestep(0.01)
+            .with_integrator(RungeKutta4);
+        // A static model, so that the feedback starts with an output of the right size
+        let unit = StateSpace::new(
+            DMatrix::zeros(0, 0),
+            DMatrix::zeros(0, 1),
+            DMatrix::zeros(1, 0),
+            dmatrix![1.0],
+        )
+        .with_integrator(RungeKutta4);
+        let mut cloop = ClosedLoop::new(plant, unit).solver(LoopSolver::Newton);
+        let mut last = 0.0;
+
+        cloop.simulate(10.0, 0.01, Param::new(dvector![1.0]), &mut |x| {
+            last = x.output[0]
+        });
+
+        // u = r - y gives dx/dt = -1.5 x + 0.5 r and y = (x + r) / 2
+        assert!((last - (1.0 / 3.0 + 1.0) / 2.0).abs() < 1e-6);
     }
 }
@@ -0,0 +1,765 @@
+use std::fmt;
+
+use nalgebra::DVector;
+
+use crate::system::{System, routing::Signal};
+
+/// Type-erased view of a `System` whose input and output are `Signal`s.
+trait Block {
+    fn step(&mut self, time: f64, input: &[f64]) -> f64;
+    fn write_output(&self, time: f64, out: &mut Vec<f64>);
+}
+
+impl<Sys> Block for Sys
+where
+    Sys: System,
+    Sys::Input: Signal,
+    Sys::Output: Signal,
+{
+    fn step(&mut self, time: f64, input: &[f64]) -> f64 {
+        System::update(self, time, &Sys::Input::read(input))
+    }
+
+    fn write_output(&self, time: f64, out: &mut Vec<f64>) {
+        self.get_output(time).write(out);
+    }
+}
+
+/// A block of a `Diagram`, with named input and output ports.
+///
+/// The system's input is the concatenation of its input ports, in the order
+/// they are declared, and its output is split the same way into output ports.
+pub struct Node {
+    block: Box<dyn Block>,
+    inputs: Vec<(String, usize)>,
+    outputs: Vec<(String, usize)>,
+    input_size: Option<usize>,
+    output_size: Option<usize>,
+    feedthrough: bool,
+}
+
+impl Node {
+    pub fn new<Sys>(system: Sys) -> Self
+    where
+        Sys: System + 'static,
+        Sys::Input: Signal,
+        Sys::Output: Signal,
+    {
+        Self {
+            feedthrough: system.has_feedthrough(),
+            block: Box::new(system),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            input_size: Sys::Input::SIZE,
+            output_size: Sys::Output::SIZE,
+        }
+    }
+
+    /// Declares an input port with `size` components.
+    pub fn input(mut self, name: &str, size: usize) -> Self {
+        self.inputs.push((name.to_owned(), size));
+        self
+    }
+
+    /// Declares an output port with `size` components.
+    pub fn output(mut self, name: &str, size: usize) -> Self {
+        self.outputs.push((name.to_owned(), size));
+        self
+    }
+
+    /// Overrides whether the node's output depends directly on its current
+    /// input, which defaults to `System::has_feedthrough`.
+    ///
+    /// Nodes without feedthrough (e.g. integrators) break algebraic loops, as
+    /// their output only depends on their state.
+    pub fn feedthrough(mut self, feedthrough: bool) -> Self {
+        self.feedthrough = feedthrough;
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DiagramError {
+    /// Two nodes or diagram inputs share a name.
+    DuplicateName(String),
+    /// A port name does not match any node port or diagram input.
+    UnknownPort(String),
+    /// A node's ports do not add up to its system's signal size.
+    PortSizes(String),
+    /// A wire connects ports of different sizes.
+    SizeMismatch { from: String, to: String },
+    /// An input port is driven by more than one wire.
+    MultipleDrivers(String),
+    /// An input port is not driven by any wire.
+    Unconnected(String),
+    /// A cycle of nodes with direct feedthrough, in dependency order.
+    AlgebraicLoop(Vec<String>),
+}
+
+impl fmt::Display for DiagramError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuplicateName(name) => write!(f, "name `{name}` is used more than once"),
+            Self::UnknownPort(port) => write!(f, "port `{port}` does not exist"),
+            Self::PortSizes(node) => {
+                write!(f, "ports of node `{node}` do not match its signal sizes")
+            }
+            Self::SizeMismatch { from, to } => {
+                write!(
+                    f,
+                    "wire from `{from}` to `{to}` connects ports of different sizes"
+                )
+            }
+            Self::MultipleDrivers(port) => write!(f, "port `{port}` is driven more than once"),
+            Self::Unconnected(port) => write!(f, "port `{port}` is not connected"),
+            Self::AlgebraicLoop(nodes) => {
+                write!(f, "algebraic loop through `{}`", nodes.join("` -> `"))
+            }
+        }
+    }
+}
+
+impl std::error::Error for DiagramError {}
+
+/// Where the components of a port come from.
+#[derive(Clone, Copy, Debug)]
+enum Source {
+    /// A slice of the diagram's input.
+    External { offset: usize, size: usize },
+    /// A slice of a node's output.
+    Node {
+        node: usize,
+        offset: usize,
+        size: usize,
+    },
+}
+
+struct Entry {
+    name: String,
+    node: Node,
+    /// The source of each input port.
+    sources: Vec<Option<Source>>,
+}
+
+impl Entry {
+    /// Whether the nodes it drives see its output of the current event,
+    /// which is the case of nodes with feedthrough and of sources.
+    fn reads_after_update(&self) -> bool {
+        self.node.feedthrough || self.sources.is_empty()
+    }
+}
+
+/// Collects the nodes and wires of a `Diagram`.
+pub struct DiagramBuilder {
+    entries: Vec<Entry>,
+    inputs: Vec<(String, usize)>,
+    outputs: Vec<(String, String)>,
+    wires: Vec<(String, String)>,
+}
+
+/// A block diagram of arbitrarily wired `System`s
+///
+/// Nodes communicate through named ports, written `node.port`, while the
+/// diagram's own inputs are referred to by their name alone. On every event,
+/// nodes are updated in topological order, so that each one sees the current
+/// output of the nodes with feedthrough or without inputs that drive it, and
+/// the previous output of the other nodes.
+///
+/// The diagram's input is the concatenation of its inputs, and its output
+/// the concatenation of its outputs, in the order they are declared.
+pub struct Diagram {
+    entries: Vec<Entry>,
+    order: Vec<usize>,
+    input_size: usize,
+    outputs: Vec<Source>,
+    feedthrough: bool,
+    values: Vec<Vec<f64>>,
+    input: Vec<f64>,
+    buffer: Vec<f64>,
+}
+
+impl Default for DiagramBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DiagramBuilder {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            wires: Vec::new(),
+        }
+    }
+
+    /// Adds `node` to the diagram, under `name`.
+    pub fn node(mut self, name: &str, node: Node) -> Self {
+        self.entries.push(Entry {
+            name: name.to_owned(),
+            sources: vec![None; node.inputs.len()],
+            node,
+        });
+        self
+    }
+
+    /// Declares an input of the diagram with `size` components.
+    pub fn input(mut self, name: &str, size: usize) -> Self {
+        self.inputs.push((name.to_owned(), size));
+        self
+    }
+
+    /// Exposes the port `from` as an output of the diagram.
+    pub fn output(mut self, name: &str, from: &str) -> Self {
+        self.outputs.push((name.to_owned(), from.to_owned()));
+        self
+    }
+
+    /// Wires the output port or diagram input `from` to the input port `to`.
+    pub fn connect(mut self, from: &str, to: &str) -> Self {
+        self.wires.push((from.to_owned(), to.to_owned()));
+        self
+    }
+
+    fn source(&self, port: &str) -> Result<Source, DiagramError> {
+        let unknown = || DiagramError::UnknownPort(port.to_owned());
+
+        let Some((node, name)) = port.split_once('.') else {
+            let mut offset = 0;
+            for (input, size) in &self.inputs {
+                if input == port {
+                    return Ok(Source::External {
+                        offset,
+                        size: *size,
+                    });
+                }
+                offset += size;
+            }
+            return Err(unknown());
+        };
+
+        let index = self
+            .entries
+            .iter()
+            .position(|e| e.name == node)
+            .ok_or_else(unknown)?;
+        let mut offset = 0;
+        for (output, size) in &self.entries[index].node.outputs {
+            if output == name {
+                return Ok(Source::Node {
+                    node: index,
+                    offset,
+                    size: *size,
+                });
+            }
+            offset += size;
+        }
+        Err(unknown())
+    }
+
+    fn sink(&self, port: &str) -> Result<(usize, usize, usize), DiagramError> {
+        let unknown = || DiagramError::UnknownPort(port.to_owned());
+        let (node, name) = port.split_once('.').ok_or_else(unknown)?;
+
+        let index = self
+            .entries
+            .iter()
+            .position(|e| e.name == node)
+            .ok_or_else(unknown)?;
+        let inputs = &self.entries[index].node.inputs;
+        let slot = inputs
+            .iter()
+            .position(|(n, _)| n == name)
+            .ok_or_else(unknown)?;
+        Ok((index, slot, inputs[slot].1))
+    }
+
+    fn check_names(&self) -> Result<(), DiagramError> {
+        let mut names: Vec<&str> = self.entries.iter().map(|e| e.name.as_str()).collect();
+        names.extend(self.inputs.iter().map(|(n, _)| n.as_str()));
+
+        for (i, name) in names.iter().enumerate() {
+            if names[..i].contains(name) {
+                return Err(DiagramError::DuplicateName(name.to_string()));
+            }
+        }
+
+        for entry in &self.entries {
+            let node = &entry.node;
+            let inputs: usize = node.inputs.iter().map(|(_, s)| s).sum();
+            let outputs: usize = node.outputs.iter().map(|(_, s)| s).sum();
+            if node.input_size.is_some_and(|s| s != inputs)
+                || node.output_size.is_some_and(|s| s != outputs)
+            {
+                return Err(DiagramError::PortSizes(entry.name.clone()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Orders the nodes so that every node comes after the nodes with
+    /// feedthrough that drive it.
+    fn order(entries: &[Entry]) -> Result<Vec<usize>, DiagramError> {
+        let drivers = |i: usize| {
+            entries[i].sources.iter().filter_map(|s| match s {
+                Some(Source::Node { node, .. }) if entries[*node].reads_after_update() => {
+                    Some(*node)
+                }
+                _ => None,
+            })
+        };
+
+        let mut order = Vec::with_capacity(entries.len());
+        let mut done = vec![false; entries.len()];
+
+        while order.len() < entries.len() {
+            let ready = (0..entries.len()).find(|&i| !done[i] && drivers(i).all(|d| done[d]));
+
+            match ready {
+                Some(i) => {
+                    done[i] = true;
+                    order.push(i);
+                }
+                None => {
+                    // Walk back through pending drivers until a node repeats
+                    let mut path = vec![(0..entries.len()).find(|&i| !done[i]).unwrap()];
+                    loop {
+                        let last = *path.last().unwrap();
+                        let next = drivers(last).find(|&d| !done[d]).unwrap();
+                        if let Some(start) = path.iter().position(|&p| p == next) {
+                            let mut cycle: Vec<String> = path[start..]
+                                .iter()
+                                .map(|&i| entries[i].name.clone())
+                                .collect();
+                            // Each node drives the next one, starting from the first added
+                            cycle.reverse();
+                            let first = (0..cycle.len())
+                                .min_by_key(|&k| entries.iter().position(|e| e.name == cycle[k]));
+                            cycle.rotate_left(first.unwrap());
+                            return Err(DiagramError::AlgebraicLoop(cycle));
+                        }
+                        path.push(next);
+                    }
+                }
+            }
+        }
+
+        Ok(order)
+    }
+
+    /// Checks the wiring and orders the nodes.
+    pub fn build(mut self) -> Result<Diagram, DiagramError> {
+        self.check_names()?;
+
+        for (from, to) in &self.wires {
+            let source = self.source(from)?;
+            let (node, slot, size) = self.sink(to)?;
+
+            let source_size = match source {
+                Source::External { size, .. } | Source::Node { size, .. } => size,
+            };
+            if source_size != size {
+                return Err(DiagramError::SizeMismatch {
+                    from: from.clone(),
+                    to: to.clone(),
+                });
+            }
+
+            let sink = &mut self.entries[node].sources[slot];
+            if sink.is_some() {
+                return Err(DiagramError::MultipleDrivers(to.clone()));
+            }
+            *sink = Some(source);
+        }
+
+        for entry in &self.entries {
+            if let Some(slot) = entry.sources.iter().position(|s| s.is_none()) {
+                let port = format!("{}.{}", entry.name, entry.node.inputs[slot].0);
+                return Err(DiagramError::Unconnected(port));
+            }
+        }
+
+        let outputs = self
+            .outputs
+            .iter()
+            .map(|(_, from)| self.source(from))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let order = Self::order(&self.entries)?;
+
+        // Whether each node's output depends on the diagram's current input
+        let mut direct = vec![false; self.entries.len()];
+        for &i in &order {
+            let entry = &self.entries[i];
+            direct[i] = entry.node.feedthrough
+                && entry.sources.iter().any(|s| match s.unwrap() {
+                    Source::External { .. } => true,
+                    Source::Node { node, .. } => direct[node],
+                });
+        }
+        let feedthrough = outputs.iter().any(|s| match s {
+            Source::External { .. } => true,
+            Source::Node { node, .. } => direct[*node],
+        });
+
+        let mut values = Vec::with_capacity(self.entries.len());
+        for entry in &self.entries {
+            let mut value = Vec::new();
+            entry.node.block.write_output(0.0, &mut value);
+            values.push(value);
+        }
+
+        let input_size = self.inputs.iter().map(|(_, s)| s).sum();
+        Ok(Diagram {
+            input_size,
+            entries: self.entries,
+            order,
+            outputs,
+            feedthrough,
+            values,
+            input: vec![0.0; input_size],
+            buffer: Vec::new(),
+        })
+    }
+}
+
+impl Diagram {
+    fn read(values: &[Vec<f64>], input: &[f64], source: Source, out: &mut Vec<f64>) {
+        match source {
+            Source::External { offset, size } => out.extend(&input[offset..offset + size]),
+            Source::Node { node, offset, size } => out.extend(&values[node][offset..offset + size]),
+        }
+    }
+}
+
+impl System for Diagram {
+    type Input = DVector<f64>;
+    type Output = DVector<f64>;
+
+    fn update(&mut self, time: f64, input: &DVector<f64>) -> f64 {
+        assert_eq!(
+            input.len(),
+            self.input_size,
+            "Diagram input has the wrong size"
+        );
+        self.input.clear();
+        self.input.extend(input.iter());
+
+        // Nodes without feedthrough are read before being updated
+        for (entry, value) in self.entries.iter().zip(&mut self.values) {
+            if !entry.reads_after_update() {
+                value.clear();
+                entry.node.block.write_output(time, value);
+            }
+        }
+
+        let mut next = f64::INFINITY;
+        for &i in &self.order {
+            self.buffer.clear();
+            for source in &self.entries[i].sources {
+                Self::read(&self.values, &self.input, source.unwrap(), &mut self.buffer);
+            }
+
+            let entry = &mut self.entries[i];
+            next = next.min(entry.node.block.step(time, &self.buffer));
+
+            if entry.reads_after_update() {
+                let value = &mut self.values[i];
+                value.clear();
+                entry.node.block.write_output(time, value);
+            }
+        }
+
+        // Nodes read before the update report their new output once all have stepped
+        for (entry, value) in self.entries.iter().zip(&mut self.values) {
+            if !entry.reads_after_update() {
+                value.clear();
+                entry.node.block.write_output(time, value);
+            }
+        }
+
+        next
+    }
+
+    fn get_output(&self, _time: f64) -> DVector<f64> {
+        let mut out = Vec::new();
+        for &source in &self.outputs {
+            Self::read(&self.values, &self.input, source, &mut out);
+        }
+        DVector::from_vec(out)
+    }
+
+    fn has_feedthrough(&self) -> bool {
+        self.feedthrough
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        continuous::{ContinuousSystem, integrator::RectangularIntegrator},
+        linear::StateSpace,
+        system::{UnitSystem, cloop::ClosedLoop, gain::Gain},
+        utils::Param,
+    };
+    use nalgebra::{Const, Owned, Vector, dmatrix, dvector};
+
+    pub type VecN<const N: usize, S = Owned<f64, Const<N>, Const<1>>> = Vector<f64, Const<N>, S>;
+
+    // Subtracts the second component of its input from the first
+    #[derive(Default)]
+    struct Difference {
+        output: f64,
+    }
+
+    impl System for Difference {
+        type Input = VecN<2>;
+        type Output = f64;
+
+        fn update(&mut self, _time: f64, input: &VecN<2>) -> f64 {
+            self.output = input[0] - input[1];
+            f64::INFINITY
+        }
+
+        fn get_output(&self, _time: f64) -> f64 {
+            self.output
+        }
+    }
+
+    #[test]
+    fn test_diagram_matches_closed_loop() {
+        let mut diagram = DiagramBuilder::new()
+            .input("r", 1)
+            .node(
+                "sum",
+                Node::new(Difference::default())
+                    .input("r", 1)
+                    .input("y", 1)
+                    .output("e", 1),
+            )
+            .node(
+                "gain",
+                Node::new(Gain::<f64>::new(0.5))
+                    .input("u", 1)
+                    .output("y", 1),
+            )
+            .node(
+                "sensor",
+                Node::new(UnitSystem::<f64>::default())
+                    .input("u", 1)
+                    .output("y", 1)
+                    .feedthrough(false),
+            )
+            .connect("r", "sum.r")
+            .connect("sensor.y", "sum.y")
+            .connect("sum.e", "gain.u")
+            .connect("gain.y", "sensor.u")
+            .output("y", "gain.y")
+            .build()
+            .unwrap();
+
+        let mut out = vec![];
+        diagram.simulate(1.0, 0.2, Param::new(dvector![1.0]), &mut |x| {
+            out.push(x.output[0])
+        });
+
+        let mut cloop = ClosedLoop::new(Gain::<f64>::new(0.5), UnitSystem::default());
+        let mut expected = vec![];
+        cloop.simulate(1.0, 0.2, Param::new(1.0), &mut |x| expected.push(x.output));
+
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_diagram_with_integrator() {
+        // dx/dt = r - x, integrated with rectangles of 0.1
+        let integrator =
+            StateSpace::new(dmatrix![0.0], dmatrix![1.0], dmatrix![1.0], dmatrix![0.0])
+                .max_timestep(0.1)
+                .with_integrator(RectangularIntegrator);
+        let mut diagram = DiagramBuilder::new()
+            .input("r", 1)
+            .node(
+                "sum",
+                Node::new(Difference::default())
+                    .input("r", 1)
+                    .input("x", 1)
+                    .output("e", 1),
+            )
+            .node(
+                "integrator",
+                Node::new(integrator).input("u", 1).output("x", 1),
+            )
+            .connect("integrator.x", "sum.x")
+            .connect("r", "sum.r")
+            .connect("sum.e", "integrator.u")
+            .output("x", "integrator.x")
+            .build()
+            .unwrap();
+
+        // The integrator breaks the loop and the diagram's feedthrough
+        assert!(!diagram.has_feedthrough());
+
+        let mut last = 0.0;
+        diagram.simulate(10.0, 0.1, Param::new(dvector![1.0]), &mut |x| {
+            last = x.output[0]
+        });
+
+        assert!((last - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_declaration_order_does_not_change_the_trace() {
+        let trace = |integrator_first: bool| {
+            let integrator = || {
+                let plant =
+                    StateSpace::new(dmatrix![0.0], dmatrix![1.0], dmatrix![1.0], dmatrix![0.0])
+                        .max_timestep(0.1)
+                        .with_integrator(RectangularIntegrator);
+                Node::new(plant).input("u", 1).output("x", 1)
+            };
+            let gain = || {
+                Node::new(Gain::<f64>::new(2.0))
+                    .input("u", 1)
+                    .output("y", 1)
+            };
+            let builder = DiagramBuilder::new().input("r", 1);
+            let builder = if integrator_first {
+                builder
+                    .node("integrator", integrator())
+                    .node("gain", gain())
+            } else {
+                builder
+                    .node("gain", gain())
+                    .node("integrator", integrator())
+            };
+            let mut diagram = builder
+                .connect("r", "integrator.u")
+                .connect("integrator.x", "gain.u")
+                .output("y", "gain.y")
+                .build()
+                .unwrap();
+
+            let mut out = vec![];
+            diagram.simulate(0.4, 0.1, Param::new(dvector![1.0]), &mut |x| {
+                out.push(x.output[0])
+            });
+            out
+        };
+
+        assert_eq!(trace(true), trace(false));
+    }
+
+    #[test]
+    fn test_algebraic_loop_is_reported() {
+        let error = DiagramBuilder::new()
+            .node(
+                "a",
+                Node::new(Gain::<f64>::new(0.5))
+                    .input("u", 1)
+                    .output("y", 1),
+            )
+            .node(
+                "b",
+                Node::new(Gain::<f64>::new(0.5))
+                    .input("u", 1)
+                    .output("y", 1),
+            )
+            .node(
+                "c",
+                Node::new(Gain::<f64>::new(0.5))
+                    .input("u", 1)
+                    .output("y", 1),
+            )
+            .connect("b.y", "a.u")
+            .connect("a.y", "b.u")
+            .connect("a.y", "c.u")
+            .build()
+            .err()
+            .unwrap();
+
+        assert_eq!(
+            error,
+            DiagramError::AlgebraicLoop(vec!["a".into(), "b".into()])
+        );
+    }
+
+    #[test]
+    fn test_wiring_errors() {
+        let gain = || {
+            Node::new(Gain::<f64>::new(1.0))
+                .input("u", 1)
+                .output("y", 1)
+        };
+        let vector = || {
+            Node::new(Gain::<VecN<2>>::new(1.0))
+                .input("u", 2)
+                .output("y", 2)
+        };
+
+        let unconnected = DiagramBuilder::new().node("a", gain()).build().err();
+        assert_eq!(unconnected, Some(DiagramError::Unconnected("a.u".into())));
+
+        let unknown = DiagramBuilder::new()
+            .node("a", gain())
+            .connect("a.z", "a.u")
+            .build()
+            .err();
+        assert_eq!(unknown, Some(DiagramError::UnknownPort("a.z".into())));
+
+        let mismatch = DiagramBuilder::new()
+            .node("a", gain())
+            .node("b", vector())
+            .connect("a.y", "b.u")
+            .build()
+            .err();
+        assert!(matches!(mismatch, Some(DiagramError::SizeMismatch { .. })));
+
+        let sizes = DiagramBuilder::new()
+            .node(
+                "a",
+                Node::new(Gain::<VecN<2>>::new(1.0))
+                    .input("u", 1)
+                    .output("y", 2),
+            )
+            .build()
+            .err();
+        assert_eq!(sizes, Some(DiagramError::PortSizes("a".into())));
+
+        let duplicate = DiagramBuilder::new()
+            .input("a", 1)
+            .node("a", gain())
+            .build()
+            .err();
+        assert_eq!(duplicate, Some(DiagramError::DuplicateName("a".into())));
+    }
+
+    #[test]
+    fn test_mixed_signal_types() {
+        let mut diagram = DiagramBuilder::new()
+            .input("v", 2)
+            .node(
+                "double",
+                Node::new(Gain::<VecN<2>>::new(2.0))
+                    .input("u", 2)
+                    .output("y", 2),
+            )
+            .node(
+                "pass",
+                Node::new(UnitSystem::<DVector<f64>>::default())
+                    .input("u", 2)
+                    .output("y", 2),
+            )
+            .connect("v", "double.u")
+            .connect("double.y", "pass.u")
+            .output("y", "pass.y")
+            .build()
+            .unwrap();
+
+        diagram.update(0.0, &dvector![1.0, -1.0]);
+        assert_eq!(diagram.get_output(0.0), dvector![2.0, -2.0]);
+    }
+}
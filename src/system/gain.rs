@@ -1,9 +1,13 @@
-use std::ops::Mul;
+use std::{marker::PhantomData, ops::Mul};
 
-use super::System;
+use nalgebra::{DMatrix, DVector};
 
+use super::{System, routing::Signal};
+use crate::utils::Param;
+
+/// Multiplies its input by a scalar gain, which can follow a `Param` over time.
 pub struct Gain<Data> {
-    gain: f64,
+    gain: Param<f64>,
     output: Data,
 }
 
@@ -15,11 +19,11 @@ where
     type Input = Data;
     type Output = Data;
 
-    fn update(&mut self, _: f64, input: &Data) -> f64
+    fn update(&mut self, time: f64, input: &Data) -> f64
     where
         Data: Clone,
     {
-        self.output = input * self.gain;
+        self.output = input * *self.gain.at(time);
         f64::INFINITY
     }
 
@@ -33,18 +37,129 @@ impl<Data> Gain<Data> {
     where
         Data: Default,
     {
+        Self::scheduled(Param::new(gain))
+    }
+
+    /// Creates a gain taking the value of `gain` at each instant.
+    pub fn scheduled(gain: Param<f64>) -> Self
+    where
+        Data: Default,
+    {
+        Self {
+            gain,
+            output: Data::default(),
+        }
+    }
+}
+
+/// Multiplies its input by a matrix, mapping `n` inputs to `m` outputs.
+///
+///  INPUT (n) ---[ K (m x n) ]--- OUTPUT (m)
+///
+/// The matrix can follow a `Param` over time, e.g. to switch between gains
+/// designed for different operating points.
+pub struct MatrixGain<Input, Output> {
+    gain: Param<DMatrix<f64>>,
+    output: Output,
+    buffer: Vec<f64>,
+    _dummy: PhantomData<Input>,
+}
+
+impl<Input: Signal, Output: Signal> MatrixGain<Input, Output> {
+    pub fn new(gain: DMatrix<f64>) -> Self {
+        Self::scheduled(Param::new(gain))
+    }
+
+    /// Creates a gain taking the value of `gain` at each instant. All its
+    /// values must have the same shape.
+    pub fn scheduled(gain: Param<DMatrix<f64>>) -> Self {
+        let (rows, cols) = gain.shape();
+        if let Some(size) = Input::SIZE {
+            assert_eq!(cols, size, "Gain does not match the input size");
+        }
+        if let Some(size) = Output::SIZE {
+            assert_eq!(rows, size, "Gain does not match the output size");
+        }
+
         Self {
+            output: Output::read(&vec![0.0; rows]),
+            buffer: Vec::with_capacity(cols),
             gain,
+            _dummy: PhantomData,
+        }
+    }
+}
+
+impl<Input: Signal, Output: Signal> System for MatrixGain<Input, Output> {
+    type Input = Input;
+    type Output = Output;
+
+    fn update(&mut self, time: f64, input: &Input) -> f64 {
+        self.buffer.clear();
+        input.write(&mut self.buffer);
+
+        let gain = self.gain.at(time);
+        assert_eq!(
+            gain.ncols(),
+            self.buffer.len(),
+            "Gain does not match the input size"
+        );
+
+        let output = gain * DVector::from_column_slice(&self.buffer);
+        self.output = Output::read(output.as_slice());
+        f64::INFINITY
+    }
+
+    fn get_output(&self, _time: f64) -> Output {
+        self.output.clone()
+    }
+}
+
+/// Multiplies its input by a gain received alongside it, so that the gain can
+/// come from any signal, e.g. a table indexed by the operating point.
+///
+///  (INPUT, GAIN) ---[ x ]--- OUTPUT
+///
+pub struct ScheduledGain<Data> {
+    output: Data,
+}
+
+impl<Data: Default> ScheduledGain<Data> {
+    pub fn new() -> Self {
+        Self {
             output: Data::default(),
         }
     }
 }
 
+impl<Data: Default> Default for ScheduledGain<Data> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Data> System for ScheduledGain<Data>
+where
+    Data: Clone,
+    for<'a> &'a Data: Mul<f64, Output = Data>,
+{
+    type Input = (Data, f64);
+    type Output = Data;
+
+    fn update(&mut self, _time: f64, (input, gain): &(Data, f64)) -> f64 {
+        self.output = input * *gain;
+        f64::INFINITY
+    }
+
+    fn get_output(&self, _time: f64) -> Data {
+        self.output.clone()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::utils::Param;
-    use nalgebra::{vector, Const, Owned, Vector};
+    use nalgebra::{dmatrix, dvector, vector, Const, Owned, Vector};
     
     pub type VecN<const N: usize, S = Owned<f64, Const<N>, Const<1>>> = Vector<f64, Const<N>, S>;
 
@@ -72,4 +187,48 @@ mod tests {
 
         assert_eq!(count, 4);
     }
+
+    #[test]
+    fn test_scheduled_gain() {
+        let mut sys = Gain::<f64>::scheduled(Param::<f64>::new(2.0).step(-1.0, 0.25));
+        let mut out = vec![];
+
+        sys.simulate(0.4, 0.1, Param::new(3.0), &mut |x| out.push(x.output));
+
+        assert_eq!(out, vec![6.0, 6.0, 6.0, -3.0]);
+    }
+
+    #[test]
+    fn test_matrix_gain_maps_sizes() {
+        let mut sys = MatrixGain::<VecN<3>, VecN<2>>::new(dmatrix![1.0, 0.0, 1.0; 0.0, 2.0, 0.0]);
+
+        assert_eq!(sys.get_output(0.0), VecN::<2>::zeros());
+        sys.update(0.0, &vector![1.0, 2.0, 3.0]);
+        assert_eq!(sys.get_output(0.0), vector![4.0, 4.0]);
+    }
+
+    #[test]
+    fn test_matrix_gain_schedule_and_types() {
+        let gain = Param::<DMatrix<f64>>::new(dmatrix![1.0, 1.0]).step(dmatrix![1.0, -1.0], 1.0);
+        let mut sys = MatrixGain::<DVector<f64>, f64>::scheduled(gain);
+
+        sys.update(0.5, &dvector![2.0, 1.0]);
+        assert_eq!(sys.get_output(0.5), 3.0);
+        sys.update(1.0, &dvector![2.0, 1.0]);
+        assert_eq!(sys.get_output(1.0), 1.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Gain does not match the output size")]
+    fn test_matrix_gain_checks_shape() {
+        MatrixGain::<VecN<2>, VecN<2>>::new(dmatrix![1.0, 0.0]);
+    }
+
+    #[test]
+    fn test_gain_from_signal() {
+        let mut sys = ScheduledGain::<VecN<2>>::new();
+
+        sys.update(0.0, &(vector![1.0, -2.0], 0.5));
+        assert_eq!(sys.get_output(0.0), vector![0.5, -1.0]);
+    }
 }
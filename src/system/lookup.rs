@@ -0,0 +1,469 @@
+use std::{fs, io, path::Path};
+
+use nalgebra::{DMatrix, SVector};
+
+use crate::{
+    system::System,
+    utils::{csv_lines, invalid_data, parse_cells},
+};
+
+/// How a lookup table computes values between its breakpoints.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Interpolation {
+    #[default]
+    Linear,
+    /// Takes the value of the closest breakpoint.
+    Nearest,
+    /// Follows a natural cubic spline through the breakpoints.
+    CubicSpline,
+}
+
+/// How a lookup table computes values outside of its breakpoints.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Extrapolation {
+    /// Takes the value of the closest end of the table.
+    #[default]
+    Clamp,
+    /// Extends the line through the two breakpoints at that end of the table.
+    Linear,
+}
+
+fn check_breakpoints(breakpoints: &[f64]) -> Result<(), &'static str> {
+    if breakpoints.len() < 2 {
+        return Err("A table needs at least two breakpoints");
+    }
+    if breakpoints.windows(2).any(|w| w[0] >= w[1]) {
+        return Err("Breakpoints must be strictly increasing");
+    }
+    Ok(())
+}
+
+/// Second derivatives of the natural cubic spline through `(xs, ys)`.
+fn spline_moments(xs: &[f64], ys: &[f64]) -> Vec<f64> {
+    let n = xs.len();
+    let mut moments = vec![0.0; n];
+    if n < 3 {
+        return moments;
+    }
+
+    // Thomas algorithm on the interior points, the ends having no curvature
+    let mut diagonal = vec![0.0; n];
+    let mut rhs = vec![0.0; n];
+    for i in 1..n - 1 {
+        let (h0, h1) = (xs[i] - xs[i - 1], xs[i + 1] - xs[i]);
+        diagonal[i] = 2.0 * (h0 + h1);
+        rhs[i] = 6.0 * ((ys[i + 1] - ys[i]) / h1 - (ys[i] - ys[i - 1]) / h0);
+
+        if i > 1 {
+            let factor = h0 / diagonal[i - 1];
+            diagonal[i] -= factor * h0;
+            rhs[i] -= factor * rhs[i - 1];
+        }
+    }
+    for i in (1..n - 1).rev() {
+        let h1 = xs[i + 1] - xs[i];
+        moments[i] = (rhs[i] - h1 * moments[i + 1]) / diagonal[i];
+    }
+
+    moments
+}
+
+fn interpolate(
+    xs: &[f64],
+    ys: &[f64],
+    moments: &[f64],
+    x: f64,
+    interpolation: Interpolation,
+    extrapolation: Extrapolation,
+) -> f64 {
+    let n = xs.len();
+
+    let x = if x < xs[0] || x > xs[n - 1] {
+        match extrapolation {
+            Extrapolation::Clamp => x.clamp(xs[0], xs[n - 1]),
+            Extrapolation::Linear => {
+                let i = if x < xs[0] { 0 } else { n - 2 };
+                let slope = (ys[i + 1] - ys[i]) / (xs[i + 1] - xs[i]);
+                return ys[i] + slope * (x - xs[i]);
+            }
+        }
+    } else {
+        x
+    };
+
+    let i = xs.partition_point(|&b| b <= x).clamp(1, n - 1) - 1;
+    let h = xs[i + 1] - xs[i];
+    let t = (x - xs[i]) / h;
+
+    match interpolation {
+        Interpolation::Linear => ys[i] + t * (ys[i + 1] - ys[i]),
+        Interpolation::Nearest => {
+            if t < 0.5 {
+                ys[i]
+            } else {
+                ys[i + 1]
+            }
+        }
+        Interpolation::CubicSpline => {
+            let s = 1.0 - t;
+            s * ys[i]
+                + t * ys[i + 1]
+                + h * h / 6.0 * ((s.powi(3) - s) * moments[i] + (t.powi(3) - t) * moments[i + 1])
+        }
+    }
+}
+
+/// A table of `f64` values indexed by one breakpoint
+///
+///  INPUT ---[ table ]--- OUTPUT
+///
+pub struct LookupTable1D {
+    breakpoints: Vec<f64>,
+    values: Vec<f64>,
+    moments: Vec<f64>,
+    interpolation: Interpolation,
+    extrapolation: Extrapolation,
+    output: f64,
+}
+
+impl LookupTable1D {
+    /// Creates a table taking `values[i]` at `breakpoints[i]`, with linear
+    /// interpolation and clamped extrapolation.
+    pub fn new(breakpoints: Vec<f64>, values: Vec<f64>) -> Self {
+        check_breakpoints(&breakpoints).unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(
+            breakpoints.len(),
+            values.len(),
+            "There must be one value per breakpoint"
+        );
+
+        Self {
+            moments: spline_moments(&breakpoints, &values),
+            breakpoints,
+            values,
+            interpolation: Interpolation::default(),
+            extrapolation: Extrapolation::default(),
+            output: 0.0,
+        }
+    }
+
+    /// Parses a table with one breakpoint and one value per line, after an
+    /// optional header line.
+    pub fn from_csv_str(text: &str) -> io::Result<Self> {
+        let mut lines = csv_lines(text).peekable();
+        if lines.peek().is_some_and(|line| parse_cells(line).is_err()) {
+            lines.next();
+        }
+
+        let mut breakpoints = Vec::new();
+        let mut values = Vec::new();
+        for line in lines {
+            match parse_cells(line)?[..] {
+                [Some(x), Some(y)] => {
+                    breakpoints.push(x);
+                    values.push(y);
+                }
+                _ => return Err(invalid_data("Each line must hold a breakpoint and a value")),
+            }
+        }
+
+        check_breakpoints(&breakpoints).map_err(invalid_data)?;
+        Ok(Self::new(breakpoints, values))
+    }
+
+    /// Reads a table from the CSV file at `path`, as in `from_csv_str`.
+    pub fn from_csv(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_csv_str(&fs::read_to_string(path)?)
+    }
+
+    pub fn interpolation(mut self, interpolation: Interpolation) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
+
+    pub fn extrapolation(mut self, extrapolation: Extrapolation) -> Self {
+        self.extrapolation = extrapolation;
+        self
+    }
+
+    /// The value of the table at `x`.
+    pub fn lookup(&self, x: f64) -> f64 {
+        interpolate(
+            &self.breakpoints,
+            &self.values,
+            &self.moments,
+            x,
+            self.interpolation,
+            self.extrapolation,
+        )
+    }
+}
+
+impl System for LookupTable1D {
+    type Input = f64;
+    type Output = f64;
+
+    fn update(&mut self, _time: f64, input: &f64) -> f64 {
+        self.output = self.lookup(*input);
+        f64::INFINITY
+    }
+
+    fn get_output(&self, _time: f64) -> f64 {
+        self.output
+    }
+}
+
+/// A table of `f64` values indexed by two breakpoints, e.g. an engine map
+///
+/// The input holds the row coordinate, then the column coordinate. Both
+/// axes share the same interpolation and extrapolation, applied along the
+/// columns first, then along the rows.
+///
+///  [ROW, COLUMN] ---[ table ]--- OUTPUT
+///
+pub struct LookupTable2D {
+    rows: Vec<f64>,
+    columns: Vec<f64>,
+    values: DMatrix<f64>,
+    /// Spline moments along each row of `values`.
+    moments: Vec<Vec<f64>>,
+    interpolation: Interpolation,
+    extrapolation: Extrapolation,
+    output: f64,
+}
+
+impl LookupTable2D {
+    /// Creates a table taking `values[(i, j)]` at `(rows[i], columns[j])`,
+    /// with linear interpolation and clamped extrapolation.
+    pub fn new(rows: Vec<f64>, columns: Vec<f64>, values: DMatrix<f64>) -> Self {
+        check_breakpoints(&rows).unwrap_or_else(|e| panic!("{e}"));
+        check_breakpoints(&columns).unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(
+            values.shape(),
+            (rows.len(), columns.len()),
+            "There must be one value per pair of breakpoints"
+        );
+
+        let moments = values
+            .row_iter()
+            .map(|row| spline_moments(&columns, &row.iter().copied().collect::<Vec<_>>()))
+            .collect();
+
+        Self {
+            rows,
+            columns,
+            values,
+            moments,
+            interpolation: Interpolation::default(),
+            extrapolation: Extrapolation::default(),
+            output: 0.0,
+        }
+    }
+
+    /// Parses a table whose first line holds the column breakpoints after an
+    /// ignored corner cell, and whose other lines each hold a row breakpoint
+    /// followed by the values of that row.
+    pub fn from_csv_str(text: &str) -> io::Result<Self> {
+        let mut lines = csv_lines(text);
+        let header = lines
+            .next()
+            .ok_or_else(|| invalid_data("The table is empty"))?;
+        let (_, header) = header
+            .split_once(',')
+            .ok_or_else(|| invalid_data("The first line must hold column breakpoints"))?;
+
+        let columns = parse_cells(header)?
+            .into_iter()
+            .map(|c| c.ok_or_else(|| invalid_data("Column breakpoints cannot be empty")))
+            .collect::<io::Result<Vec<f64>>>()?;
+
+        let mut rows = Vec::new();
+        let mut values = Vec::new();
+        for line in lines {
+            let line = parse_cells(line)?;
+            if line.len() != columns.len() + 1 || line.iter().any(Option::is_none) {
+                return Err(invalid_data(
+                    "Each line must hold a breakpoint and a full row",
+                ));
+            }
+            rows.push(line[0].unwrap());
+            values.extend(line[1..].iter().map(|v| v.unwrap()));
+        }
+
+        // Every line holds a full row, so only the breakpoints need checking
+        check_breakpoints(&rows).map_err(invalid_data)?;
+        check_breakpoints(&columns).map_err(invalid_data)?;
+        let values = DMatrix::from_row_slice(rows.len(), columns.len(), &values);
+        Ok(Self::new(rows, columns, values))
+    }
+
+    /// Reads a table from the CSV file at `path`, as in `from_csv_str`.
+    pub fn from_csv(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_csv_str(&fs::read_to_string(path)?)
+    }
+
+    pub fn interpolation(mut self, interpolation: Interpolation) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
+
+    pub fn extrapolation(mut self, extrapolation: Extrapolation) -> Self {
+        self.extrapolation = extrapolation;
+        self
+    }
+
+    /// The value of the table at row coordinate `x` and column coordinate `y`.
+    pub fn lookup(&self, x: f64, y: f64) -> f64 {
+        let along_rows: Vec<f64> = self
+            .values
+            .row_iter()
+            .zip(&self.moments)
+            .map(|(row, moments)| {
+                let row: Vec<f64> = row.iter().copied().collect();
+                interpolate(
+                    &self.columns,
+                    &row,
+                    moments,
+                    y,
+                    self.interpolation,
+                    self.extrapolation,
+                )
+            })
+            .collect();
+
+        let moments = match self.interpolation {
+            Interpolation::CubicSpline => spline_moments(&self.rows, &along_rows),
+            _ => vec![0.0; self.rows.len()],
+        };
+        interpolate(
+            &self.rows,
+            &along_rows,
+            &moments,
+            x,
+            self.interpolation,
+            self.extrapolation,
+        )
+    }
+}
+
+impl System for LookupTable2D {
+    type Input = SVector<f64, 2>;
+    type Output = f64;
+
+    fn update(&mut self, _time: f64, input: &SVector<f64, 2>) -> f64 {
+        self.output = self.lookup(input[0], input[1]);
+        f64::INFINITY
+    }
+
+    fn get_output(&self, _time: f64) -> f64 {
+        self.output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::gain::ScheduledGain;
+    use nalgebra::{dmatrix, vector};
+
+    fn table() -> LookupTable1D {
+        LookupTable1D::new(vec![0.0, 1.0, 2.0, 4.0], vec![0.0, 1.0, 4.0, 16.0])
+    }
+
+    #[test]
+    fn test_linear_and_nearest() {
+        let linear = table();
+        assert_eq!(linear.lookup(1.5), 2.5);
+        assert_eq!(linear.lookup(3.0), 10.0);
+
+        let nearest = table().interpolation(Interpolation::Nearest);
+        assert_eq!(nearest.lookup(1.4), 1.0);
+        assert_eq!(nearest.lookup(2.9), 4.0);
+        assert_eq!(nearest.lookup(3.0), 16.0);
+    }
+
+    #[test]
+    fn test_extrapolation() {
+        assert_eq!(table().lookup(-1.0), 0.0);
+        assert_eq!(table().lookup(5.0), 16.0);
+
+        let linear = table().extrapolation(Extrapolation::Linear);
+        assert_eq!(linear.lookup(-1.0), -1.0);
+        assert_eq!(linear.lookup(5.0), 22.0);
+    }
+
+    #[test]
+    fn test_cubic_spline() {
+        // A natural spline reproduces straight lines, and passes through the breakpoints
+        let line = LookupTable1D::new(vec![0.0, 1.0, 3.0], vec![1.0, 3.0, 7.0])
+            .interpolation(Interpolation::CubicSpline);
+        assert!((line.lookup(2.2) - 5.4).abs() < 1e-12);
+
+        let xs: Vec<f64> = (0..=20).map(|i| i as f64 * 0.1).collect();
+        let sine = LookupTable1D::new(xs.clone(), xs.iter().map(|x| x.sin()).collect())
+            .interpolation(Interpolation::CubicSpline);
+        assert!((sine.lookup(0.4) - 0.4f64.sin()).abs() < 1e-12);
+        assert!((sine.lookup(1.23) - 1.23f64.sin()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_2d_bilinear() {
+        let mut map = LookupTable2D::new(
+            vec![0.0, 1.0],
+            vec![0.0, 10.0, 20.0],
+            dmatrix![0.0, 1.0, 2.0; 10.0, 11.0, 12.0],
+        );
+
+        map.update(0.0, &vector![0.5, 15.0]);
+        assert_eq!(map.get_output(0.0), 6.5);
+        assert_eq!(map.lookup(2.0, -5.0), 10.0);
+
+        let map = map.extrapolation(Extrapolation::Linear);
+        assert_eq!(map.lookup(2.0, -5.0), 19.5);
+    }
+
+    #[test]
+    fn test_2d_cubic_reproduces_planes() {
+        let rows = vec![0.0, 1.0, 2.0, 4.0];
+        let columns = vec![0.0, 0.5, 2.0];
+        let values = DMatrix::from_fn(4, 3, |i, j| 2.0 * rows[i] - columns[j]);
+        let map =
+            LookupTable2D::new(rows, columns, values).interpolation(Interpolation::CubicSpline);
+
+        assert!((map.lookup(2.5, 1.2) - 3.8).abs() < 1e-12);
+    }
+
+    #[test]
+    #[should_panic(expected = "There must be one value per pair of breakpoints")]
+    fn test_2d_shape_mismatch() {
+        LookupTable2D::new(vec![0.0, 1.0], vec![0.0, 1.0, 2.0], DMatrix::zeros(3, 2));
+    }
+
+    #[test]
+    fn test_csv() {
+        let curve = LookupTable1D::from_csv_str("speed,torque\n0,10\n100, 12\n\n200,11\n").unwrap();
+        assert_eq!(curve.lookup(50.0), 11.0);
+
+        let map = LookupTable2D::from_csv_str(
+            "# torque map\nrpm\\load,0,1\n1000,0.0,5.0\n2000,1.0,7.0\n",
+        )
+        .unwrap();
+        assert_eq!(map.lookup(1500.0, 0.5), 3.25);
+
+        let error = LookupTable1D::from_csv_str("0,1\n0,2\n").err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(LookupTable2D::from_csv_str("x,0,1\n0,1\n").is_err());
+        assert!(LookupTable1D::from_csv_str("0,1\n1,abc\n").is_err());
+    }
+
+    #[test]
+    fn test_table_schedules_gain() {
+        let mut schedule = LookupTable1D::new(vec![0.0, 100.0], vec![2.0, 1.0]);
+        let mut gain = ScheduledGain::<f64>::new();
+
+        schedule.update(0.0, &50.0);
+        gain.update(0.0, &(3.0, schedule.get_output(0.0)));
+
+        assert_eq!(gain.get_output(0.0), 4.5);
+    }
+}
@@ -1,7 +1,12 @@
 use std::f64;
 
 pub mod cloop;
+pub mod diagram;
 pub mod gain;
+pub mod lookup;
+pub mod noise;
+pub mod parallel;
+pub mod routing;
 pub mod series;
 
 use crate::utils::Param;
@@ -26,13 +31,23 @@ pub trait System {
     /// Returns the system's current output. Should be called after `update`ing the system.
     fn get_output(&self, time: f64) -> Self::Output;
 
+    /// Whether the output depends directly on the input of the same instant,
+    /// rather than only through the system's state.
+    ///
+    /// Systems with feedthrough must accept several `update`s at the same
+    /// instant, the last one taking effect, so that the algebraic loops they
+    /// take part in can be solved.
+    fn has_feedthrough(&self) -> bool {
+        true
+    }
+
     /// Simulates the system for a full `total_time` time units.
     fn simulate(
         &mut self,
         total_time: f64,
         max_timestep: f64,
         mut input: Param<Self::Input>,
-        callback: &mut dyn FnMut(Sample<Self::Input, Self::Output>) -> (),
+        callback: &mut dyn FnMut(Sample<Self::Input, Self::Output>),
     ) where
         Self::Input: Clone,
         Self::Output: Clone,
@@ -55,6 +70,7 @@ pub trait System {
     }
 }
 
+#[derive(Clone, Debug)]
 pub struct Sample<Input, Output> {
     pub instant: f64,
     pub input: Input,
@@ -0,0 +1,369 @@
+use std::{f64::consts::TAU, marker::PhantomData};
+
+use crate::{
+    discrete::{SampleClock, Tick},
+    system::System,
+    utils::Rng,
+};
+
+// Sources ignore their input, whose type is left free so that they can be
+// put in series with or in parallel to any system.
+
+/// Gaussian white noise, held between samples
+///
+/// Approximates continuous white noise of power spectral density `psd` by
+/// samples of variance `psd / timestep`, drawn every `timestep`.
+pub struct WhiteNoise<Input = ()> {
+    std_dev: f64,
+    rng: Rng,
+    clock: SampleClock,
+    output: f64,
+    _dummy: PhantomData<Input>,
+}
+
+impl<Input> WhiteNoise<Input> {
+    pub fn new(psd: f64, timestep: f64, seed: u64) -> Self {
+        let std_dev = (psd / timestep).sqrt();
+        let mut rng = Rng::new(seed);
+
+        Self {
+            output: std_dev * rng.normal(),
+            std_dev,
+            rng,
+            clock: SampleClock::new(timestep),
+            _dummy: PhantomData,
+        }
+    }
+}
+
+impl<Input> System for WhiteNoise<Input> {
+    type Input = Input;
+    type Output = f64;
+
+    fn update(&mut self, time: f64, _input: &Input) -> f64 {
+        if self.clock.tick(time) == Tick::Fire {
+            self.output = self.std_dev * self.rng.normal();
+        }
+        self.clock.next()
+    }
+
+    fn get_output(&self, _time: f64) -> f64 {
+        self.output
+    }
+
+    fn has_feedthrough(&self) -> bool {
+        false
+    }
+}
+
+/// Gaussian noise of standard deviation `std_dev` whose spectrum rolls off
+/// above `cutoff` Hz, as white noise through a first-order low-pass filter
+///
+/// The filter is discretized exactly, and starts in its stationary
+/// distribution.
+pub struct BandLimitedNoise<Input = ()> {
+    /// Filter pole over one timestep.
+    pole: f64,
+    /// Standard deviation of the noise driving the filter.
+    drive: f64,
+    rng: Rng,
+    clock: SampleClock,
+    output: f64,
+    _dummy: PhantomData<Input>,
+}
+
+impl<Input> BandLimitedNoise<Input> {
+    pub fn new(std_dev: f64, cutoff: f64, timestep: f64, seed: u64) -> Self {
+        let pole = (-TAU * cutoff * timestep).exp();
+        let mut rng = Rng::new(seed);
+
+        Self {
+            output: std_dev * rng.normal(),
+            drive: std_dev * (1.0 - pole * pole).sqrt(),
+            pole,
+            rng,
+            clock: SampleClock::new(timestep),
+            _dummy: PhantomData,
+        }
+    }
+}
+
+impl<Input> System for BandLimitedNoise<Input> {
+    type Input = Input;
+    type Output = f64;
+
+    fn update(&mut self, time: f64, _input: &Input) -> f64 {
+        if self.clock.tick(time) == Tick::Fire {
+            self.output = self.pole * self.output + self.drive * self.rng.normal();
+        }
+        self.clock.next()
+    }
+
+    fn get_output(&self, _time: f64) -> f64 {
+        self.output
+    }
+
+    fn has_feedthrough(&self) -> bool {
+        false
+    }
+}
+
+/// Feedback taps of maximal-length linear feedback shift registers, by order.
+const PRBS_TAPS: [&[u32]; 15] = [
+    &[2, 1],
+    &[3, 2],
+    &[4, 3],
+    &[5, 3],
+    &[6, 5],
+    &[7, 6],
+    &[8, 6, 5, 4],
+    &[9, 5],
+    &[10, 7],
+    &[11, 9],
+    &[12, 6, 4, 1],
+    &[13, 4, 3, 1],
+    &[14, 5, 3, 1],
+    &[15, 14],
+    &[16, 15, 13, 4],
+];
+
+/// A pseudo-random binary sequence switching between `-amplitude` and
+/// `amplitude`, with a new bit every `bit_period`
+///
+/// The sequence comes from a maximal-length shift register of `order` bits,
+/// so it repeats every `2^order - 1` bits and excites all frequencies up to
+/// about `1 / (2 * bit_period)` evenly. The seed picks where it starts.
+pub struct Prbs<Input = ()> {
+    register: u32,
+    order: u32,
+    amplitude: f64,
+    clock: SampleClock,
+    _dummy: PhantomData<Input>,
+}
+
+impl<Input> Prbs<Input> {
+    pub fn new(order: u32, bit_period: f64, amplitude: f64, seed: u64) -> Self {
+        assert!(
+            (2..=16).contains(&order),
+            "PRBS order must be between 2 and 16"
+        );
+        let period = (1u64 << order) - 1;
+
+        Self {
+            register: (seed % period + 1) as u32,
+            order,
+            amplitude,
+            clock: SampleClock::new(bit_period),
+            _dummy: PhantomData,
+        }
+    }
+
+    /// Number of bits after which the sequence repeats.
+    pub fn period(&self) -> usize {
+        (1 << self.order) - 1
+    }
+}
+
+impl<Input> System for Prbs<Input> {
+    type Input = Input;
+    type Output = f64;
+
+    fn update(&mut self, time: f64, _input: &Input) -> f64 {
+        if self.clock.tick(time) == Tick::Fire {
+            let taps = PRBS_TAPS[self.order as usize - 2];
+            let feedback = taps
+                .iter()
+                .fold(0, |bit, tap| bit ^ (self.register >> (tap - 1)));
+            self.register = ((self.register << 1) | (feedback & 1)) & ((1 << self.order) - 1);
+        }
+        self.clock.next()
+    }
+
+    fn get_output(&self, _time: f64) -> f64 {
+        if self.register & 1 == 1 {
+            self.amplitude
+        } else {
+            -self.amplitude
+        }
+    }
+
+    fn has_feedthrough(&self) -> bool {
+        false
+    }
+}
+
+/// A random walk, sampling Brownian motion of the given `intensity` (the
+/// growth rate of its variance) every `timestep`
+pub struct RandomWalk<Input = ()> {
+    step_std_dev: f64,
+    rng: Rng,
+    clock: SampleClock,
+    output: f64,
+    _dummy: PhantomData<Input>,
+}
+
+impl<Input> RandomWalk<Input> {
+    pub fn new(intensity: f64, timestep: f64, seed: u64) -> Self {
+        Self {
+            step_std_dev: (intensity * timestep).sqrt(),
+            rng: Rng::new(seed),
+            clock: SampleClock::new(timestep),
+            output: 0.0,
+            _dummy: PhantomData,
+        }
+    }
+
+    /// Starts the walk from `value` instead of zero.
+    pub fn initial_value(mut self, value: f64) -> Self {
+        self.output = value;
+        self
+    }
+}
+
+impl<Input> System for RandomWalk<Input> {
+    type Input = Input;
+    type Output = f64;
+
+    fn update(&mut self, time: f64, _input: &Input) -> f64 {
+        if self.clock.tick(time) == Tick::Fire {
+            self.output += self.step_std_dev * self.rng.normal();
+        }
+        self.clock.next()
+    }
+
+    fn get_output(&self, _time: f64) -> f64 {
+        self.output
+    }
+
+    fn has_feedthrough(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        system::{
+            diagram::{DiagramBuilder, Node},
+            gain::Gain,
+            parallel::ParallelSystem,
+            series::SeriesSystem,
+        },
+        utils::Param,
+    };
+    use nalgebra::dvector;
+
+    fn record<Sys: System<Input = (), Output = f64>>(
+        sys: &mut Sys,
+        total: f64,
+        dt: f64,
+    ) -> Vec<f64> {
+        let mut out = vec![];
+        sys.simulate(total, dt, Param::new(()), &mut |x| out.push(x.output));
+        out
+    }
+
+    fn variance(samples: &[f64]) -> f64 {
+        let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+        samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / samples.len() as f64
+    }
+
+    #[test]
+    fn test_white_noise_power() {
+        let samples = record(&mut WhiteNoise::new(0.02, 0.01, 1), 200.0, 1.0);
+
+        assert_eq!(samples.len(), 20_000);
+        assert!((variance(&samples) - 2.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_seeded_sources_are_reproducible() {
+        let a = record(&mut BandLimitedNoise::new(1.0, 5.0, 0.01, 3), 1.0, 1.0);
+        let b = record(&mut BandLimitedNoise::new(1.0, 5.0, 0.01, 3), 1.0, 1.0);
+        let c = record(&mut BandLimitedNoise::new(1.0, 5.0, 0.01, 4), 1.0, 1.0);
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn test_band_limited_correlation() {
+        let samples = record(&mut BandLimitedNoise::new(0.5, 2.0, 0.01, 9), 500.0, 1.0);
+
+        let n = samples.len();
+        let lag1 = samples.windows(2).map(|w| w[0] * w[1]).sum::<f64>() / (n - 1) as f64;
+        let pole = (-TAU * 2.0 * 0.01f64).exp();
+
+        assert!((variance(&samples) - 0.25).abs() < 0.02);
+        assert!((lag1 / 0.25 - pole).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_prbs_is_periodic_and_balanced() {
+        let mut prbs = Prbs::new(5, 0.1, 2.0, 11);
+        assert_eq!(prbs.period(), 31);
+
+        let bits = record(&mut prbs, 6.2, 1.0);
+        assert_eq!(bits.len(), 62);
+        assert_eq!(bits[..31], bits[31..]);
+        assert_eq!(bits[..31].iter().filter(|&&b| b == 2.0).count(), 16);
+        assert!(bits.iter().all(|b| b.abs() == 2.0));
+    }
+
+    #[test]
+    fn test_random_walk_increments() {
+        let walk = record(
+            &mut RandomWalk::new(4.0, 0.01, 5).initial_value(10.0),
+            100.0,
+            1.0,
+        );
+        let increments: Vec<f64> = walk.windows(2).map(|w| w[1] - w[0]).collect();
+
+        assert!((variance(&increments) - 0.04).abs() < 0.003);
+    }
+
+    #[test]
+    fn test_noise_as_input_and_disturbance() {
+        // As the input of a gain, whatever the simulation input
+        let mut driven = SeriesSystem::new(WhiteNoise::<f64>::new(1.0, 0.1, 2), Gain::new(2.0));
+        let mut out = vec![];
+        driven.simulate(0.5, 0.1, Param::new(0.0), &mut |x| out.push(x.output));
+
+        let noise = record(&mut WhiteNoise::new(1.0, 0.1, 2), 0.5, 0.1);
+        assert_eq!(out, noise.iter().map(|n| 2.0 * n).collect::<Vec<_>>());
+
+        // Added to the output of a gain
+        let mut disturbed =
+            ParallelSystem::new(Gain::<f64>::new(1.0), Prbs::<f64>::new(3, 0.1, 0.5, 1));
+        disturbed.update(0.0, &1.0);
+        assert_eq!((disturbed.get_output(0.0) - 1.0).abs(), 0.5);
+    }
+
+    #[test]
+    fn test_noise_in_diagram() {
+        let mut diagram = DiagramBuilder::new()
+            .node(
+                "walk",
+                Node::new(RandomWalk::<()>::new(1.0, 0.1, 8)).output("y", 1),
+            )
+            .node(
+                "gain",
+                Node::new(Gain::<f64>::new(-1.0))
+                    .input("u", 1)
+                    .output("y", 1),
+            )
+            .connect("walk.y", "gain.u")
+            .output("y", "gain.y")
+            .build()
+            .unwrap();
+
+        let mut out = vec![];
+        diagram.simulate(1.0, 1.0, Param::new(dvector![]), &mut |x| {
+            out.push(x.output[0])
+        });
+
+        let walk = record(&mut RandomWalk::new(1.0, 0.1, 8), 1.0, 1.0);
+        assert_eq!(out, walk.iter().map(|w| -w).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,81 @@
+use std::{marker::PhantomData, ops::Add};
+
+use crate::system::System;
+
+/// Describes a couple of systems receiving the same input, whose outputs are summed.
+///
+///             +----------+
+///         +---+  first   +---+
+///         |   +----------+   |
+///  INPUT -+                 (+)--- OUTPUT
+///         |   +----------+   |
+///         +---+  second  +---+
+///             +----------+
+///
+pub struct ParallelSystem<Input, Output, First, Second>
+where
+    First: System<Input = Input, Output = Output>,
+    Second: System<Input = Input, Output = Output>,
+{
+    first: First,
+    second: Second,
+    _dummy: PhantomData<(Input, Output)>,
+}
+
+impl<Input, Output, First, Second> ParallelSystem<Input, Output, First, Second>
+where
+    First: System<Input = Input, Output = Output>,
+    Second: System<Input = Input, Output = Output>,
+{
+    pub fn new(first: First, second: Second) -> Self {
+        Self {
+            first,
+            second,
+            _dummy: PhantomData,
+        }
+    }
+}
+
+impl<Input, Output, First, Second> System for ParallelSystem<Input, Output, First, Second>
+where
+    First: System<Input = Input, Output = Output>,
+    Second: System<Input = Input, Output = Output>,
+    Output: Add<Output = Output>,
+{
+    type Input = Input;
+    type Output = Output;
+
+    fn update(&mut self, time: f64, input: &Input) -> f64 {
+        let next1 = self.first.update(time, input);
+        let next2 = self.second.update(time, input);
+
+        next1.min(next2)
+    }
+
+    fn get_output(&self, time: f64) -> Output {
+        self.first.get_output(time) + self.second.get_output(time)
+    }
+
+    fn has_feedthrough(&self) -> bool {
+        self.first.has_feedthrough() || self.second.has_feedthrough()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        system::{UnitSystem, gain::Gain},
+        utils::Param,
+    };
+
+    #[test]
+    fn test_parallel_sums_outputs() {
+        let mut sys = ParallelSystem::new(Gain::<f64>::new(2.0), UnitSystem::default());
+        let mut out = vec![];
+
+        sys.simulate(0.2, 0.1, Param::new(3.0), &mut |x| out.push(x.output));
+
+        assert_eq!(out, vec![9.0, 9.0]);
+    }
+}
@@ -0,0 +1,487 @@
+use std::marker::PhantomData;
+
+use nalgebra::{DVector, SVector};
+
+use crate::system::System;
+
+/// A signal made of a flat list of `f64` components.
+pub trait Signal: Clone {
+    /// Number of components, when it is fixed by the type.
+    const SIZE: Option<usize>;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Appends the components of the signal to `out`.
+    fn write(&self, out: &mut Vec<f64>);
+
+    /// Builds a signal from exactly its components.
+    fn read(components: &[f64]) -> Self;
+}
+
+/// The signal of blocks without inputs, such as sources.
+impl Signal for () {
+    const SIZE: Option<usize> = Some(0);
+
+    fn len(&self) -> usize {
+        0
+    }
+
+    fn write(&self, _out: &mut Vec<f64>) {}
+
+    fn read(components: &[f64]) -> Self {
+        assert!(components.is_empty(), "An empty signal has no component");
+    }
+}
+
+impl Signal for f64 {
+    const SIZE: Option<usize> = Some(1);
+
+    fn len(&self) -> usize {
+        1
+    }
+
+    fn write(&self, out: &mut Vec<f64>) {
+        out.push(*self);
+    }
+
+    fn read(components: &[f64]) -> Self {
+        assert_eq!(
+            components.len(),
+            1,
+            "A scalar signal has a single component"
+        );
+        components[0]
+    }
+}
+
+impl<const N: usize> Signal for SVector<f64, N> {
+    const SIZE: Option<usize> = Some(N);
+
+    fn len(&self) -> usize {
+        N
+    }
+
+    fn write(&self, out: &mut Vec<f64>) {
+        out.extend(self.iter());
+    }
+
+    fn read(components: &[f64]) -> Self {
+        SVector::from_column_slice(components)
+    }
+}
+
+impl Signal for DVector<f64> {
+    const SIZE: Option<usize> = None;
+
+    fn len(&self) -> usize {
+        self.nrows()
+    }
+
+    fn write(&self, out: &mut Vec<f64>) {
+        out.extend(self.iter());
+    }
+
+    fn read(components: &[f64]) -> Self {
+        DVector::from_column_slice(components)
+    }
+}
+
+/// A tuple of `Signal`s, routed together by `Mux` and `Demux`.
+pub trait SignalTuple: Clone {
+    /// Number of components of each element, when fixed by its type.
+    fn sizes() -> Vec<Option<usize>>;
+
+    fn write(&self, out: &mut Vec<f64>);
+
+    /// Builds the tuple from its components, where element `i` has `sizes[i]` components.
+    fn read(components: &[f64], sizes: &[usize]) -> Self;
+}
+
+macro_rules! signal_tuple {
+    ($($name:ident $index:tt),+) => {
+        impl<$($name: Signal),+> SignalTuple for ($($name,)+) {
+            fn sizes() -> Vec<Option<usize>> {
+                vec![$($name::SIZE),+]
+            }
+
+            fn write(&self, out: &mut Vec<f64>) {
+                $(self.$index.write(out);)+
+            }
+
+            // The offset is advanced past the last element too
+            #[allow(unused_assignments)]
+            fn read(components: &[f64], sizes: &[usize]) -> Self {
+                let mut offset = 0;
+                ($({
+                    let part = $name::read(&components[offset..offset + sizes[$index]]);
+                    offset += sizes[$index];
+                    part
+                },)+)
+            }
+        }
+    };
+}
+
+signal_tuple!(A 0, B 1);
+signal_tuple!(A 0, B 1, C 2);
+signal_tuple!(A 0, B 1, C 2, D 3);
+signal_tuple!(A 0, B 1, C 2, D 3, E 4);
+
+fn static_sizes<Parts: SignalTuple>() -> Vec<usize> {
+    Parts::sizes()
+        .into_iter()
+        .map(|s| s.expect("Dynamically sized parts need explicit sizes"))
+        .collect()
+}
+
+fn check_sizes<Parts: SignalTuple>(sizes: &[usize]) {
+    let expected = Parts::sizes();
+    assert_eq!(
+        sizes.len(),
+        expected.len(),
+        "There must be one size per part"
+    );
+    for (size, expected) in sizes.iter().zip(expected) {
+        if let Some(expected) = expected {
+            assert_eq!(*size, expected, "Size does not match the part's type");
+        }
+    }
+}
+
+fn check_whole<Whole: Signal>(total: usize) {
+    if let Some(size) = Whole::SIZE {
+        assert_eq!(size, total, "The parts do not add up to the whole signal");
+    }
+}
+
+/// Stacks a tuple of signals into a single vector.
+///
+///   PART 0 ---+-------+
+///             |       |
+///   PART 1 ---+  mux  +--- WHOLE
+///             |       |
+///   PART 2 ---+-------+
+///
+pub struct Mux<Parts, Whole> {
+    output: Whole,
+    buffer: Vec<f64>,
+    _dummy: PhantomData<Parts>,
+}
+
+impl<Parts: SignalTuple, Whole: Signal> Mux<Parts, Whole> {
+    /// Creates a mux whose parts all have a size fixed by their type.
+    pub fn new() -> Self {
+        Self::with_sizes(&static_sizes::<Parts>())
+    }
+
+    /// Creates a mux where part `i` has `sizes[i]` components.
+    pub fn with_sizes(sizes: &[usize]) -> Self {
+        check_sizes::<Parts>(sizes);
+        let total = sizes.iter().sum();
+        check_whole::<Whole>(total);
+
+        Self {
+            output: Whole::read(&vec![0.0; total]),
+            buffer: Vec::with_capacity(total),
+            _dummy: PhantomData,
+        }
+    }
+}
+
+impl<Parts: SignalTuple, Whole: Signal> Default for Mux<Parts, Whole> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Parts: SignalTuple, Whole: Signal> System for Mux<Parts, Whole> {
+    type Input = Parts;
+    type Output = Whole;
+
+    fn update(&mut self, _time: f64, input: &Parts) -> f64 {
+        self.buffer.clear();
+        input.write(&mut self.buffer);
+        self.output = Whole::read(&self.buffer);
+        f64::INFINITY
+    }
+
+    fn get_output(&self, _time: f64) -> Whole {
+        self.output.clone()
+    }
+}
+
+/// Splits a vector into a tuple of signals.
+///
+///                     +---------+--- PART 0
+///                     |         |
+///   WHOLE ------------+  demux  +--- PART 1
+///                     |         |
+///                     +---------+--- PART 2
+///
+pub struct Demux<Whole, Parts> {
+    sizes: Vec<usize>,
+    output: Parts,
+    buffer: Vec<f64>,
+    _dummy: PhantomData<Whole>,
+}
+
+impl<Whole: Signal, Parts: SignalTuple> Demux<Whole, Parts> {
+    /// Creates a demux whose parts all have a size fixed by their type.
+    pub fn new() -> Self {
+        Self::with_sizes(&static_sizes::<Parts>())
+    }
+
+    /// Creates a demux where part `i` has `sizes[i]` components.
+    pub fn with_sizes(sizes: &[usize]) -> Self {
+        check_sizes::<Parts>(sizes);
+        let total = sizes.iter().sum();
+        check_whole::<Whole>(total);
+
+        Self {
+            output: Parts::read(&vec![0.0; total], sizes),
+            sizes: sizes.to_vec(),
+            buffer: Vec::with_capacity(total),
+            _dummy: PhantomData,
+        }
+    }
+}
+
+impl<Whole: Signal, Parts: SignalTuple> Default for Demux<Whole, Parts> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Whole: Signal, Parts: SignalTuple> System for Demux<Whole, Parts> {
+    type Input = Whole;
+    type Output = Parts;
+
+    fn update(&mut self, _time: f64, input: &Whole) -> f64 {
+        self.buffer.clear();
+        input.write(&mut self.buffer);
+        assert_eq!(
+            self.buffer.len(),
+            self.sizes.iter().sum::<usize>(),
+            "Input does not match the demux sizes"
+        );
+        self.output = Parts::read(&self.buffer, &self.sizes);
+        f64::INFINITY
+    }
+
+    fn get_output(&self, _time: f64) -> Parts {
+        self.output.clone()
+    }
+}
+
+/// Picks components of a signal, in the given order, possibly repeating them.
+pub struct Selector<Input, Output> {
+    indices: Vec<usize>,
+    output: Output,
+    buffer: Vec<f64>,
+    _dummy: PhantomData<Input>,
+}
+
+impl<Input: Signal, Output: Signal> Selector<Input, Output> {
+    pub fn new(indices: &[usize]) -> Self {
+        check_whole::<Output>(indices.len());
+        if let Some(size) = Input::SIZE {
+            assert!(
+                indices.iter().all(|&i| i < size),
+                "Selected index is out of the input's bounds"
+            );
+        }
+
+        Self {
+            output: Output::read(&vec![0.0; indices.len()]),
+            indices: indices.to_vec(),
+            buffer: Vec::new(),
+            _dummy: PhantomData,
+        }
+    }
+}
+
+impl<Input: Signal, Output: Signal> System for Selector<Input, Output> {
+    type Input = Input;
+    type Output = Output;
+
+    fn update(&mut self, _time: f64, input: &Input) -> f64 {
+        self.buffer.clear();
+        input.write(&mut self.buffer);
+        assert!(
+            self.indices.iter().all(|&i| i < self.buffer.len()),
+            "Selected index is out of the input's bounds"
+        );
+        let selected: Vec<f64> = self.indices.iter().map(|&i| self.buffer[i]).collect();
+        self.output = Output::read(&selected);
+        f64::INFINITY
+    }
+
+    fn get_output(&self, _time: f64) -> Output {
+        self.output.clone()
+    }
+}
+
+/// Runs a system on signals of other types with the same components, e.g. a
+/// `StateSpace` model as a single-input single-output block on `f64`.
+///
+///  INPUT ---[ system ]--- OUTPUT
+///
+pub struct Adapter<Sys, Input, Output> {
+    system: Sys,
+    buffer: Vec<f64>,
+    _dummy: PhantomData<(Input, Output)>,
+}
+
+impl<Sys, Input, Output> Adapter<Sys, Input, Output>
+where
+    Sys: System,
+    Sys::Input: Signal,
+    Sys::Output: Signal,
+    Input: Signal,
+    Output: Signal,
+{
+    pub fn new(system: Sys) -> Self {
+        Self {
+            system,
+            buffer: Vec::new(),
+            _dummy: PhantomData,
+        }
+    }
+}
+
+impl<Sys> Adapter<Sys, f64, f64>
+where
+    Sys: System,
+    Sys::Input: Signal,
+    Sys::Output: Signal,
+{
+    /// Adapts a system with one input and one output to `f64` signals.
+    pub fn siso(system: Sys) -> Self {
+        Self::new(system)
+    }
+}
+
+impl<Sys, Input, Output> System for Adapter<Sys, Input, Output>
+where
+    Sys: System,
+    Sys::Input: Signal,
+    Sys::Output: Signal,
+    Input: Signal,
+    Output: Signal,
+{
+    type Input = Input;
+    type Output = Output;
+
+    fn update(&mut self, time: f64, input: &Input) -> f64 {
+        self.buffer.clear();
+        input.write(&mut self.buffer);
+        self.system.update(time, &Sys::Input::read(&self.buffer))
+    }
+
+    fn get_output(&self, time: f64) -> Output {
+        let mut components = Vec::new();
+        self.system.get_output(time).write(&mut components);
+        Output::read(&components)
+    }
+
+    fn has_feedthrough(&self) -> bool {
+        self.system.has_feedthrough()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        system::{UnitSystem, cloop::ClosedLoop, gain::Gain, series::SeriesSystem},
+        utils::Param,
+    };
+    use nalgebra::{Const, Owned, Vector, dvector, vector};
+
+    pub type VecN<const N: usize, S = Owned<f64, Const<N>, Const<1>>> = Vector<f64, Const<N>, S>;
+
+    #[test]
+    fn test_mux_static() {
+        let mut mux = Mux::<(f64, VecN<2>), VecN<3>>::new();
+
+        assert_eq!(mux.get_output(0.0), VecN::<3>::zeros());
+        mux.update(0.0, &(1.0, vector![2.0, 3.0]));
+        assert_eq!(mux.get_output(0.0), vector![1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn test_demux_dynamic() {
+        let mut demux = Demux::<DVector<f64>, (DVector<f64>, f64)>::with_sizes(&[2, 1]);
+
+        demux.update(0.0, &dvector![1.0, 2.0, 3.0]);
+        assert_eq!(demux.get_output(0.0), (dvector![1.0, 2.0], 3.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "The parts do not add up to the whole signal")]
+    fn test_mux_size_mismatch() {
+        Mux::<(f64, VecN<2>), VecN<4>>::new();
+    }
+
+    #[test]
+    #[should_panic(expected = "Dynamically sized parts need explicit sizes")]
+    fn test_demux_dynamic_needs_sizes() {
+        Demux::<DVector<f64>, (DVector<f64>, f64)>::new();
+    }
+
+    #[test]
+    fn test_selector() {
+        let mut selector = Selector::<VecN<3>, VecN<2>>::new(&[2, 0]);
+
+        selector.update(0.0, &vector![1.0, 2.0, 3.0]);
+        assert_eq!(selector.get_output(0.0), vector![3.0, 1.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Selected index is out of the input's bounds")]
+    fn test_selector_dynamic_input_too_short() {
+        let mut selector = Selector::<DVector<f64>, VecN<2>>::new(&[2, 0]);
+        selector.update(0.0, &DVector::from_vec(vec![1.0, 2.0]));
+    }
+
+    #[test]
+    fn test_adapter() {
+        let mut siso = Adapter::siso(UnitSystem::<DVector<f64>>::default());
+
+        siso.update(0.0, &2.0);
+        assert_eq!(siso.get_output(0.0), 2.0);
+    }
+
+    #[test]
+    fn test_demux_then_mux_in_series() {
+        let split = Demux::<VecN<3>, (VecN<2>, f64)>::new();
+        let join = Mux::<(VecN<2>, f64), VecN<3>>::new();
+        let mut series = SeriesSystem::new(split, join);
+
+        let mut out = vec![];
+        series.simulate(0.2, 0.1, Param::new(vector![1.0, 2.0, 3.0]), &mut |x| {
+            out.push(x.output)
+        });
+
+        assert_eq!(out, vec![vector![1.0, 2.0, 3.0]; 2]);
+    }
+
+    #[test]
+    fn test_selector_in_closed_loop() {
+        // The first component of the output is fed back to both inputs
+        let forward = SeriesSystem::new(Gain::<VecN<2>>::new(0.5), UnitSystem::default());
+        let feedback = Selector::<VecN<2>, VecN<2>>::new(&[0, 0]);
+        let mut cloop = ClosedLoop::new(forward, feedback);
+
+        let mut out = vec![];
+        cloop.simulate(0.3, 0.1, Param::new(vector![1.0, 1.0]), &mut |x| {
+            out.push(x.output)
+        });
+
+        assert_eq!(out[2], vector![0.375, 0.375]);
+    }
+}
@@ -53,6 +53,10 @@ where
     fn get_output(&self, time: f64) -> Self::Output {
         self.second.get_output(time)
     }
+
+    fn has_feedthrough(&self) -> bool {
+        self.first.has_feedthrough() && self.second.has_feedthrough()
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,26 @@
+use std::io;
+
+pub(crate) fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// The lines of comma-separated `text`, without blank lines and lines
+/// starting with `#`.
+pub(crate) fn csv_lines(text: &str) -> impl Iterator<Item = &str> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+}
+
+/// Parses the cells of a CSV line, where empty cells are `None`.
+pub(crate) fn parse_cells(line: &str) -> io::Result<Vec<Option<f64>>> {
+    line.split(',')
+        .map(|cell| match cell.trim() {
+            "" => Ok(None),
+            cell => cell
+                .parse()
+                .map(Some)
+                .map_err(|_| invalid_data(format!("`{cell}` is not a number"))),
+        })
+        .collect()
+}
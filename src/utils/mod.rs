@@ -1,3 +1,9 @@
+mod csv;
 mod param;
+mod random;
 
-pub use self::param::{Param, ParamWith};
+pub(crate) use self::csv::{csv_lines, invalid_data, parse_cells};
+pub use self::{
+    param::{Param, ParamWith},
+    random::Rng,
+};
@@ -58,6 +58,16 @@ impl<V> Param<V> {
         }
     }
 
+    /// Returns the value the parameter holds at instant `time`, without
+    /// advancing it.
+    pub fn at(&self, time: f64) -> &V {
+        self.steps
+            .iter()
+            .take_while(|(t, _)| *t <= time)
+            .last()
+            .map_or(&self.value, |(_, v)| v)
+    }
+
     pub fn with(self, callback: fn(V) -> V) -> ParamWith<V>
     where
         V: Clone,
@@ -149,6 +159,17 @@ mod tests {
         assert_eq!(*param, 1.0);
     }
 
+    #[test]
+    fn test_param_at() {
+        let param = Param::<f64>::new(0.0).step(2.0, 5.0).step(1.0, 3.0);
+
+        assert_eq!(*param.at(0.0), 0.0);
+        assert_eq!(*param.at(3.0), 1.0);
+        assert_eq!(*param.at(4.9), 1.0);
+        assert_eq!(*param.at(5.0), 2.0);
+        assert_eq!(*param, 0.0);
+    }
+
     #[test]
     fn test_param_with() {
         let mut param = Param::<f64>::new(2.0).step(1.0, 5.0).with(|x| 1. / x);